@@ -1,8 +1,10 @@
 use clap::{self, Arg, Command, ValueHint};
 use indicatif::MultiProgress;
 use std::sync::Arc;
+use std::time::Duration;
 
 pub struct Conf {
+    options: Arc<imd::DiscoveryOptions>,
     target_machines: Vec<imd::TargetMachine>,
     user: Arc<imd::IMDUser>,
     wordlist: Arc<String>,
@@ -50,18 +52,126 @@ impl Conf {
         // Get the wordlist, which is either user-provided or a default value
         let wordlist = Arc::new(matches.get_one::<String>("wordlist").unwrap().to_string());
 
+        // Get the port scanner settings, which fall back to nmap and the native scanner's defaults
+        let scanner = *matches
+            .get_one::<imd::scanner::PortScanner>("scanner")
+            .unwrap();
+        let native_scanner = imd::scanner::NativeScanner::new(
+            *matches.get_one::<usize>("concurrency").unwrap(),
+            matches.get_one::<Vec<u16>>("ports").unwrap().clone(),
+            Duration::from_millis(*matches.get_one::<u64>("timeout").unwrap()),
+        );
+
+        // Get the largest file imd will download from an anonymous FTP server
+        let ftp_max_size = *matches.get_one::<u64>("ftp_max_size").unwrap();
+
+        // Get what to do with hostnames imd discovers on its own
+        let hostname_mode = *matches
+            .get_one::<imd::hostnames::HostnameMode>("discovered_hostnames")
+            .unwrap();
+
+        // Get the tool used to enumerate SMB shares
+        let smb_tool = *matches.get_one::<imd::smb::SmbTool>("smb_tool").unwrap();
+
+        // Testing SMB shares for write access changes the state of the target, so it only happens when asked for
+        let smb_write_test = matches.get_flag("smb_write_test");
+
+        // Get the usernames to try against SMTP, from a file if the user gave one
+        let mail_usernames = match matches.get_one::<String>("mail_usernames") {
+            Some(file) => std::fs::read_to_string(file)
+                .unwrap_or_default()
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty())
+                .map(str::to_string)
+                .collect(),
+            None => vec![],
+        };
+
+        // Mounting NFS exports changes the state of this machine, so it only happens when asked for
+        let mount_nfs = matches.get_flag("mount_nfs");
+
+        // Get the SNMP communities to try
+        let snmp_communities = matches
+            .get_many::<String>("snmp_communities")
+            .unwrap()
+            .cloned()
+            .collect();
+
+        // Get the subdomains to fuzz virtual hosts with, which are skipped if the file doesn't exist
+        let vhost_wordlist = matches
+            .get_one::<String>("vhost_wordlist")
+            .unwrap()
+            .to_string();
+
+        // Get the rules that pick content discovery extensions and extra wordlists from each web port's fingerprint
+        let web_extension_rules = matches
+            .get_one::<Vec<imd::content::ExtensionRule>>("web_extension_rules")
+            .unwrap()
+            .clone();
+
+        // Get the follow-ups to run against the web technologies imd recognizes
+        let web_follow_ups = matches
+            .get_one::<Vec<imd::webtech::FollowUp>>("web_follow_ups")
+            .unwrap()
+            .clone();
+
+        // Get the largest exposed backup or dotfile imd will save from a web server
+        let web_loot_max_size = *matches.get_one::<u64>("web_loot_max_size").unwrap();
+
+        // Get the content discovery scanner and the limits it runs within
+        let web_scanner = *matches
+            .get_one::<imd::content::WebScanner>("web_scanner")
+            .unwrap();
+        let web_threads = *matches.get_one::<usize>("web_threads").unwrap();
+        let web_time_limit =
+            Duration::from_secs(60 * *matches.get_one::<u64>("web_time_limit").unwrap());
+
+        // Get the web vuln scanner, and the local nuclei templates if the user has them
+        let vuln_scanner = *matches
+            .get_one::<imd::vulns::VulnScanner>("vuln_scanner")
+            .unwrap();
+        let nuclei_templates = matches.get_one::<String>("nuclei_templates").cloned();
+
+        let options = Arc::new(
+            imd::DiscoveryOptions::default()
+                .with_ftp_max_size(ftp_max_size)
+                .with_hostname_mode(hostname_mode)
+                .with_mail_usernames(mail_usernames)
+                .with_mount_nfs(mount_nfs)
+                .with_nuclei_templates(nuclei_templates)
+                .with_scanner(scanner)
+                .with_smb_tool(smb_tool)
+                .with_smb_write_test(smb_write_test)
+                .with_snmp_communities(snmp_communities)
+                .with_vhost_wordlist(vhost_wordlist)
+                .with_vuln_scanner(vuln_scanner)
+                .with_web_extension_rules(web_extension_rules)
+                .with_web_follow_ups(web_follow_ups)
+                .with_web_loot_max_size(web_loot_max_size)
+                .with_web_scanner(web_scanner)
+                .with_web_threads(web_threads)
+                .with_web_time_limit(web_time_limit)
+                .with_native_scanner(native_scanner),
+        );
+
         bar.finish_with_message(format!(
             "Starting discovery on {} target machines",
             target_machines.len()
         ));
 
         Conf {
+            options,
             target_machines,
             user,
             wordlist,
         }
     }
 
+    pub fn options(&self) -> Arc<imd::DiscoveryOptions> {
+        self.options.clone()
+    }
+
     pub fn target_machines(&self) -> &Vec<imd::TargetMachine> {
         &self.target_machines
     }
@@ -82,6 +192,98 @@ fn cli() -> Command {
         .about(clap::crate_description!());
 
     app.arg(
+        Arg::new("concurrency")
+            .long("concurrency")
+            .value_name("CONNECTIONS")
+            .num_args(1)
+            .default_value("200")
+            .value_parser(clap::value_parser!(usize))
+            .help("Number of simultaneous connections the native port scanner makes"),
+    )
+    .arg(
+        Arg::new("discovered_hostnames")
+            .long("discovered-hostnames")
+            .value_name("MODE")
+            .num_args(1)
+            .default_value("auto")
+            .value_parser(clap::builder::ValueParser::new(imd::hostnames::HostnameMode::new))
+            .help("What to do with hostnames found in web redirects and TLS certificates: auto adds them to /etc/hosts and scans them, prompt asks first, ignore only records them"),
+    )
+    .arg(
+        Arg::new("ftp_max_size")
+            .long("ftp-max-size")
+            .value_name("BYTES")
+            .num_args(1)
+            .default_value("1048576")
+            .value_parser(clap::value_parser!(u64))
+            .help("Largest file to download from anonymous FTP servers"),
+    )
+    .arg(
+        Arg::new("mail_usernames")
+            .long("mail-usernames")
+            .value_name("USERNAMES_FILE")
+            .num_args(1)
+            .value_parser(clap::builder::ValueParser::new(imd::wrap_wordlist_parse))
+            .help("File of usernames (one per line) to check with SMTP VRFY, EXPN and RCPT TO, instead of a short built-in list"),
+    )
+    .arg(
+        Arg::new("mount_nfs")
+            .long("mount-nfs")
+            .action(clap::ArgAction::SetTrue)
+            .help("Mount NFS exports open to this machine read-only to list their contents and ownership"),
+    )
+    .arg(
+        Arg::new("nuclei_templates")
+            .long("nuclei-templates")
+            .value_name("DIRECTORY")
+            .num_args(1)
+            .value_hint(ValueHint::DirPath)
+            .help("Local nuclei templates directory, so nuclei works without internet access"),
+    )
+    .arg(
+        Arg::new("ports")
+            .long("ports")
+            .value_name("PORTS")
+            .num_args(1)
+            .default_value(imd::scanner::DEFAULT_PORTS)
+            .hide_default_value(true)
+            .value_parser(clap::builder::ValueParser::new(imd::scanner::parse_port_list))
+            .help("Ports for the native port scanner's common port scan: E.G. 22,80,8000-8100"),
+    )
+    .arg(
+        Arg::new("scanner")
+            .long("scanner")
+            .value_name("SCANNER")
+            .num_args(1)
+            .default_value("nmap")
+            .value_parser(clap::builder::ValueParser::new(imd::scanner::PortScanner::new))
+            .help("Port scanner to use, either nmap or native. The native scanner is used automatically if nmap is not installed"),
+    )
+    .arg(
+        Arg::new("smb_tool")
+            .long("smb-tool")
+            .value_name("TOOL")
+            .num_args(1)
+            .default_value("smbclient")
+            .value_parser(clap::builder::ValueParser::new(imd::smb::SmbTool::new))
+            .help("Tool used to enumerate SMB shares: smbclient, smbmap or enum4linux-ng"),
+    )
+    .arg(
+        Arg::new("smb_write_test")
+            .long("smb-write-test")
+            .action(clap::ArgAction::SetTrue)
+            .help("Make and remove a directory in each SMB disk share smbclient can reach to see if it is writable"),
+    )
+    .arg(
+        Arg::new("snmp_communities")
+            .long("snmp-communities")
+            .value_name("COMMUNITIES")
+            .num_args(1)
+            .value_delimiter(',')
+            .default_value(imd::snmp::DEFAULT_COMMUNITIES)
+            .help("Comma separated SNMP communities to try when SNMP is available"),
+    )
+    .arg(
         Arg::new("targets")
             .short('t')
             .value_name("TARGET_MACHINES")
@@ -91,6 +293,88 @@ fn cli() -> Command {
             .value_parser(clap::builder::ValueParser::new(imd::CLITarget::new))
             .help("Target machine(s)'s IP address(es), optionally with =hostname: E.G. 127.0.0.1 OR 127.0.0.1=myhostname")
     )
+    .arg(
+        Arg::new("timeout")
+            .long("timeout")
+            .value_name("MILLISECONDS")
+            .num_args(1)
+            .default_value("1000")
+            .value_parser(clap::value_parser!(u64))
+            .help("How long imd's native network checks wait on each connection"),
+    )
+    .arg(
+        Arg::new("vhost_wordlist")
+            .long("vhost-wordlist")
+            .value_name("WORDLIST")
+            .num_args(1)
+            .value_hint(ValueHint::FilePath)
+            .default_value(imd::vhosts::DEFAULT_WORDLIST)
+            .help("Subdomains to try as virtual hosts on web ports when the target has a hostname"),
+    )
+    .arg(
+        Arg::new("vuln_scanner")
+            .long("vuln-scanner")
+            .value_name("SCANNER")
+            .num_args(1)
+            .default_value("nikto")
+            .value_parser(clap::builder::ValueParser::new(imd::vulns::VulnScanner::new))
+            .help("Web vulnerability scanner: nikto, nuclei or both"),
+    )
+    .arg(
+        Arg::new("web_extension_rules")
+            .long("web-extension-rules")
+            .value_name("RULES")
+            .num_args(1)
+            .default_value(imd::content::DEFAULT_EXTENSION_RULES)
+            .hide_default_value(true)
+            .value_parser(clap::builder::ValueParser::new(imd::content::parse_extension_rules))
+            .help("Extensions and extra wordlists for content discovery when a web port's server headers or technologies name a product as a whole word, like iis=asp,aspx,config+/path/to/wordlist;php=php,phps,bak, a file with one rule per line, or none. Defaults to rules for IIS, ASP.NET, PHP, Apache, nginx, Tomcat, WordPress, Drupal and Joomla"),
+    )
+    .arg(
+        Arg::new("web_follow_ups")
+            .long("web-follow-ups")
+            .value_name("FOLLOW_UPS")
+            .num_args(1)
+            .default_value(imd::webtech::DEFAULT_FOLLOW_UPS)
+            .value_parser(clap::builder::ValueParser::new(imd::webtech::parse_follow_ups))
+            .help("Comma separated checks to run when a web port turns out to be a known technology: droopescan, gitlab, jenkins, tomcat-manager and wpscan, or none"),
+    )
+    .arg(
+        Arg::new("web_loot_max_size")
+            .long("web-loot-max-size")
+            .value_name("BYTES")
+            .num_args(1)
+            .default_value("1048576")
+            .value_parser(clap::value_parser!(u64))
+            .help("Largest exposed backup, .env or other file to download from web servers"),
+    )
+    .arg(
+        Arg::new("web_scanner")
+            .long("web-scanner")
+            .value_name("SCANNER")
+            .num_args(1)
+            .default_value("feroxbuster")
+            .value_parser(clap::builder::ValueParser::new(imd::content::WebScanner::new))
+            .help("Web content discovery scanner: feroxbuster, gobuster, ffuf or dirsearch. Another of them is used if it is not installed"),
+    )
+    .arg(
+        Arg::new("web_threads")
+            .long("web-threads")
+            .value_name("THREADS")
+            .num_args(1)
+            .default_value("50")
+            .value_parser(clap::value_parser!(usize))
+            .help("Threads the web content discovery scanner uses on each web port"),
+    )
+    .arg(
+        Arg::new("web_time_limit")
+            .long("web-time-limit")
+            .value_name("MINUTES")
+            .num_args(1)
+            .default_value("10")
+            .value_parser(clap::value_parser!(u64))
+            .help("How long the web content discovery scanner can spend on each web port"),
+    )
     .arg(
         Arg::new("wordlist")
             .short('w')
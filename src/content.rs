@@ -0,0 +1,775 @@
+use crate::error::PanicDiscoveryError;
+use crate::http::{json_escape, json_value};
+use crate::{command_exists, run_command_with_args};
+use std::collections::HashSet;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+// Extensions and extra wordlists used when a web port's server headers or technologies mention the rule's name, unless the user picks their own
+pub const DEFAULT_EXTENSION_RULES: &str = "iis=asp,aspx,config+/usr/share/wordlists/seclists/Discovery/Web-Content/IIS.fuzz.txt;asp.net=aspx,ashx,asmx,config;php=php,phps,bak;apache=php,html,txt;nginx=php,html;tomcat=jsp,do,action;wordpress=php;drupal=php;joomla=php";
+
+// Names that are worth a look even when the server refuses access to them
+const SENSITIVE_NAMES: [&str; 20] = [
+    "admin",
+    "backup",
+    "config",
+    "console",
+    "dashboard",
+    "db",
+    "debug",
+    "dev",
+    "internal",
+    "manager",
+    "phpmyadmin",
+    "portal",
+    "private",
+    "secret",
+    "server-status",
+    "sql",
+    "upload",
+    ".env",
+    ".git",
+    ".ht",
+];
+
+// Tool used to find web content, falling back through the others if it isn't installed
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum WebScanner {
+    Dirsearch,
+    #[default]
+    Feroxbuster,
+    Ffuf,
+    Gobuster,
+}
+
+impl WebScanner {
+    // Parse a WebScanner from the command line arguments
+    pub fn new(input: &str) -> Result<WebScanner, PanicDiscoveryError> {
+        match input {
+            "dirsearch" => Ok(WebScanner::Dirsearch),
+            "feroxbuster" => Ok(WebScanner::Feroxbuster),
+            "ffuf" => Ok(WebScanner::Ffuf),
+            "gobuster" => Ok(WebScanner::Gobuster),
+            _ => Err(PanicDiscoveryError::InvalidWebScanner),
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        match self {
+            WebScanner::Dirsearch => "dirsearch",
+            WebScanner::Feroxbuster => "feroxbuster",
+            WebScanner::Ffuf => "ffuf",
+            WebScanner::Gobuster => "gobuster",
+        }
+    }
+
+    // The chosen scanner if it is installed, otherwise the first of the others that is
+    pub fn installed(&self) -> Option<WebScanner> {
+        [
+            *self,
+            WebScanner::Feroxbuster,
+            WebScanner::Gobuster,
+            WebScanner::Ffuf,
+            WebScanner::Dirsearch,
+        ]
+        .into_iter()
+        .find(|scanner| command_exists(scanner.as_str()))
+    }
+
+    // The command line for the scan, translated into the scanner's own options
+    pub fn args(&self, scan: &ContentScan, url: &str) -> Vec<String> {
+        let threads = scan.threads.to_string();
+        let seconds = scan.time_limit.as_secs().to_string();
+        let time_limit = format!("{seconds}s");
+        let fuzz_url = format!("{}/FUZZ", url.trim_end_matches('/'));
+        let json_output = scan_file(&scan.directory, "feroxbuster", url, "json");
+        let mut args: Vec<&str> = match self {
+            WebScanner::Dirsearch => vec![
+                "-q",
+                "--no-color",
+                "--full-url",
+                "-u",
+                url,
+                "-t",
+                &threads,
+                "--max-time",
+                &seconds,
+            ],
+            WebScanner::Feroxbuster => vec![
+                "-q",
+                "--thorough",
+                "--no-state",
+                "-u",
+                url,
+                "-t",
+                &threads,
+                "--time-limit",
+                &time_limit,
+                "--json",
+                "-o",
+                &json_output,
+            ],
+            WebScanner::Ffuf => vec![
+                "-s", "-ac", "-json", "-u", &fuzz_url, "-t", &threads, "-maxtime", &seconds,
+            ],
+            WebScanner::Gobuster => vec![
+                "dir",
+                "-q",
+                "--no-color",
+                "--no-error",
+                "-u",
+                url,
+                "-t",
+                &threads,
+            ],
+        };
+        args.extend(["-w", &scan.wordlist]);
+
+        let extensions = scan.extensions.join(",");
+        let dotted: Vec<String> = scan.extensions.iter().map(|e| format!(".{e}")).collect();
+        let dotted = dotted.join(",");
+        if !scan.extensions.is_empty() {
+            match self {
+                WebScanner::Dirsearch => args.extend(["-e", &extensions, "--force-extensions"]),
+                WebScanner::Feroxbuster | WebScanner::Gobuster => args.extend(["-x", &extensions]),
+                WebScanner::Ffuf => args.extend(["-e", &dotted]),
+            }
+        }
+
+        args.into_iter().map(str::to_string).collect()
+    }
+
+    // Run the scan, returning what it found in a common format along with the command line that found it
+    pub fn run(
+        &self,
+        scan: &ContentScan,
+        url: &str,
+    ) -> Result<(Vec<ContentHit>, String), Box<dyn Error>> {
+        // Seeds go in front of the wordlist and extra wordlists after it, in a copy that is removed however the scan ends
+        let combined = !scan.seeds.is_empty() || !scan.wordlists.is_empty();
+        let seeded;
+        let _combined_wordlist;
+        let scan = match combined {
+            false => scan,
+            true => {
+                let wordlist = scan_file(&scan.directory, "wordlist", url, "txt");
+                _combined_wordlist = TempFile::new(&wordlist);
+                let mut words: Vec<String> = scan.seeds.clone();
+                let mut seen: HashSet<String> = words.iter().cloned().collect();
+                for file in std::iter::once(&scan.wordlist).chain(&scan.wordlists) {
+                    for word in fs::read_to_string(file).unwrap_or_default().lines() {
+                        if seen.insert(word.to_string()) {
+                            words.push(word.to_string());
+                        }
+                    }
+                }
+                fs::write(&wordlist, words.join("\n") + "\n")?;
+                seeded = ContentScan {
+                    wordlist,
+                    ..scan.clone()
+                };
+                &seeded
+            }
+        };
+
+        let args = self.args(scan, url);
+        let command = format!("{} {}", self.as_str(), args.join(" "));
+        let args: Vec<&str> = args.iter().map(String::as_str).collect();
+
+        let output = match self {
+            // gobuster has no time limit of its own
+            WebScanner::Gobuster => {
+                let limit = format!("{}s", scan.time_limit.as_secs());
+                let mut timeout_args = vec![limit.as_str(), "gobuster"];
+                timeout_args.extend(args);
+                run_command_with_args("timeout", timeout_args)?
+            }
+            // feroxbuster only writes JSON to its output file
+            WebScanner::Feroxbuster => {
+                let json_output =
+                    TempFile::new(&scan_file(&scan.directory, "feroxbuster", url, "json"));
+                run_command_with_args("feroxbuster", args)?;
+                fs::read_to_string(json_output.path()).unwrap_or_default()
+            }
+            _ => run_command_with_args(self.as_str(), args)?,
+        };
+
+        let mut hits: Vec<ContentHit> = output
+            .lines()
+            .filter_map(|line| match self {
+                WebScanner::Dirsearch => parse_dirsearch(line),
+                WebScanner::Feroxbuster => parse_feroxbuster(line),
+                WebScanner::Ffuf => parse_ffuf(line),
+                WebScanner::Gobuster => parse_gobuster(line, url),
+            })
+            .collect();
+
+        hits.sort_by(|a, b| (&a.url, &a.method).cmp(&(&b.url, &b.method)));
+        hits.dedup_by(|a, b| a.url == b.url && a.method == b.method);
+
+        Ok((hits, command))
+    }
+}
+
+// Extensions, and optionally extra wordlists, for content discovery on web ports whose fingerprint mentions the name
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ExtensionRule {
+    extensions: Vec<String>,
+    name: String,
+    wordlists: Vec<String>,
+}
+
+impl ExtensionRule {
+    pub fn extensions(&self) -> &Vec<String> {
+        &self.extensions
+    }
+
+    pub fn name(&self) -> &String {
+        &self.name
+    }
+
+    pub fn wordlists(&self) -> &Vec<String> {
+        &self.wordlists
+    }
+
+    // Whether the fingerprint names the rule's product, ignoring case. Names have to match a whole word like "PHP/8.1", or the
+    // product half of "Vendor-Product" like "Microsoft-IIS", so apache doesn't match Tomcat's Apache-Coyote
+    pub fn matches(&self, signature: &str) -> bool {
+        signature
+            .to_lowercase()
+            .split(|c: char| !(c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_')))
+            .map(|word| word.trim_matches('.'))
+            .any(|word| {
+                word == self.name
+                    || word
+                        .split_once('-')
+                        .is_some_and(|(_, product)| product == self.name)
+            })
+    }
+}
+
+// Parse rules like "iis=asp,aspx+/path/to/wordlist;php=php,phps", either given directly or one per line in a file
+pub fn parse_extension_rules(input: &str) -> Result<Vec<ExtensionRule>, PanicDiscoveryError> {
+    if input == "none" {
+        return Ok(vec![]);
+    }
+    let input = match fs::metadata(input).is_ok_and(|metadata| metadata.is_file()) {
+        true => {
+            fs::read_to_string(input).map_err(|_| PanicDiscoveryError::InvalidExtensionRules)?
+        }
+        false => input.to_string(),
+    };
+    input
+        .split([';', '\n'])
+        .map(str::trim)
+        .filter(|rule| !rule.is_empty() && !rule.starts_with('#'))
+        .map(|rule| {
+            let (name, rest) = rule
+                .split_once('=')
+                .ok_or(PanicDiscoveryError::InvalidExtensionRules)?;
+            let mut parts = rest.split('+').map(str::trim);
+            let extensions: Vec<String> = parts
+                .next()
+                .unwrap_or_default()
+                .split(',')
+                .map(|extension| extension.trim().trim_start_matches('.').to_string())
+                .filter(|extension| !extension.is_empty())
+                .collect();
+            let wordlists: Vec<String> = parts
+                .filter(|wordlist| !wordlist.is_empty())
+                .map(str::to_string)
+                .collect();
+            let name = name.trim().to_lowercase();
+            let valid = extensions
+                .iter()
+                .all(|extension| extension.chars().all(|c| c.is_ascii_alphanumeric()));
+            if name.is_empty() || !valid || (extensions.is_empty() && wordlists.is_empty()) {
+                return Err(PanicDiscoveryError::InvalidExtensionRules);
+            }
+            Ok(ExtensionRule {
+                extensions,
+                name,
+                wordlists,
+            })
+        })
+        .collect()
+}
+
+// The options every content discovery scanner understands
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ContentScan {
+    directory: String,
+    extensions: Vec<String>,
+    fingerprint: String,
+    rules: Vec<String>,
+    seeds: Vec<String>,
+    threads: usize,
+    time_limit: Duration,
+    wordlist: String,
+    wordlists: Vec<String>,
+}
+
+impl ContentScan {
+    // Scanners' temporary files go in the directory, which should be the target's own
+    pub fn new(
+        directory: &str,
+        wordlist: &str,
+        threads: usize,
+        time_limit: Duration,
+    ) -> ContentScan {
+        ContentScan {
+            directory: directory.to_string(),
+            extensions: vec![],
+            fingerprint: String::new(),
+            rules: vec![],
+            seeds: vec![],
+            threads,
+            time_limit,
+            wordlist: wordlist.to_string(),
+            wordlists: vec![],
+        }
+    }
+
+    pub fn with_extensions(mut self, extensions: Vec<String>) -> ContentScan {
+        self.extensions = extensions;
+        self
+    }
+
+    // Add the extensions and extra wordlists of every rule the port's fingerprint matches, skipping wordlists that don't exist
+    pub fn with_rules(mut self, rules: &[ExtensionRule], fingerprint: &str) -> ContentScan {
+        for rule in rules.iter().filter(|rule| rule.matches(fingerprint)) {
+            self.rules.push(rule.name.clone());
+            for extension in &rule.extensions {
+                if !self.extensions.contains(extension) {
+                    self.extensions.push(extension.clone());
+                }
+            }
+            for wordlist in &rule.wordlists {
+                if Path::new(wordlist).is_file() && !self.wordlists.contains(wordlist) {
+                    self.wordlists.push(wordlist.clone());
+                }
+            }
+        }
+        self.fingerprint = fingerprint.to_string();
+        self
+    }
+
+    // Paths found some other way, like crawling, that are tried before the wordlist
+    pub fn with_seeds(mut self, seeds: Vec<String>) -> ContentScan {
+        self.seeds = seeds;
+        self
+    }
+
+    pub fn extensions(&self) -> &Vec<String> {
+        &self.extensions
+    }
+
+    // What the extension rules were matched against
+    pub fn fingerprint(&self) -> &String {
+        &self.fingerprint
+    }
+
+    // Names of the extension rules that matched
+    pub fn rules(&self) -> &Vec<String> {
+        &self.rules
+    }
+
+    pub fn seeds(&self) -> &Vec<String> {
+        &self.seeds
+    }
+
+    pub fn threads(&self) -> usize {
+        self.threads
+    }
+
+    pub fn time_limit(&self) -> Duration {
+        self.time_limit
+    }
+
+    pub fn wordlist(&self) -> &String {
+        &self.wordlist
+    }
+
+    // Wordlists added by extension rules, on top of the main one
+    pub fn wordlists(&self) -> &Vec<String> {
+        &self.wordlists
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ContentHit {
+    lines: Option<usize>,
+    method: String,
+    redirect: Option<String>,
+    size: Option<usize>,
+    status: u16,
+    url: String,
+    words: Option<usize>,
+}
+
+impl ContentHit {
+    pub fn lines(&self) -> Option<usize> {
+        self.lines
+    }
+
+    pub fn method(&self) -> &String {
+        &self.method
+    }
+
+    pub fn redirect(&self) -> Option<&String> {
+        self.redirect.as_ref()
+    }
+
+    pub fn size(&self) -> Option<usize> {
+        self.size
+    }
+
+    pub fn status(&self) -> u16 {
+        self.status
+    }
+
+    pub fn url(&self) -> &String {
+        &self.url
+    }
+
+    pub fn words(&self) -> Option<usize> {
+        self.words
+    }
+
+    // Pages that loaded, redirects into directories, and refusals on names that suggest something sensitive
+    pub fn interesting(&self) -> bool {
+        let path = url_path(&self.url).trim_end_matches('/');
+        match self.status {
+            200 => true,
+            301 | 302 | 307 | 308 => self
+                .redirect
+                .as_ref()
+                .is_some_and(|redirect| url_path(redirect) == format!("{path}/")),
+            401 | 403 => {
+                let name = path.rsplit('/').next().unwrap_or_default().to_lowercase();
+                SENSITIVE_NAMES
+                    .iter()
+                    .any(|sensitive| name.contains(sensitive))
+            }
+            _ => false,
+        }
+    }
+
+    // One object of web_paths, on a line of its own
+    pub fn json(&self) -> String {
+        let number = |n: Option<usize>| n.map(|n| n.to_string()).unwrap_or("null".to_string());
+        let redirect = match &self.redirect {
+            Some(redirect) => format!("\"{}\"", json_escape(redirect)),
+            None => "null".to_string(),
+        };
+        format!(
+            "{{\"status\":{},\"method\":\"{}\",\"size\":{},\"words\":{},\"lines\":{},\"url\":\"{}\",\"redirect\":{redirect},\"interesting\":{}}}",
+            self.status,
+            json_escape(&self.method),
+            number(self.size),
+            number(self.words),
+            number(self.lines),
+            json_escape(&self.url),
+            self.interesting()
+        )
+    }
+
+    // One line of web_dirs_and_files, the same whichever scanner found it
+    pub fn line(&self) -> String {
+        let size = self.size.map(|s| s.to_string()).unwrap_or("-".to_string());
+        match &self.redirect {
+            Some(redirect) => format!("{: <5}{size: >10}  {} -> {redirect}", self.status, self.url),
+            None => format!("{: <5}{size: >10}  {}", self.status, self.url),
+        }
+    }
+}
+
+// A temporary file for a tool's scan of a URL in the target's directory, so that scans of different ports, hostnames and
+// runs don't collide, and nobody else on the machine can read or plant it the way they could in /tmp
+pub fn scan_file(directory: &str, tool: &str, url: &str, extension: &str) -> String {
+    let name: String = url
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    format!(
+        "{directory}/imd-{tool}-{}-{name}.{extension}",
+        std::process::id()
+    )
+}
+
+// A file that is removed when it goes out of scope, so early returns don't leave it behind
+pub struct TempFile {
+    path: String,
+}
+
+impl TempFile {
+    pub fn new(path: &str) -> TempFile {
+        TempFile {
+            path: path.to_string(),
+        }
+    }
+
+    pub fn path(&self) -> &String {
+        &self.path
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+// feroxbuster's --json writes one object per line, and the ones of type "response" are what it found
+fn parse_feroxbuster(line: &str) -> Option<ContentHit> {
+    if json_value(line, "type")? != "response" {
+        return None;
+    }
+    Some(ContentHit {
+        lines: json_value(line, "line_count").and_then(|n| n.parse().ok()),
+        method: json_value(line, "method").unwrap_or("GET".to_string()),
+        redirect: json_value(line, "location").filter(|r| !r.is_empty()),
+        size: json_value(line, "content_length").and_then(|n| n.parse().ok()),
+        status: json_value(line, "status")?.parse().ok()?,
+        url: json_value(line, "url")?,
+        words: json_value(line, "word_count").and_then(|n| n.parse().ok()),
+    })
+}
+
+// gobuster prints "/admin                (Status: 301) [Size: 312] [--> http://host/admin/]"
+fn parse_gobuster(line: &str, url: &str) -> Option<ContentHit> {
+    let (path, rest) = line.trim().split_once(" (Status: ")?;
+    let status = rest.split(')').next()?.trim().parse().ok()?;
+    let size = rest
+        .split_once("[Size: ")
+        .and_then(|(_, size)| size.split(']').next())
+        .and_then(|size| size.trim().parse().ok());
+    let redirect = rest
+        .split_once("[--> ")
+        .and_then(|(_, redirect)| redirect.split(']').next())
+        .map(|redirect| redirect.trim().to_string());
+    Some(ContentHit {
+        lines: None,
+        method: "GET".to_string(),
+        redirect,
+        size,
+        status,
+        url: format!(
+            "{}/{}",
+            url.trim_end_matches('/'),
+            path.trim().trim_start_matches('/')
+        ),
+        words: None,
+    })
+}
+
+// ffuf's -json prints one object per result, with the fields imd wants as plain values
+fn parse_ffuf(line: &str) -> Option<ContentHit> {
+    let status = json_value(line, "status")?.parse().ok()?;
+    let redirect = json_value(line, "redirectlocation").filter(|r| !r.is_empty());
+    Some(ContentHit {
+        lines: json_value(line, "lines").and_then(|n| n.parse().ok()),
+        method: "GET".to_string(),
+        redirect,
+        size: json_value(line, "length").and_then(|size| size.parse().ok()),
+        status,
+        url: json_value(line, "url")?,
+        words: json_value(line, "words").and_then(|n| n.parse().ok()),
+    })
+}
+
+// dirsearch prints "[12:00:00] 301 -  312B  - http://host/admin  ->  http://host/admin/"
+fn parse_dirsearch(line: &str) -> Option<ContentHit> {
+    let line = match line.trim().strip_prefix('[') {
+        Some(line) => line.split_once(']')?.1,
+        None => line,
+    };
+    let mut parts = line.splitn(3, " - ");
+    let status = parts.next()?.trim().parse().ok()?;
+    let size = parse_size(parts.next()?.trim());
+    let rest = parts.next()?;
+    let (url, redirect) = match rest.split_once("->") {
+        Some((url, redirect)) => (url.trim(), Some(redirect.trim().to_string())),
+        None => (rest.trim(), None),
+    };
+    Some(ContentHit {
+        lines: None,
+        method: "GET".to_string(),
+        redirect,
+        size,
+        status,
+        url: url.to_string(),
+        words: None,
+    })
+}
+
+// dirsearch rounds sizes into B, KB and MB
+fn parse_size(size: &str) -> Option<usize> {
+    let (number, multiplier) = match size {
+        size if size.ends_with("KB") => (size.trim_end_matches("KB"), 1024.0),
+        size if size.ends_with("MB") => (size.trim_end_matches("MB"), 1024.0 * 1024.0),
+        size => (size.trim_end_matches('B'), 1.0),
+    };
+    number
+        .trim()
+        .parse::<f64>()
+        .ok()
+        .map(|n| (n * multiplier) as usize)
+}
+
+// The path of a URL, or the URL itself if it is already just a path
+pub fn url_path(url: &str) -> &str {
+    match url.split_once("://") {
+        Some((_, rest)) => rest.find('/').map(|i| &rest[i..]).unwrap_or("/"),
+        None => url,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hit(status: u16, size: usize, url: &str, redirect: Option<&str>) -> ContentHit {
+        ContentHit {
+            method: "GET".to_string(),
+            redirect: redirect.map(str::to_string),
+            size: Some(size),
+            status,
+            url: url.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn parse_feroxbuster_response() {
+        // feroxbuster 2.10 --json
+        let line = r#"{"type":"response","url":"http://10.10.10.5/admin","original_url":"http://10.10.10.5","path":"/admin","wildcard":false,"status":301,"method":"GET","content_length":312,"line_count":9,"word_count":28,"headers":{"content-type":"text/html; charset=iso-8859-1","location":"http://10.10.10.5/admin/","server":"Apache/2.4.41 (Ubuntu)","content-length":"312"},"extension":"","truncated":false,"timestamp":1700000000.123}"#;
+        let expected = ContentHit {
+            lines: Some(9),
+            words: Some(28),
+            ..hit(
+                301,
+                312,
+                "http://10.10.10.5/admin",
+                Some("http://10.10.10.5/admin/"),
+            )
+        };
+        assert_eq!(parse_feroxbuster(line), Some(expected));
+
+        let statistics = r#"{"type":"statistics","timeouts":0,"requests":4615,"expected_per_scan":4615,"total_expected":4615,"errors":0,"successes":4,"redirects":1,"client_errors":4610,"server_errors":0}"#;
+        assert_eq!(parse_feroxbuster(statistics), None);
+    }
+
+    #[test]
+    fn parse_gobuster_line() {
+        // gobuster 3.6 dir -q --no-color
+        let line = "/admin                (Status: 301) [Size: 312] [--> http://10.10.10.5/admin/]";
+        assert_eq!(
+            parse_gobuster(line, "http://10.10.10.5/"),
+            Some(hit(
+                301,
+                312,
+                "http://10.10.10.5/admin",
+                Some("http://10.10.10.5/admin/")
+            ))
+        );
+        let line = "/index.php            (Status: 200) [Size: 10918]";
+        assert_eq!(
+            parse_gobuster(line, "http://10.10.10.5"),
+            Some(hit(200, 10918, "http://10.10.10.5/index.php", None))
+        );
+        assert_eq!(
+            parse_gobuster("Progress: 4614 / 4615 (99.98%)", "http://10.10.10.5"),
+            None
+        );
+    }
+
+    #[test]
+    fn parse_ffuf_result() {
+        // ffuf 2.1 -s -json
+        let line = r#"{"input":{"FFUFHASH":"4d1f62","FUZZ":"admin"},"position":24,"status":301,"length":312,"words":20,"lines":10,"content-type":"text/html; charset=iso-8859-1","redirectlocation":"http://10.10.10.5/admin/","scraper":{},"duration":1634125,"resultfile":"","url":"http://10.10.10.5/admin","host":"10.10.10.5"}"#;
+        let expected = ContentHit {
+            lines: Some(10),
+            words: Some(20),
+            ..hit(
+                301,
+                312,
+                "http://10.10.10.5/admin",
+                Some("http://10.10.10.5/admin/"),
+            )
+        };
+        assert_eq!(parse_ffuf(line), Some(expected));
+    }
+
+    #[test]
+    fn parse_dirsearch_line() {
+        // dirsearch 0.4 -q --no-color --full-url
+        let line =
+            "[12:00:00] 301 -  312B  - http://10.10.10.5/admin  ->  http://10.10.10.5/admin/";
+        assert_eq!(
+            parse_dirsearch(line),
+            Some(hit(
+                301,
+                312,
+                "http://10.10.10.5/admin",
+                Some("http://10.10.10.5/admin/")
+            ))
+        );
+        let line = "[12:00:01] 200 -    2KB - http://10.10.10.5/login.php";
+        assert_eq!(
+            parse_dirsearch(line),
+            Some(hit(200, 2048, "http://10.10.10.5/login.php", None))
+        );
+    }
+
+    #[test]
+    fn parse_size_units() {
+        assert_eq!(parse_size("312B"), Some(312));
+        assert_eq!(parse_size("1.5KB"), Some(1536));
+        assert_eq!(parse_size("2MB"), Some(2 * 1024 * 1024));
+        assert_eq!(parse_size("big"), None);
+    }
+
+    #[test]
+    fn parse_extension_rules_defaults_and_errors() {
+        let rules = parse_extension_rules(DEFAULT_EXTENSION_RULES).unwrap();
+        let iis = rules.iter().find(|rule| rule.name() == "iis").unwrap();
+        assert_eq!(iis.extensions(), &["asp", "aspx", "config"]);
+        assert_eq!(
+            iis.wordlists(),
+            &["/usr/share/wordlists/seclists/Discovery/Web-Content/IIS.fuzz.txt"]
+        );
+        assert_eq!(rules.len(), 9);
+
+        let rules = parse_extension_rules("PHP = .php, phps ; # comment\ntomcat=jsp").unwrap();
+        assert_eq!(rules[0].name(), "php");
+        assert_eq!(rules[0].extensions(), &["php", "phps"]);
+        assert_eq!(rules[1].name(), "tomcat");
+
+        assert!(parse_extension_rules("none").unwrap().is_empty());
+        assert!(parse_extension_rules("php").is_err());
+        assert!(parse_extension_rules("=php").is_err());
+        assert!(parse_extension_rules("php=p/hp").is_err());
+        assert!(parse_extension_rules("php=").is_err());
+    }
+
+    #[test]
+    fn extension_rules_match_whole_names() {
+        let rules = parse_extension_rules(DEFAULT_EXTENSION_RULES).unwrap();
+        let matched = |signature: &str| -> Vec<String> {
+            rules
+                .iter()
+                .filter(|rule| rule.matches(signature))
+                .map(|rule| rule.name().clone())
+                .collect()
+        };
+        assert_eq!(matched("Apache-Coyote/1.1, Tomcat"), ["tomcat"]);
+        assert_eq!(matched("Microsoft-IIS/10.0, ASP.NET"), ["iis", "asp.net"]);
+        assert_eq!(
+            matched("Apache/2.4.41 (Ubuntu), PHP/7.4.3"),
+            ["php", "apache"]
+        );
+    }
+}
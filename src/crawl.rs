@@ -0,0 +1,404 @@
+use crate::http::HttpClient;
+use std::collections::VecDeque;
+
+// How many links deep the crawl goes from the pages it starts on, and how many pages it fetches at most
+const MAX_DEPTH: usize = 2;
+const MAX_PAGES: usize = 50;
+
+// Links to these are recorded but not fetched, since there is nothing to crawl in them
+const SKIPPED_EXTENSIONS: [&str; 16] = [
+    ".png", ".jpg", ".jpeg", ".gif", ".svg", ".ico", ".webp", ".css", ".js", ".woff", ".woff2",
+    ".ttf", ".pdf", ".zip", ".mp4", ".mp3",
+];
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CrawlReport {
+    comments: Vec<(String, String)>,
+    emails: Vec<String>,
+    forms: Vec<(String, String, String)>,
+    links: Vec<String>,
+    pages: Vec<(String, u16)>,
+    robots: Vec<String>,
+    scripts: Vec<String>,
+    sitemap: Vec<String>,
+}
+
+impl CrawlReport {
+    // HTML comments, along with the page they were on
+    pub fn comments(&self) -> &Vec<(String, String)> {
+        &self.comments
+    }
+
+    pub fn emails(&self) -> &Vec<String> {
+        &self.emails
+    }
+
+    // Each form's page, method and action
+    pub fn forms(&self) -> &Vec<(String, String, String)> {
+        &self.forms
+    }
+
+    // Every same-site path linked to, whether or not it was fetched
+    pub fn links(&self) -> &Vec<String> {
+        &self.links
+    }
+
+    // The pages fetched and the status they came back with
+    pub fn pages(&self) -> &Vec<(String, u16)> {
+        &self.pages
+    }
+
+    // Paths from robots.txt's Allow and Disallow lines
+    pub fn robots(&self) -> &Vec<String> {
+        &self.robots
+    }
+
+    pub fn scripts(&self) -> &Vec<String> {
+        &self.scripts
+    }
+
+    pub fn sitemap(&self) -> &Vec<String> {
+        &self.sitemap
+    }
+
+    // Paths worth handing to content discovery as a head start, without the leading slash
+    pub fn seeds(&self) -> Vec<String> {
+        let mut seeds: Vec<String> = vec![];
+        let forms = self.forms.iter().map(|(_, _, action)| action);
+        for path in self
+            .robots
+            .iter()
+            .chain(&self.sitemap)
+            .chain(&self.links)
+            .chain(forms)
+            .chain(&self.scripts)
+        {
+            let seed = path.trim_start_matches('/').to_string();
+            if !seed.is_empty() && !seeds.contains(&seed) {
+                seeds.push(seed);
+            }
+        }
+        seeds
+    }
+}
+
+// Fetch robots.txt, sitemap.xml and the home page, then follow same-site links a couple of levels deep
+pub fn crawl(client: &HttpClient, host: &str) -> CrawlReport {
+    let mut report = CrawlReport::default();
+    let origin = client.url(host, "");
+
+    if let Ok(response) = client.get(host, "/robots.txt") {
+        if response.status() == 200 {
+            for (path, sitemap) in parse_robots(&response.text()) {
+                match sitemap {
+                    true => push_unique(&mut report.sitemap, path),
+                    false => push_unique(&mut report.robots, path),
+                }
+            }
+        }
+    }
+    // robots.txt can point at a sitemap somewhere other than the usual place
+    let mut sitemaps = vec!["/sitemap.xml".to_string()];
+    for sitemap in report.sitemap.drain(..) {
+        push_unique(&mut sitemaps, sitemap);
+    }
+    for sitemap in sitemaps {
+        if let Ok(response) = client.get(host, &sitemap) {
+            if response.status() == 200 {
+                for url in tag_contents(&response.text(), "loc") {
+                    if let Some(path) = resolve(&origin, "/", &url) {
+                        push_unique(&mut report.sitemap, path);
+                    }
+                }
+            }
+        }
+    }
+
+    let mut queue: VecDeque<(String, usize)> = VecDeque::from([("/".to_string(), 0)]);
+    for path in report.robots.iter().chain(&report.sitemap) {
+        queue.push_back((path.clone(), 1));
+    }
+    let mut visited: Vec<String> = vec![];
+
+    while let Some((path, depth)) = queue.pop_front() {
+        if visited.len() >= MAX_PAGES || visited.contains(&path) {
+            continue;
+        }
+        visited.push(path.clone());
+        let response = match client.get(host, &path) {
+            Ok(response) => response,
+            Err(_) => continue,
+        };
+        report.pages.push((path.clone(), response.status()));
+
+        if let Some(location) = response.header("Location") {
+            if let Some(target) = resolve(&origin, &path, location) {
+                queue.push_back((target, depth));
+            }
+        }
+
+        let html = response.text();
+        for comment in html_comments(&html) {
+            report.comments.push((path.clone(), comment));
+        }
+        for email in emails(&html) {
+            push_unique(&mut report.emails, email);
+        }
+        for (method, action) in forms(&html) {
+            let action = resolve(&origin, &path, &action).unwrap_or(path.clone());
+            let form = (path.clone(), method, action);
+            if !report.forms.contains(&form) {
+                report.forms.push(form);
+            }
+        }
+        for src in attribute_values(&html, "script", "src") {
+            if let Some(script) = resolve(&origin, &path, &src) {
+                push_unique(&mut report.scripts, script);
+            }
+        }
+        for href in attribute_values(&html, "a", "href")
+            .into_iter()
+            .chain(attribute_values(&html, "link", "href"))
+            .chain(attribute_values(&html, "iframe", "src"))
+        {
+            if let Some(email) = href.strip_prefix("mailto:") {
+                push_unique(
+                    &mut report.emails,
+                    email.split('?').next().unwrap_or_default().to_string(),
+                );
+                continue;
+            }
+            let link = match resolve(&origin, &path, &href) {
+                Some(link) => link,
+                None => continue,
+            };
+            push_unique(&mut report.links, link.clone());
+            let lower = link.to_lowercase();
+            if depth < MAX_DEPTH && !SKIPPED_EXTENSIONS.iter().any(|e| lower.ends_with(e)) {
+                queue.push_back((link, depth + 1));
+            }
+        }
+    }
+
+    report
+}
+
+fn push_unique(list: &mut Vec<String>, value: String) {
+    if !value.is_empty() && !list.contains(&value) {
+        list.push(value);
+    }
+}
+
+// The paths in robots.txt, flagging the ones that are sitemaps
+fn parse_robots(robots: &str) -> Vec<(String, bool)> {
+    robots
+        .lines()
+        .filter_map(|line| {
+            let (field, value) = line.split('#').next()?.split_once(':')?;
+            let value = value.trim();
+            match field.trim().to_lowercase().as_str() {
+                "allow" | "disallow" => {
+                    // Wildcards can't be requested, so keep the part before them
+                    let path = value.split(['*', '$']).next()?.to_string();
+                    (path.starts_with('/') && path != "/").then_some((path, false))
+                }
+                "sitemap" => {
+                    let path = match value.split_once("://") {
+                        Some((_, rest)) => rest.find('/').map(|i| rest[i..].to_string())?,
+                        None => value.to_string(),
+                    };
+                    Some((path, true))
+                }
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+// Turn a link on a page into a path on the same site, or None if it goes somewhere else
+fn resolve(origin: &str, page: &str, link: &str) -> Option<String> {
+    let link = link.trim();
+    let link = link.split('#').next()?;
+    if link.is_empty() {
+        return None;
+    }
+    let lower = link.to_lowercase();
+    if ["javascript:", "mailto:", "data:", "tel:"]
+        .iter()
+        .any(|scheme| lower.starts_with(scheme))
+    {
+        return None;
+    }
+
+    let path = if lower.starts_with("http://") || lower.starts_with("https://") {
+        // The origin has the port in it, but links to the default port leave it out
+        let same = link == origin || link.starts_with(&format!("{origin}/")) || {
+            let (scheme, rest) = origin.split_once("://")?;
+            let host = rest.rsplit_once(':').map(|(host, _)| host).unwrap_or(rest);
+            let default = match scheme {
+                "https" => ":443",
+                _ => ":80",
+            };
+            let bare = format!("{scheme}://{host}");
+            rest.ends_with(default) && (link == bare || link.starts_with(&format!("{bare}/")))
+        };
+        if !same {
+            return None;
+        }
+        let (_, rest) = link.split_once("://")?;
+        rest.find('/')
+            .map(|i| rest[i..].to_string())
+            .unwrap_or("/".to_string())
+    } else if link.starts_with("//") {
+        return None;
+    } else if link.starts_with('/') {
+        link.to_string()
+    } else {
+        let directory = &page[..page.rfind('/').map(|i| i + 1).unwrap_or(0)];
+        format!("{directory}{link}")
+    };
+
+    Some(normalize(&path))
+}
+
+// Collapse "." and ".." in a path and drop the query string, so the same page isn't fetched twice
+fn normalize(path: &str) -> String {
+    let path = path.split('?').next().unwrap_or_default();
+    let mut parts: Vec<&str> = vec![];
+    for part in path.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => {
+                parts.pop();
+            }
+            part => parts.push(part),
+        }
+    }
+    let mut normalized = format!("/{}", parts.join("/"));
+    if path.ends_with('/') && normalized != "/" {
+        normalized.push('/');
+    }
+    normalized
+}
+
+// The values of an attribute on every one of a kind of tag
+fn attribute_values(html: &str, tag: &str, name: &str) -> Vec<String> {
+    // ASCII lowercasing keeps every byte where it was, so offsets into it are offsets into the page
+    let lower = html.to_ascii_lowercase();
+    let open = format!("<{tag}");
+    let mut values = vec![];
+    let mut offset = 0;
+    while let Some(start) = lower[offset..].find(&open) {
+        let start = offset + start;
+        let end = lower[start..]
+            .find('>')
+            .map(|e| start + e)
+            .unwrap_or(lower.len());
+        offset = end;
+        // Make sure this is the tag and not just one whose name starts the same way
+        if !lower[start + open.len()..]
+            .starts_with(|c: char| c.is_whitespace() || c == '>' || c == '/')
+        {
+            continue;
+        }
+        if let Some(value) = attribute(&html[start..end], &lower[start..end], name) {
+            values.push(value);
+        }
+    }
+    values
+}
+
+// One attribute's value from a tag, quoted or not
+fn attribute(tag: &str, lower: &str, name: &str) -> Option<String> {
+    let mut search = 0;
+    loop {
+        let found = search + lower[search..].find(&format!("{name}="))?;
+        search = found + name.len() + 1;
+        // Skip over matches that are the end of a longer attribute name, like data-src
+        if found > 0 && !lower[..found].ends_with(char::is_whitespace) {
+            continue;
+        }
+        let value = &tag[search..];
+        return match value.chars().next()? {
+            quote @ ('"' | '\'') => value[1..].split(quote).next().map(str::to_string),
+            _ => value
+                .split(|c: char| c.is_whitespace() || c == '>')
+                .next()
+                .map(str::to_string),
+        };
+    }
+}
+
+// Each form's method and action, with GET as the method when the form doesn't say
+fn forms(html: &str) -> Vec<(String, String)> {
+    let lower = html.to_ascii_lowercase();
+    let mut forms = vec![];
+    let mut offset = 0;
+    while let Some(start) = lower[offset..].find("<form") {
+        let start = offset + start;
+        let end = lower[start..]
+            .find('>')
+            .map(|e| start + e)
+            .unwrap_or(lower.len());
+        offset = end;
+        let tag = &html[start..end];
+        let tag_lower = &lower[start..end];
+        let method = attribute(tag, tag_lower, "method")
+            .unwrap_or("GET".to_string())
+            .to_uppercase();
+        let action = attribute(tag, tag_lower, "action").unwrap_or_default();
+        forms.push((method, action));
+    }
+    forms
+}
+
+fn html_comments(html: &str) -> Vec<String> {
+    let mut comments = vec![];
+    let mut rest = html;
+    while let Some(start) = rest.find("<!--") {
+        rest = &rest[start + 4..];
+        let end = rest.find("-->").unwrap_or(rest.len());
+        let comment = rest[..end].trim();
+        if !comment.is_empty() {
+            comments.push(comment.to_string());
+        }
+        rest = &rest[end..];
+    }
+    comments
+}
+
+// Anything shaped like name@domain.tld
+fn emails(text: &str) -> Vec<String> {
+    let is_email_char = |c: char| c.is_ascii_alphanumeric() || "._%+-@".contains(c);
+    let mut emails = vec![];
+    for word in text.split(|c: char| !is_email_char(c)) {
+        let word = word.trim_matches('.');
+        let (local, domain) = match word.split_once('@') {
+            Some(parts) => parts,
+            None => continue,
+        };
+        // Retina images are named like logo@2x.png
+        let tld = domain.rsplit('.').next().unwrap_or_default();
+        let valid = !local.is_empty()
+            && !domain.contains('@')
+            && domain.contains('.')
+            && tld.len() >= 2
+            && tld.chars().all(|c| c.is_ascii_alphabetic())
+            && !["png", "jpg", "jpeg", "gif", "svg", "webp"].contains(&tld);
+        if valid && !emails.contains(&word.to_string()) {
+            emails.push(word.to_string());
+        }
+    }
+    emails
+}
+
+// The text inside every one of a kind of tag, like the <loc> entries in a sitemap
+fn tag_contents(xml: &str, tag: &str) -> Vec<String> {
+    let open = format!("<{tag}>");
+    let close = format!("</{tag}>");
+    xml.split(&open)
+        .skip(1)
+        .filter_map(|rest| rest.split(&close).next())
+        .map(|content| content.trim().to_string())
+        .collect()
+}
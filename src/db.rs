@@ -0,0 +1,777 @@
+use crate::error::RecoverableDiscoveryError;
+use crate::http::HttpClient;
+use crate::scanner::OpenPort;
+use std::error::Error;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{IpAddr, SocketAddr, TcpStream};
+use std::time::Duration;
+
+// Database servers imd knows how to ask for an inventory without credentials
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Database {
+    Elasticsearch,
+    Memcached,
+    Mongodb,
+    Mssql,
+    Mysql,
+    Postgresql,
+    Redis,
+}
+
+impl Database {
+    // Work out which database (if any) the port scan found from the service name, and the version for Elasticsearch's HTTP API
+    pub fn from_open_port(open_port: &OpenPort) -> Option<Database> {
+        if open_port.protocol() != "tcp" {
+            return None;
+        }
+        let service = open_port.service().trim_start_matches("ssl/");
+        match service {
+            "redis" => Some(Database::Redis),
+            "mongodb" | "mongod" => Some(Database::Mongodb),
+            "mysql" => Some(Database::Mysql),
+            "postgresql" => Some(Database::Postgresql),
+            "ms-sql-s" | "mssql" => Some(Database::Mssql),
+            "memcache" | "memcached" => Some(Database::Memcached),
+            "elasticsearch" => Some(Database::Elasticsearch),
+            _ if open_port.version().contains("Elasticsearch") => Some(Database::Elasticsearch),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        match self {
+            Database::Elasticsearch => "elasticsearch",
+            Database::Memcached => "memcached",
+            Database::Mongodb => "mongodb",
+            Database::Mssql => "mssql",
+            Database::Mysql => "mysql",
+            Database::Postgresql => "postgresql",
+            Database::Redis => "redis",
+        }
+    }
+
+    // What imd asks the server for, for the progress bar
+    pub fn check_name(&self) -> &str {
+        match self {
+            Database::Elasticsearch => "GET /_cat/indices",
+            Database::Memcached => "stats",
+            Database::Mongodb => "listDatabases",
+            Database::Mssql => "sa login with no password or sa",
+            Database::Mysql => "root login with no password",
+            Database::Postgresql => "postgres login with no password",
+            Database::Redis => "INFO",
+        }
+    }
+
+    // Connect without credentials (or with the default, empty, ones) and record what the server gives up
+    pub fn check(
+        &self,
+        ip_address: IpAddr,
+        port: u16,
+        tls: bool,
+        timeout: Duration,
+    ) -> Result<DbReport, Box<dyn Error>> {
+        match self {
+            Database::Elasticsearch => elasticsearch(ip_address, port, tls, timeout),
+            Database::Memcached => memcached(ip_address, port, timeout),
+            Database::Mongodb => mongodb(ip_address, port, timeout),
+            Database::Mssql => mssql(ip_address, port, timeout),
+            Database::Mysql => mysql(ip_address, port, timeout),
+            Database::Postgresql => postgresql(ip_address, port, timeout),
+            Database::Redis => redis(ip_address, port, timeout),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DbReport {
+    inventory: Vec<String>,
+    open: bool,
+    raw: String,
+    status: String,
+    untested: bool,
+}
+
+impl DbReport {
+    // Databases, indices, keyspaces and the like
+    pub fn inventory(&self) -> &Vec<String> {
+        &self.inventory
+    }
+
+    // Whether the server let us in without a password
+    pub fn open(&self) -> bool {
+        self.open
+    }
+
+    pub fn raw(&self) -> &String {
+        &self.raw
+    }
+
+    pub fn status(&self) -> &String {
+        &self.status
+    }
+
+    // Whether no login could be tried, in which case open() says nothing either way
+    pub fn untested(&self) -> bool {
+        self.untested
+    }
+}
+
+fn connect(ip_address: IpAddr, port: u16, timeout: Duration) -> Result<TcpStream, Box<dyn Error>> {
+    let stream = TcpStream::connect_timeout(&SocketAddr::new(ip_address, port), timeout)?;
+    stream.set_read_timeout(Some(timeout * 5))?;
+    stream.set_write_timeout(Some(timeout * 5))?;
+    Ok(stream)
+}
+
+// Redis answers INFO with a bulk string, or an error if it wants a password
+fn redis(ip_address: IpAddr, port: u16, timeout: Duration) -> Result<DbReport, Box<dyn Error>> {
+    let mut reader = BufReader::new(connect(ip_address, port, timeout)?);
+    reader.get_mut().write_all(b"INFO\r\n")?;
+
+    let mut first = String::new();
+    reader.read_line(&mut first)?;
+    let length: usize = match first.strip_prefix('$') {
+        Some(length) => length.trim().parse()?,
+        None => {
+            return Ok(DbReport {
+                raw: first.clone(),
+                status: format!("INFO refused: {}", first.trim()),
+                ..Default::default()
+            })
+        }
+    };
+    let mut info = vec![0u8; length];
+    reader.read_exact(&mut info)?;
+    let info = String::from_utf8_lossy(&info).to_string();
+
+    let inventory = info
+        .lines()
+        .filter(|line| {
+            ["redis_version:", "os:", "executable:", "config_file:", "db"]
+                .iter()
+                .any(|key| line.starts_with(key))
+        })
+        .map(|line| line.trim().to_string())
+        .collect();
+    Ok(DbReport {
+        inventory,
+        open: true,
+        raw: info,
+        status: "INFO works without a password".to_string(),
+        untested: false,
+    })
+}
+
+// memcached has no authentication unless it was built with SASL
+fn memcached(ip_address: IpAddr, port: u16, timeout: Duration) -> Result<DbReport, Box<dyn Error>> {
+    let mut reader = BufReader::new(connect(ip_address, port, timeout)?);
+    reader.get_mut().write_all(b"stats\r\n")?;
+
+    let mut raw = String::new();
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 || line.starts_with("END") || line.contains("ERROR") {
+            raw.push_str(&line);
+            break;
+        }
+        raw.push_str(&line);
+    }
+
+    let inventory: Vec<String> = raw
+        .lines()
+        .filter_map(|line| line.strip_prefix("STAT "))
+        .filter(|line| {
+            ["version ", "curr_items ", "total_items "]
+                .iter()
+                .any(|key| line.starts_with(key))
+        })
+        .map(str::to_string)
+        .collect();
+    let open = !inventory.is_empty();
+    Ok(DbReport {
+        inventory,
+        open,
+        status: match open {
+            true => "stats works without authentication".to_string(),
+            false => format!("stats refused: {}", raw.trim()),
+        },
+        raw,
+        untested: false,
+    })
+}
+
+// Elasticsearch without security enabled lists its indices to anyone
+fn elasticsearch(
+    ip_address: IpAddr,
+    port: u16,
+    tls: bool,
+    timeout: Duration,
+) -> Result<DbReport, Box<dyn Error>> {
+    let scheme = if tls { "https" } else { "http" };
+    let client = HttpClient::new(scheme, ip_address, port, timeout);
+    let host = ip_address.to_string();
+    let response = client.get(&host, "/_cat/indices?v")?;
+    let open = response.status() == 200;
+
+    let mut raw = client.get(&host, "/").map(|r| r.text()).unwrap_or_default();
+    raw.push_str(&response.text());
+    Ok(DbReport {
+        inventory: match open {
+            true => response.text().lines().map(str::to_string).collect(),
+            false => vec![],
+        },
+        open,
+        raw,
+        status: match open {
+            true => "indices listed without credentials".to_string(),
+            false => format!("indices refused (HTTP {})", response.status()),
+        },
+        untested: false,
+    })
+}
+
+// Ask MongoDB for listDatabases with OP_MSG, falling back to OP_QUERY for servers older than 3.6
+fn mongodb(ip_address: IpAddr, port: u16, timeout: Duration) -> Result<DbReport, Box<dyn Error>> {
+    let command = bson_document(&[bson_int32("listDatabases", 1), bson_string("$db", "admin")]);
+    let mut body = 0u32.to_le_bytes().to_vec();
+    body.push(0);
+    body.extend(&command);
+    let reply = match mongo_exchange(ip_address, port, timeout, 2013, &body) {
+        Ok(reply) if reply.len() > 5 => reply[5..].to_vec(),
+        _ => {
+            let command = bson_document(&[bson_int32("listDatabases", 1)]);
+            let mut body = 0u32.to_le_bytes().to_vec();
+            body.extend(b"admin.$cmd\0");
+            body.extend(0u32.to_le_bytes());
+            body.extend((-1i32).to_le_bytes());
+            body.extend(&command);
+            let reply = mongo_exchange(ip_address, port, timeout, 2004, &body)?;
+            reply
+                .get(20..)
+                .ok_or(RecoverableDiscoveryError::Response)?
+                .to_vec()
+        }
+    };
+
+    let mut strings = vec![];
+    bson_strings(&reply, &mut strings);
+    let errmsg = strings
+        .iter()
+        .find(|(key, _)| key == "errmsg")
+        .map(|(_, value)| value.clone());
+    let inventory: Vec<String> = strings
+        .iter()
+        .filter(|(key, _)| key == "name")
+        .map(|(_, value)| value.clone())
+        .collect();
+    let open = errmsg.is_none() && !inventory.is_empty();
+
+    Ok(DbReport {
+        inventory: inventory.clone(),
+        open,
+        raw: strings
+            .iter()
+            .map(|(key, value)| format!("{key}: {value}\n"))
+            .collect(),
+        status: match errmsg {
+            Some(errmsg) => format!("listDatabases refused: {errmsg}"),
+            None => "listDatabases works without credentials".to_string(),
+        },
+        untested: false,
+    })
+}
+
+// Send one wire protocol message and read back the body of the reply, after its 16 byte header
+fn mongo_exchange(
+    ip_address: IpAddr,
+    port: u16,
+    timeout: Duration,
+    opcode: u32,
+    body: &[u8],
+) -> Result<Vec<u8>, Box<dyn Error>> {
+    let mut stream = connect(ip_address, port, timeout)?;
+    let mut message = ((body.len() + 16) as u32).to_le_bytes().to_vec();
+    message.extend(1u32.to_le_bytes());
+    message.extend(0u32.to_le_bytes());
+    message.extend(opcode.to_le_bytes());
+    message.extend(body);
+    stream.write_all(&message)?;
+
+    let mut header = [0u8; 16];
+    stream.read_exact(&mut header)?;
+    let length = u32::from_le_bytes(header[..4].try_into()?) as usize;
+    if !(16..=16 * 1024 * 1024).contains(&length) {
+        return Err(RecoverableDiscoveryError::Response.into());
+    }
+    let mut reply = vec![0u8; length - 16];
+    stream.read_exact(&mut reply)?;
+    Ok(reply)
+}
+
+fn bson_document(elements: &[Vec<u8>]) -> Vec<u8> {
+    let elements = elements.concat();
+    let mut document = ((elements.len() + 5) as u32).to_le_bytes().to_vec();
+    document.extend(elements);
+    document.push(0);
+    document
+}
+
+fn bson_int32(key: &str, value: i32) -> Vec<u8> {
+    [&[0x10], key.as_bytes(), &[0], &value.to_le_bytes()].concat()
+}
+
+fn bson_string(key: &str, value: &str) -> Vec<u8> {
+    let length = (value.len() + 1) as u32;
+    [
+        &[0x02],
+        key.as_bytes(),
+        &[0],
+        &length.to_le_bytes(),
+        value.as_bytes(),
+        &[0],
+    ]
+    .concat()
+}
+
+// Collect every string in a BSON document (and the documents inside it) along with its key
+fn bson_strings(document: &[u8], strings: &mut Vec<(String, String)>) -> Option<()> {
+    let length = u32::from_le_bytes(document.get(..4)?.try_into().ok()?) as usize;
+    let mut rest = document.get(4..length.checked_sub(1)?)?;
+
+    while let Some((&kind, after)) = rest.split_first() {
+        let end = after.iter().position(|&b| b == 0)?;
+        let key = String::from_utf8_lossy(&after[..end]).to_string();
+        let value = &after[end + 1..];
+        let size = match kind {
+            0x01 | 0x09 | 0x11 | 0x12 => 8,
+            0x08 => 1,
+            0x10 => 4,
+            0x07 => 12,
+            0x0a => 0,
+            0x13 => 16,
+            0x02 | 0x0d | 0x0e => {
+                let size = u32::from_le_bytes(value.get(..4)?.try_into().ok()?) as usize;
+                let text = value.get(4..4 + size.checked_sub(1)?)?;
+                strings.push((key, String::from_utf8_lossy(text).to_string()));
+                4 + size
+            }
+            0x03 | 0x04 => {
+                bson_strings(value, strings);
+                u32::from_le_bytes(value.get(..4)?.try_into().ok()?) as usize
+            }
+            0x05 => 5 + u32::from_le_bytes(value.get(..4)?.try_into().ok()?) as usize,
+            _ => return None,
+        };
+        rest = value.get(size..)?;
+    }
+    Some(())
+}
+
+// Log in to MySQL as root with an empty password, which needs no hashing, and list the databases if it works
+fn mysql(ip_address: IpAddr, port: u16, timeout: Duration) -> Result<DbReport, Box<dyn Error>> {
+    let mut stream = connect(ip_address, port, timeout)?;
+    let (_, greeting) = mysql_read(&mut stream)?;
+
+    // Hosts that aren't allowed in get an error instead of a greeting
+    if greeting.first() == Some(&0xff) {
+        let error = String::from_utf8_lossy(greeting.get(3..).unwrap_or_default()).to_string();
+        return Ok(DbReport {
+            raw: error.clone(),
+            status: format!("connection refused: {error}"),
+            ..Default::default()
+        });
+    }
+    let version_end = greeting.iter().skip(1).position(|&b| b == 0).unwrap_or(0) + 1;
+    let version =
+        String::from_utf8_lossy(greeting.get(1..version_end).unwrap_or_default()).to_string();
+
+    // CLIENT_LONG_PASSWORD, CLIENT_PROTOCOL_41, CLIENT_SECURE_CONNECTION and CLIENT_PLUGIN_AUTH
+    let capabilities: u32 = 0x0000_0001 | 0x0000_0200 | 0x0000_8000 | 0x0008_0000;
+    let mut login = capabilities.to_le_bytes().to_vec();
+    login.extend(0x0100_0000u32.to_le_bytes());
+    login.push(33);
+    login.extend([0u8; 23]);
+    login.extend(b"root\0");
+    login.push(0);
+    login.extend(b"mysql_native_password\0");
+    mysql_write(&mut stream, 1, &login)?;
+
+    let (sequence, mut reply) = mysql_read(&mut stream)?;
+    // An auth switch to another plugin still takes an empty response for an empty password
+    if reply.first() == Some(&0xfe) {
+        mysql_write(&mut stream, sequence + 1, &[])?;
+        reply = mysql_read(&mut stream)?.1;
+    }
+    if reply.first() != Some(&0x00) {
+        let error = String::from_utf8_lossy(reply.get(3..).unwrap_or_default()).to_string();
+        return Ok(DbReport {
+            raw: format!("Version: {version}\n{error}"),
+            status: format!("root login with no password refused: {error}"),
+            ..Default::default()
+        });
+    }
+
+    let mut query = vec![0x03];
+    query.extend(b"SHOW DATABASES");
+    mysql_write(&mut stream, 0, &query)?;
+    let inventory = mysql_rows(&mut stream).unwrap_or_default();
+
+    Ok(DbReport {
+        inventory,
+        open: true,
+        raw: format!("Version: {version}"),
+        status: "root login works with no password".to_string(),
+        untested: false,
+    })
+}
+
+fn mysql_read(stream: &mut TcpStream) -> Result<(u8, Vec<u8>), Box<dyn Error>> {
+    let mut header = [0u8; 4];
+    stream.read_exact(&mut header)?;
+    let length = u32::from_le_bytes([header[0], header[1], header[2], 0]) as usize;
+    let mut payload = vec![0u8; length];
+    stream.read_exact(&mut payload)?;
+    Ok((header[3], payload))
+}
+
+fn mysql_write(stream: &mut TcpStream, sequence: u8, payload: &[u8]) -> Result<(), Box<dyn Error>> {
+    let mut packet = (payload.len() as u32).to_le_bytes()[..3].to_vec();
+    packet.push(sequence);
+    packet.extend(payload);
+    stream.write_all(&packet)?;
+    Ok(())
+}
+
+// Read a single column result set: the column count, its definition, an EOF, then rows until the next EOF
+fn mysql_rows(stream: &mut TcpStream) -> Result<Vec<String>, Box<dyn Error>> {
+    let (_, count) = mysql_read(stream)?;
+    if count.first() == Some(&0xff) {
+        return Err(RecoverableDiscoveryError::Response.into());
+    }
+    loop {
+        let (_, packet) = mysql_read(stream)?;
+        if packet.first() == Some(&0xfe) && packet.len() < 9 {
+            break;
+        }
+    }
+
+    let mut rows = vec![];
+    loop {
+        let (_, packet) = mysql_read(stream)?;
+        if packet.first() == Some(&0xfe) && packet.len() < 9 {
+            return Ok(rows);
+        }
+        let length = *packet.first().ok_or(RecoverableDiscoveryError::Response)? as usize;
+        let value = packet
+            .get(1..1 + length)
+            .ok_or(RecoverableDiscoveryError::Response)?;
+        rows.push(String::from_utf8_lossy(value).to_string());
+    }
+}
+
+// Start a PostgreSQL session as postgres and see what it asks for, listing the databases if it asks for nothing
+fn postgresql(
+    ip_address: IpAddr,
+    port: u16,
+    timeout: Duration,
+) -> Result<DbReport, Box<dyn Error>> {
+    let mut stream = connect(ip_address, port, timeout)?;
+    let parameters = b"user\0postgres\0database\0postgres\0\0";
+    let mut startup = ((parameters.len() + 8) as u32).to_be_bytes().to_vec();
+    startup.extend(196608u32.to_be_bytes());
+    startup.extend(parameters);
+    stream.write_all(&startup)?;
+
+    let (kind, body) = postgresql_read(&mut stream)?;
+    let method = match (kind, body.get(..4)) {
+        (b'R', Some([0, 0, 0, 0])) => None,
+        (b'R', Some([0, 0, 0, 3])) => Some("a cleartext password"),
+        (b'R', Some([0, 0, 0, 5])) => Some("an MD5 password"),
+        (b'R', Some([0, 0, 0, 10])) => Some("a SCRAM-SHA-256 password"),
+        (b'E', _) => {
+            let error = postgresql_error(&body);
+            return Ok(DbReport {
+                raw: error.clone(),
+                status: format!("connection refused: {error}"),
+                ..Default::default()
+            });
+        }
+        _ => Some("an unknown authentication method"),
+    };
+    if let Some(method) = method {
+        return Ok(DbReport {
+            status: format!("postgres login needs {method}"),
+            ..Default::default()
+        });
+    }
+
+    // Skip the parameter status messages until the server is ready for a query
+    while postgresql_read(&mut stream)?.0 != b'Z' {}
+    let query = b"SELECT datname FROM pg_database\0";
+    let mut message = vec![b'Q'];
+    message.extend(((query.len() + 4) as u32).to_be_bytes());
+    message.extend(query);
+    stream.write_all(&message)?;
+
+    let mut inventory = vec![];
+    loop {
+        let (kind, body) = postgresql_read(&mut stream)?;
+        match kind {
+            b'D' => {
+                let length = i32::from_be_bytes(
+                    body.get(2..6)
+                        .ok_or(RecoverableDiscoveryError::Response)?
+                        .try_into()?,
+                );
+                if length > 0 {
+                    let value = body
+                        .get(6..6 + length as usize)
+                        .ok_or(RecoverableDiscoveryError::Response)?;
+                    inventory.push(String::from_utf8_lossy(value).to_string());
+                }
+            }
+            b'Z' | b'E' => break,
+            _ => (),
+        }
+    }
+
+    Ok(DbReport {
+        inventory,
+        open: true,
+        status: "postgres login works with no password (trust authentication)".to_string(),
+        ..Default::default()
+    })
+}
+
+fn postgresql_read(stream: &mut TcpStream) -> Result<(u8, Vec<u8>), Box<dyn Error>> {
+    let mut header = [0u8; 5];
+    stream.read_exact(&mut header)?;
+    let length = u32::from_be_bytes(header[1..].try_into()?) as usize;
+    if !(4..=1024 * 1024).contains(&length) {
+        return Err(RecoverableDiscoveryError::Response.into());
+    }
+    let mut body = vec![0u8; length - 4];
+    stream.read_exact(&mut body)?;
+    Ok((header[0], body))
+}
+
+// Error messages are a list of fields, of which M is the human readable message
+fn postgresql_error(body: &[u8]) -> String {
+    body.split(|&b| b == 0)
+        .find_map(|field| field.strip_prefix(b"M"))
+        .map(|message| String::from_utf8_lossy(message).to_string())
+        .unwrap_or_default()
+}
+
+// Default MSSQL logins, tried when the server lets the login go over the wire without TLS
+const MSSQL_CREDENTIALS: [(&str, &str); 2] = [("sa", ""), ("sa", "sa")];
+
+// Try the default sa logins, which only works in the clear when the server doesn't support encryption at all, and record the version either way
+fn mssql(ip_address: IpAddr, port: u16, timeout: Duration) -> Result<DbReport, Box<dyn Error>> {
+    let (_, version, encryption) = mssql_prelogin(ip_address, port, timeout)?;
+    let encryption_name = match encryption {
+        // ENCRYPT_OFF would encrypt just the login for a client that can, but imd says it can't, so nothing is encrypted
+        Some(0x00) => "off",
+        Some(0x01) => "on",
+        Some(0x02) => "not supported",
+        Some(0x03) => "required",
+        _ => "unknown",
+    };
+    let mut raw = format!("Version: {version}\nEncryption: {encryption_name}");
+
+    // imd doesn't speak TLS inside TDS, so the login can only be tried when the server is happy to go without it
+    if !matches!(encryption, Some(0x00) | Some(0x02)) {
+        return Ok(DbReport {
+            raw,
+            status: format!("version {version}, encryption {encryption_name}, so logins need TLS and default credentials like sa with no password were not tried"),
+            untested: true,
+            ..Default::default()
+        });
+    }
+
+    for (username, password) in MSSQL_CREDENTIALS {
+        let shown = match password.is_empty() {
+            true => "no password".to_string(),
+            false => format!("password {password}"),
+        };
+        let (mut stream, _, _) = mssql_prelogin(ip_address, port, timeout)?;
+        let (accepted, message) = mssql_login(&mut stream, username, password)?;
+        raw.push_str(&format!("\n{username} with {shown}: {message}"));
+        if accepted {
+            return Ok(DbReport {
+                open: true,
+                raw,
+                status: format!("version {version}, {username} login works with {shown}"),
+                ..Default::default()
+            });
+        }
+    }
+
+    Ok(DbReport {
+        raw,
+        status: format!("version {version}, sa logins with no password and sa:sa refused"),
+        ..Default::default()
+    })
+}
+
+// Send a prelogin, returning the connection along with the version and encryption byte the server answers with
+fn mssql_prelogin(
+    ip_address: IpAddr,
+    port: u16,
+    timeout: Duration,
+) -> Result<(TcpStream, String, Option<u8>), Box<dyn Error>> {
+    let mut stream = connect(ip_address, port, timeout)?;
+    // VERSION and ENCRYPTION options, then the terminator, then their data, saying imd doesn't support encryption
+    let options = [
+        0x00, 0x00, 0x0b, 0x00, 0x06, 0x01, 0x00, 0x11, 0x00, 0x01, 0xff, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x02,
+    ];
+    let mut packet = vec![0x12, 0x01];
+    packet.extend(((options.len() + 8) as u16).to_be_bytes());
+    packet.extend([0x00, 0x00, 0x00, 0x00]);
+    packet.extend(options);
+    stream.write_all(&packet)?;
+
+    let body = mssql_read(&mut stream)?;
+    let mut version = String::from("unknown");
+    let mut encryption = None;
+    let mut offset = 0;
+    while let Some(&token) = body.get(offset) {
+        if token == 0xff {
+            break;
+        }
+        let start = u16::from_be_bytes(
+            body.get(offset + 1..offset + 3)
+                .ok_or(RecoverableDiscoveryError::Response)?
+                .try_into()?,
+        ) as usize;
+        match (token, body.get(start..)) {
+            (0x00, Some(data)) if data.len() >= 4 => {
+                let build = u16::from_be_bytes([data[2], data[3]]);
+                version = format!("{}.{}.{build}", data[0], data[1]);
+            }
+            (0x01, Some(data)) if !data.is_empty() => encryption = Some(data[0]),
+            _ => (),
+        }
+        offset += 5;
+    }
+
+    Ok((stream, version, encryption))
+}
+
+// Send a LOGIN7 packet, returning whether the server acknowledged the login and its message if it didn't
+fn mssql_login(
+    stream: &mut TcpStream,
+    username: &str,
+    password: &str,
+) -> Result<(bool, String), Box<dyn Error>> {
+    let utf16 = |text: &str| -> Vec<u8> {
+        text.encode_utf16()
+            .flat_map(|unit| unit.to_le_bytes())
+            .collect()
+    };
+    // Passwords are obfuscated by swapping the nibbles of each byte and XORing with 0xA5
+    let obfuscated: Vec<u8> = utf16(password)
+        .into_iter()
+        .map(|byte| byte.rotate_left(4) ^ 0xa5)
+        .collect();
+    // Host, user, password, app, server, extension, library, language and database
+    let fields = [
+        utf16("imd"),
+        utf16(username),
+        obfuscated,
+        utf16("imd"),
+        vec![],
+        vec![],
+        utf16("imd"),
+        vec![],
+        vec![],
+    ];
+
+    // The fixed part of LOGIN7 is 94 bytes, with the variable data after it
+    const FIXED: usize = 94;
+    let length = FIXED + fields.iter().map(Vec::len).sum::<usize>();
+    let mut login = vec![];
+    login.extend((length as u32).to_le_bytes());
+    login.extend(0x7400_0004u32.to_le_bytes());
+    login.extend(4096u32.to_le_bytes());
+    login.extend(7u32.to_le_bytes());
+    login.extend(std::process::id().to_le_bytes());
+    login.extend(0u32.to_le_bytes());
+    login.extend([0xe0, 0x03, 0x00, 0x00]);
+    login.extend(0i32.to_le_bytes());
+    login.extend(0x0409u32.to_le_bytes());
+    let mut offset = FIXED;
+    for field in &fields {
+        login.extend((offset as u16).to_le_bytes());
+        login.extend(((field.len() / 2) as u16).to_le_bytes());
+        offset += field.len();
+    }
+    login.extend([0u8; 6]);
+    // No SSPI, database file to attach or password change
+    for _ in 0..3 {
+        login.extend((offset as u16).to_le_bytes());
+        login.extend(0u16.to_le_bytes());
+    }
+    login.extend(0u32.to_le_bytes());
+    for field in fields {
+        login.extend(field);
+    }
+
+    let mut packet = vec![0x10, 0x01];
+    packet.extend(((login.len() + 8) as u16).to_be_bytes());
+    packet.extend([0x00, 0x00, 0x01, 0x00]);
+    packet.extend(login);
+    stream.write_all(&packet)?;
+
+    let body = mssql_read(stream)?;
+    let mut i = 0;
+    while let Some(&token) = body.get(i) {
+        let length = body
+            .get(i + 1..i + 3)
+            .map(|bytes| u16::from_le_bytes([bytes[0], bytes[1]]) as usize)
+            .unwrap_or_default();
+        match token {
+            // LOGINACK
+            0xad => return Ok((true, "login acknowledged".to_string())),
+            // ERROR: number, state and class, then the message as a character count and UTF-16
+            0xaa => {
+                let characters = body
+                    .get(i + 9..i + 11)
+                    .map(|bytes| u16::from_le_bytes([bytes[0], bytes[1]]) as usize)
+                    .unwrap_or_default();
+                let units: Vec<u16> = body
+                    .get(i + 11..i + 11 + characters * 2)
+                    .unwrap_or_default()
+                    .chunks(2)
+                    .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+                    .collect();
+                return Ok((false, String::from_utf16_lossy(&units)));
+            }
+            // ENVCHANGE and INFO, which come before the LOGINACK
+            0xe3 | 0xab => i += 3 + length,
+            // DONE
+            0xfd => i += 13,
+            _ => break,
+        }
+    }
+    Ok((false, "no login acknowledgement".to_string()))
+}
+
+// Read a whole TDS message, which can be split over several packets
+fn mssql_read(stream: &mut TcpStream) -> Result<Vec<u8>, Box<dyn Error>> {
+    let mut body = vec![];
+    loop {
+        let mut header = [0u8; 8];
+        stream.read_exact(&mut header)?;
+        let length = u16::from_be_bytes([header[2], header[3]]) as usize;
+        let mut packet = vec![0u8; length.saturating_sub(8)];
+        stream.read_exact(&mut packet)?;
+        body.extend(packet);
+        // The status byte marks the last packet of the message
+        if header[1] & 0x01 != 0 {
+            return Ok(body);
+        }
+    }
+}
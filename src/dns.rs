@@ -0,0 +1,62 @@
+use crate::hostnames;
+
+// Port DNS is served on, over both TCP and UDP
+pub const DNS_PORT: u16 = 53;
+
+// Record types whose owner names are hosts worth adding to the target
+const HOST_RECORDS: [&str; 3] = ["A", "AAAA", "CNAME"];
+
+// The zones worth asking for: each known hostname, and the domain it sits in if that isn't a bare TLD
+pub fn candidate_domains(known: &[String]) -> Vec<String> {
+    let mut domains: Vec<String> = vec![];
+    for hostname in known {
+        let parent = hostname
+            .split_once('.')
+            .map(|(_, parent)| parent.to_string());
+        for domain in [Some(hostname.clone()), parent].into_iter().flatten() {
+            if domain.contains('.') && !domains.contains(&domain) {
+                domains.push(domain);
+            }
+        }
+    }
+    domains
+}
+
+// A transfer that worked starts (and ends) with the zone's SOA record
+pub fn axfr_succeeded(output: &str) -> bool {
+    records(output).any(|(_, kind, _)| kind == "SOA")
+}
+
+// Pull the host names out of `dig axfr` output, along with the targets of PTR records from reverse lookups
+pub fn hostnames_from_records(output: &str) -> Vec<String> {
+    let mut found: Vec<String> = vec![];
+    for (name, kind, data) in records(output) {
+        let name = match kind {
+            _ if HOST_RECORDS.contains(&kind) => hostnames::clean(name),
+            "PTR" => hostnames::clean(data),
+            _ => None,
+        };
+        if let Some(name) = name {
+            if !found.contains(&name) {
+                found.push(name);
+            }
+        }
+    }
+    found
+}
+
+// Records in dig's output look like "name. 3600 IN A 10.10.10.10"
+fn records(output: &str) -> impl Iterator<Item = (&str, &str, &str)> {
+    output
+        .lines()
+        .filter(|line| !line.starts_with(';'))
+        .filter_map(|line| {
+            let columns: Vec<&str> = line.split_whitespace().collect();
+            match columns[..] {
+                [name, _, "IN", kind, ref data @ ..] if !data.is_empty() => {
+                    Some((name, kind, data[0]))
+                }
+                _ => None,
+            }
+        })
+}
@@ -4,7 +4,15 @@ use std::fmt;
 
 #[derive(Debug)]
 pub enum PanicDiscoveryError {
+    InvalidExtensionRules,
+    InvalidFollowUp,
+    InvalidHostnameMode,
     InvalidIPAddress,
+    InvalidPorts,
+    InvalidScanner,
+    InvalidSmbTool,
+    InvalidVulnScanner,
+    InvalidWebScanner,
     InvalidWordlist,
     NotRunAsRoot,
 }
@@ -12,9 +20,33 @@ pub enum PanicDiscoveryError {
 impl PanicDiscoveryError {
     pub fn as_str(&self) -> StyledContent<&str> {
         match self {
+            PanicDiscoveryError::InvalidExtensionRules => {
+                "The provided value is not none, a file of rules or rules like iis=asp,aspx;php=php,phps+/path/to/wordlist".red()
+            }
+            PanicDiscoveryError::InvalidFollowUp => {
+                "The provided value is not none or a list of droopescan, gitlab, jenkins, tomcat-manager and wpscan".red()
+            }
+            PanicDiscoveryError::InvalidHostnameMode => {
+                "The provided value is not one of auto, ignore or prompt".red()
+            }
             PanicDiscoveryError::InvalidIPAddress => {
                 "The provided value does not contain a valid IP address".red()
             }
+            PanicDiscoveryError::InvalidPorts => {
+                "The provided value is not a valid list of ports, E.G. 22,80,8000-8100".red()
+            }
+            PanicDiscoveryError::InvalidScanner => {
+                "The provided value is not a supported port scanner".red()
+            }
+            PanicDiscoveryError::InvalidSmbTool => {
+                "The provided value is not one of enum4linux-ng, smbclient or smbmap".red()
+            }
+            PanicDiscoveryError::InvalidVulnScanner => {
+                "The provided value is not one of both, nikto or nuclei".red()
+            }
+            PanicDiscoveryError::InvalidWebScanner => {
+                "The provided value is not one of dirsearch, feroxbuster, ffuf or gobuster".red()
+            }
             PanicDiscoveryError::InvalidWordlist => "The provided value is not a valid file".red(),
             PanicDiscoveryError::NotRunAsRoot => {
                 "✕ imd must be run as root. Try `sudo !!` to retry that command with sudo".red()
@@ -36,7 +68,9 @@ pub enum RecoverableDiscoveryError {
     AlreadyInHost,
     Connection,
     DirectoryExists,
+    Response,
     Services,
+    Wordlist,
 }
 
 impl RecoverableDiscoveryError {
@@ -47,9 +81,15 @@ impl RecoverableDiscoveryError {
                 "✕ Could not ping host, it might be down".red()
             }
             RecoverableDiscoveryError::DirectoryExists => "〰 Directory already exists".yellow(),
+            RecoverableDiscoveryError::Response => {
+                "✕ Could not understand the service's response".red()
+            }
             RecoverableDiscoveryError::Services => {
                 "✕ Could not discover host services, ending discovery".red()
             }
+            RecoverableDiscoveryError::Wordlist => {
+                "〰 Wordlist is missing or empty, skipping".yellow()
+            }
         }
     }
 }
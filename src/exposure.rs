@@ -0,0 +1,1081 @@
+use crate::content::{url_path, ContentHit};
+use crate::http::{HttpClient, HttpResponse};
+use crate::{command_exists, create_dirs, create_file, IMDUser};
+use std::collections::{HashSet, VecDeque};
+use std::error::Error;
+use std::io::Write;
+use std::path::Path;
+use std::process::Command;
+use std::sync::Arc;
+
+// Directories and files from content discovery probed on top of the web root, so a huge scan doesn't mean thousands of requests
+const MAX_DIRECTORIES: usize = 20;
+const MAX_FILES: usize = 30;
+
+// Objects fetched while rebuilding an exposed .git, which is plenty for the small repositories CTFs leave lying around
+const MAX_GIT_OBJECTS: usize = 2000;
+
+// Backups and dumps tried in every directory
+const BACKUP_NAMES: [&str; 17] = [
+    "backup.zip",
+    "backup.tar.gz",
+    "backup.sql",
+    "site.zip",
+    "www.zip",
+    "web.zip",
+    "html.zip",
+    "db.sql",
+    "database.sql",
+    "dump.sql",
+    "index.php.bak",
+    "index.php~",
+    "config.php.bak",
+    "config.php~",
+    "wp-config.php.bak",
+    "web.config.bak",
+    ".index.php.swp",
+];
+
+// Suffixes editors and admins leave on copies of the files content discovery found
+const BACKUP_SUFFIXES: [&str; 3] = [".bak", "~", ".old"];
+
+// Files under .git worth saving as they are, besides the objects
+const GIT_FILES: [&str; 13] = [
+    "HEAD",
+    "ORIG_HEAD",
+    "FETCH_HEAD",
+    "COMMIT_EDITMSG",
+    "description",
+    "index",
+    "packed-refs",
+    "info/exclude",
+    "logs/HEAD",
+    "refs/heads/master",
+    "refs/heads/main",
+    "refs/stash",
+    "objects/info/packs",
+];
+
+// How much of a file is read to decide what it is, asked for with a Range header so big files aren't downloaded just to check them
+const PROBE_SIZE: usize = 64 * 1024;
+
+// Room for the status line and headers on top of the body when limiting how much of a response is read
+const HEADER_ALLOWANCE: usize = 16 * 1024;
+
+// A name that is never real, to learn what the server does with a missing backup in a directory
+const BASELINE_NAME: &str = "imd-baseline-5d0c.bak";
+
+// The four character codes that follow file names in a .DS_Store's records
+const DS_STORE_CODES: [&[u8; 4]; 18] = [
+    b"Iloc", b"bwsp", b"lsvp", b"lsvP", b"icvp", b"vSrn", b"dscl", b"moDD", b"modD", b"ph1S",
+    b"lg1S", b"cmmt", b"fwi0", b"fwsw", b"fwvh", b"ICVO", b"LSVO", b"vstl",
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ArtifactKind {
+    Backup,
+    DsStore,
+    Env,
+    Git,
+    Svn,
+}
+
+impl ArtifactKind {
+    pub fn as_str(&self) -> &str {
+        match self {
+            ArtifactKind::Backup => "backup",
+            ArtifactKind::DsStore => ".DS_Store",
+            ArtifactKind::Env => ".env",
+            ArtifactKind::Git => ".git",
+            ArtifactKind::Svn => ".svn",
+        }
+    }
+
+    // .DS_Store only gives away file names, everything else can hand over source code or secrets
+    pub fn serious(&self) -> bool {
+        !matches!(self, ArtifactKind::DsStore)
+    }
+}
+
+// Something the web server shouldn't be handing out, and what became of it
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Artifact {
+    kind: ArtifactKind,
+    note: String,
+    path: String,
+    size: usize,
+}
+
+impl Artifact {
+    pub fn kind(&self) -> ArtifactKind {
+        self.kind
+    }
+
+    // What was saved, or why it wasn't
+    pub fn note(&self) -> &String {
+        &self.note
+    }
+
+    pub fn path(&self) -> &String {
+        &self.path
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    // One line of web_exposures
+    pub fn line(&self) -> String {
+        format!(
+            "{: <11}{: >10}  {}  {}",
+            self.kind.as_str(),
+            self.size,
+            self.path,
+            self.note
+        )
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ExposureReport {
+    artifacts: Vec<Artifact>,
+    directories: Vec<String>,
+    files: Vec<String>,
+    git: Option<GitDump>,
+}
+
+impl ExposureReport {
+    pub fn artifacts(&self) -> &Vec<Artifact> {
+        &self.artifacts
+    }
+
+    pub fn directories(&self) -> &Vec<String> {
+        &self.directories
+    }
+
+    pub fn files(&self) -> &Vec<String> {
+        &self.files
+    }
+
+    pub fn git(&self) -> Option<&GitDump> {
+        self.git.as_ref()
+    }
+}
+
+// How much of an exposed .git made it back
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct GitDump {
+    directory: String,
+    missing: Vec<String>,
+    objects: usize,
+    restored: Vec<String>,
+    too_big: Vec<String>,
+    url: String,
+}
+
+impl GitDump {
+    pub fn directory(&self) -> &String {
+        &self.directory
+    }
+
+    // Objects that were referenced but couldn't be fetched
+    pub fn missing(&self) -> &Vec<String> {
+        &self.missing
+    }
+
+    pub fn objects(&self) -> usize {
+        self.objects
+    }
+
+    // Working tree files written out from the objects
+    pub fn restored(&self) -> &Vec<String> {
+        &self.restored
+    }
+
+    // Files that weren't saved because they are over the size cap, like big pack files
+    pub fn too_big(&self) -> &Vec<String> {
+        &self.too_big
+    }
+
+    pub fn url(&self) -> &String {
+        &self.url
+    }
+}
+
+// Where the finds get saved: exposed .git repositories are rebuilt in git_dir, everything else is mirrored under loot_dir
+#[derive(Clone, Debug)]
+pub struct LootDirs {
+    git_dir: String,
+    loot_dir: String,
+    max_size: usize,
+    user: Arc<IMDUser>,
+}
+
+impl LootDirs {
+    pub fn new(git_dir: &str, loot_dir: &str, max_size: u64, user: Arc<IMDUser>) -> LootDirs {
+        LootDirs {
+            git_dir: git_dir.to_string(),
+            loot_dir: loot_dir.to_string(),
+            max_size: usize::try_from(max_size).unwrap_or(usize::MAX),
+            user,
+        }
+    }
+
+    // Write a file relative to one of the loot directories, creating whatever directories it needs
+    fn save(&self, dir: &str, path: &str, contents: &[u8]) -> Result<String, Box<dyn Error>> {
+        let local = format!("{dir}/{}", path.trim_start_matches('/'));
+        if let Some(parent) = Path::new(&local).parent() {
+            create_dirs(&parent.to_string_lossy(), self.user.clone())?;
+        }
+        let mut f = create_file(&local, self.user.clone())?;
+        f.write_all(contents)?;
+        Ok(local)
+    }
+}
+
+// The directories and files worth probing: the web root, plus what content discovery found, capped so the step stays quick
+pub fn targets(hits: &[ContentHit]) -> (Vec<String>, Vec<String>) {
+    let mut directories = vec!["/".to_string()];
+    let mut files = vec![];
+    for hit in hits {
+        let path = url_path(hit.url())
+            .split(['?', '#'])
+            .next()
+            .unwrap_or_default();
+        if path.contains("..") || !path.starts_with('/') {
+            continue;
+        }
+        let redirects_to_directory = hit
+            .redirect()
+            .is_some_and(|redirect| url_path(redirect) == format!("{path}/"));
+        let directory = match path.ends_with('/') {
+            true if hit.status() < 404 => Some(path.to_string()),
+            false if redirects_to_directory => Some(format!("{path}/")),
+            _ => None,
+        };
+        match directory {
+            Some(directory) => {
+                if !directories.contains(&directory) && directories.len() < MAX_DIRECTORIES {
+                    directories.push(directory);
+                }
+            }
+            None => {
+                let name = path.rsplit('/').next().unwrap_or_default();
+                let path = path.to_string();
+                if hit.status() == 200
+                    && name.contains('.')
+                    && !files.contains(&path)
+                    && files.len() < MAX_FILES
+                {
+                    files.push(path);
+                }
+            }
+        }
+    }
+    (directories, files)
+}
+
+// Probe every directory for version control, .DS_Store, .env and backups, and every file for a backup copy of it
+pub fn probe(
+    client: &HttpClient,
+    host: &str,
+    directories: &[String],
+    files: &[String],
+    loot: &LootDirs,
+) -> ExposureReport {
+    let mut report = ExposureReport {
+        directories: directories.to_vec(),
+        files: files.to_vec(),
+        ..Default::default()
+    };
+
+    for directory in directories {
+        let baseline = fetch(client, host, &format!("{directory}{BASELINE_NAME}"));
+
+        let git_head = format!("{directory}.git/HEAD");
+        if let Some(response) = fetch(client, host, &git_head) {
+            let head = response.text();
+            if is_git_head(&head) {
+                let note = match (&report.git, command_exists("git")) {
+                    (Some(_), _) => "another .git was already rebuilt, not rebuilt".to_string(),
+                    (None, true) => {
+                        let dump = dump_git(client, host, &format!("{directory}.git/"), loot);
+                        let note = format!(
+                            "rebuilt {} files from {} objects in {}",
+                            dump.restored.len(),
+                            dump.objects,
+                            dump.directory
+                        );
+                        report.git = Some(dump);
+                        note
+                    }
+                    (None, false) => save_note(
+                        loot.save(&loot.git_dir, "HEAD", response.body()),
+                        "git is not installed so only HEAD was",
+                    ),
+                };
+                report.artifacts.push(Artifact {
+                    kind: ArtifactKind::Git,
+                    note,
+                    path: git_head,
+                    size: response.body().len(),
+                });
+            }
+        }
+
+        for name in [".svn/wc.db", ".svn/entries"] {
+            let path = format!("{directory}{name}");
+            if let Some(response) = fetch(client, host, &path) {
+                if is_svn(name, response.body()) {
+                    report.artifacts.push(keep(
+                        ArtifactKind::Svn,
+                        &path,
+                        &response,
+                        client,
+                        host,
+                        loot,
+                    ));
+                    break;
+                }
+            }
+        }
+
+        let path = format!("{directory}.DS_Store");
+        if let Some(response) = fetch(client, host, &path) {
+            if response.body().starts_with(b"\0\0\0\x01Bud1") {
+                let mut artifact =
+                    keep(ArtifactKind::DsStore, &path, &response, client, host, loot);
+                let names = ds_store_names(response.body());
+                if !names.is_empty() {
+                    artifact.note = format!("{}, lists {}", artifact.note, names.join(", "));
+                }
+                report.artifacts.push(artifact);
+            }
+        }
+
+        let path = format!("{directory}.env");
+        if let Some(response) = fetch(client, host, &path) {
+            if is_env(&response.text()) {
+                report.artifacts.push(keep(
+                    ArtifactKind::Env,
+                    &path,
+                    &response,
+                    client,
+                    host,
+                    loot,
+                ));
+            }
+        }
+
+        let mut backups: Vec<String> = BACKUP_NAMES
+            .iter()
+            .map(|name| format!("{directory}{name}"))
+            .collect();
+        if directory == "/" {
+            backups.push(format!("/{host}.zip"));
+        }
+        for path in backups {
+            if let Some(response) = fetch(client, host, &path) {
+                if is_backup(&path, &response, baseline.as_ref()) {
+                    report.artifacts.push(keep(
+                        ArtifactKind::Backup,
+                        &path,
+                        &response,
+                        client,
+                        host,
+                        loot,
+                    ));
+                }
+            }
+        }
+    }
+
+    for file in files {
+        let (directory, name) = file.rsplit_once('/').unwrap_or_default();
+        let baseline = fetch(client, host, &format!("{directory}/{BASELINE_NAME}"));
+        let mut copies: Vec<String> = BACKUP_SUFFIXES
+            .iter()
+            .map(|suffix| format!("{file}{suffix}"))
+            .collect();
+        copies.push(format!("{directory}/.{name}.swp"));
+        copies.retain(|copy| {
+            !report
+                .artifacts
+                .iter()
+                .any(|artifact| &artifact.path == copy)
+        });
+        for path in copies {
+            if let Some(response) = fetch(client, host, &path) {
+                if is_backup(&path, &response, baseline.as_ref()) {
+                    report.artifacts.push(keep(
+                        ArtifactKind::Backup,
+                        &path,
+                        &response,
+                        client,
+                        host,
+                        loot,
+                    ));
+                }
+            }
+        }
+    }
+
+    report
+}
+
+// GET the start of a path, keeping the response only if the server says it is there
+fn fetch(client: &HttpClient, host: &str, path: &str) -> Option<HttpResponse> {
+    let range = format!("bytes=0-{}", PROBE_SIZE - 1);
+    client
+        .clone()
+        .with_max_response_size(PROBE_SIZE + HEADER_ALLOWANCE)
+        .request("GET", host, path, &[("Range", &range)], &[])
+        .ok()
+        .filter(|response| matches!(response.status(), 200 | 206) && !response.body().is_empty())
+}
+
+// The size of the whole file, from Content-Range when the server honoured the Range header and Content-Length when it didn't
+fn total_size(response: &HttpResponse) -> Option<usize> {
+    match response.status() {
+        206 => response
+            .header("Content-Range")
+            .and_then(|range| range.rsplit('/').next())
+            .and_then(|total| total.trim().parse().ok()),
+        _ => response
+            .header("Content-Length")
+            .and_then(|length| length.trim().parse().ok()),
+    }
+}
+
+// The whole of a file the probe only saw the start of, read up to the size cap
+enum Download {
+    Complete(Vec<u8>),
+    Failed,
+    TooBig(usize),
+    Truncated(Vec<u8>),
+}
+
+fn download(
+    client: &HttpClient,
+    host: &str,
+    path: &str,
+    probe: &HttpResponse,
+    max_size: usize,
+) -> Download {
+    let size = total_size(probe);
+    match size {
+        Some(size) if size > max_size => return Download::TooBig(size),
+        Some(size) if size <= probe.body().len() => {
+            return Download::Complete(probe.body()[..size].to_vec());
+        }
+        // A probe that came back short with no size given is already the whole file
+        None if probe.body().len() < PROBE_SIZE.min(max_size) => {
+            return Download::Complete(probe.body().clone());
+        }
+        _ => {}
+    }
+    let response = client
+        .clone()
+        .with_max_response_size(max_size.saturating_add(HEADER_ALLOWANCE))
+        .get(host, path);
+    let mut body = match response {
+        Ok(response) if response.status() == 200 => response.body().clone(),
+        _ => return Download::Failed,
+    };
+    let truncated = match size {
+        Some(size) => body.len() < size,
+        None => body.len() >= max_size,
+    };
+    body.truncate(max_size);
+    match truncated {
+        true => Download::Truncated(body),
+        false => Download::Complete(body),
+    }
+}
+
+// Save an artifact under the loot directory, downloading the rest of it if it is under the size cap
+fn keep(
+    kind: ArtifactKind,
+    path: &str,
+    probe: &HttpResponse,
+    client: &HttpClient,
+    host: &str,
+    loot: &LootDirs,
+) -> Artifact {
+    let size = total_size(probe).unwrap_or(probe.body().len());
+    let note = match download(client, host, path, probe, loot.max_size) {
+        Download::Complete(contents) => {
+            save_note(loot.save(&loot.loot_dir, path, &contents), "saved")
+        }
+        Download::Truncated(contents) => save_note(
+            loot.save(&loot.loot_dir, path, &contents),
+            "truncated at the size cap and saved",
+        ),
+        Download::TooBig(_) => "over the size cap, not downloaded".to_string(),
+        Download::Failed => "could not be downloaded".to_string(),
+    };
+    Artifact {
+        kind,
+        note,
+        path: path.to_string(),
+        size,
+    }
+}
+
+fn save_note(saved: Result<String, Box<dyn Error>>, verb: &str) -> String {
+    match saved {
+        Ok(local) => format!("{verb} to {local}"),
+        Err(e) => format!("could not be saved: {e}"),
+    }
+}
+
+// Catch-all pages come back for anything, so the content has to look like what was asked for
+fn looks_like_html(body: &[u8]) -> bool {
+    let start = String::from_utf8_lossy(&body[..body.len().min(512)]).to_lowercase();
+    start.contains("<html") || start.contains("<!doctype") || start.contains("<head")
+}
+
+fn is_git_head(head: &str) -> bool {
+    let head = head.trim();
+    head.starts_with("ref: refs/") || (head.len() == 40 && is_sha(head))
+}
+
+fn is_sha(text: &str) -> bool {
+    text.len() == 40 && text.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+fn is_svn(name: &str, body: &[u8]) -> bool {
+    match name.ends_with("wc.db") {
+        true => body.starts_with(b"SQLite format 3"),
+        false => {
+            let text = String::from_utf8_lossy(body);
+            let first = text.lines().next().unwrap_or_default().trim();
+            !first.is_empty() && first.chars().all(|c| c.is_ascii_digit())
+        }
+    }
+}
+
+// At least one KEY=value line and no markup
+fn is_env(text: &str) -> bool {
+    !looks_like_html(text.as_bytes())
+        && text.lines().any(|line| {
+            let line = line.trim().trim_start_matches("export ");
+            match line.split_once('=') {
+                Some((key, _)) => {
+                    !key.is_empty()
+                        && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+                        && !key.starts_with(|c: char| c.is_ascii_digit())
+                }
+                None => false,
+            }
+        })
+}
+
+// Archives have magic bytes, dumps have SQL in them, and copies of source files have code the server would otherwise run
+fn is_backup(path: &str, response: &HttpResponse, baseline: Option<&HttpResponse>) -> bool {
+    let body = response.body();
+    if baseline.is_some_and(|baseline| baseline.body() == body) {
+        return false;
+    }
+    let name = path.rsplit('/').next().unwrap_or_default().to_lowercase();
+    if name.ends_with(".zip") {
+        return body.starts_with(b"PK\x03\x04");
+    }
+    if name.ends_with(".gz") || name.ends_with(".tgz") {
+        return body.starts_with(&[0x1f, 0x8b]);
+    }
+    if name.ends_with(".swp") {
+        return body.starts_with(b"b0VIM");
+    }
+    if looks_like_html(body) {
+        return false;
+    }
+    let text = String::from_utf8_lossy(body).to_lowercase();
+    match name.ends_with(".sql") {
+        true => {
+            text.contains("create table") || text.contains("insert into") || text.contains("dump")
+        }
+        false => {
+            text.contains("<?php")
+                || text.contains("<%")
+                || text.contains("<configuration")
+                || text.contains("password")
+        }
+    }
+}
+
+// .DS_Store records start with a big-endian length and a UTF-16 file name, followed by a four character code
+fn ds_store_names(body: &[u8]) -> Vec<String> {
+    let mut names = vec![];
+    let mut i = 0;
+    while i + 4 < body.len() {
+        let length = u32::from_be_bytes([body[i], body[i + 1], body[i + 2], body[i + 3]]) as usize;
+        let end = i + 4 + length * 2;
+        if length == 0 || length > 255 || end + 4 > body.len() {
+            i += 1;
+            continue;
+        }
+        let code = &body[end..end + 4];
+        if !DS_STORE_CODES.iter().any(|known| known.as_slice() == code) {
+            i += 1;
+            continue;
+        }
+        let units: Vec<u16> = body[i + 4..end]
+            .chunks(2)
+            .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+            .collect();
+        match String::from_utf16(&units) {
+            Ok(name) if !name.chars().any(char::is_control) => {
+                if !names.contains(&name) {
+                    names.push(name);
+                }
+                i = end + 4;
+            }
+            _ => i += 1,
+        }
+    }
+    names
+}
+
+// Rebuild an exposed .git: save the refs and index, walk the objects from every commit and index entry, then write the working tree back out with git
+fn dump_git(client: &HttpClient, host: &str, base: &str, loot: &LootDirs) -> GitDump {
+    let directory = loot.git_dir.clone();
+    let git_dir = format!("{directory}/.git");
+    let mut dump = GitDump {
+        directory: directory.clone(),
+        url: client.url(host, base),
+        ..Default::default()
+    };
+
+    // git acts on settings in the server's config (fsmonitor, pager, sshCommand and the like), both here and whenever the
+    // loot is looked at later, so it is kept as config.server for reading and never used as the repository's config
+    let _ = loot.save(
+        &git_dir,
+        "config",
+        b"[core]\n\trepositoryformatversion = 0\n\tbare = false\n",
+    );
+    let _ = create_dirs(&format!("{git_dir}/objects"), loot.user.clone());
+    let _ = create_dirs(&format!("{git_dir}/refs/heads"), loot.user.clone());
+    if let Some(config) = git_file(client, host, &format!("{base}config"), loot, &mut dump) {
+        let _ = loot.save(&git_dir, "config.server", &config);
+    }
+
+    let mut files: Vec<String> = GIT_FILES.iter().map(|file| file.to_string()).collect();
+    let mut queue = VecDeque::new();
+    let mut index = vec![];
+    let mut i = 0;
+    while i < files.len() {
+        let file = files[i].clone();
+        i += 1;
+        let contents = match git_file(client, host, &format!("{base}{file}"), loot, &mut dump) {
+            Some(contents) => contents,
+            None => continue,
+        };
+        let _ = loot.save(&git_dir, &file, &contents);
+        if file == "index" {
+            index = git_index(&contents);
+            continue;
+        }
+        if file.starts_with("objects/pack/") {
+            continue;
+        }
+        let text = String::from_utf8_lossy(&contents);
+        for line in text.lines() {
+            if let Some(reference) = line.strip_prefix("ref: ") {
+                let reference = reference.trim().to_string();
+                if reference.starts_with("refs/")
+                    && !reference.contains("..")
+                    && !files.contains(&reference)
+                {
+                    files.push(reference);
+                }
+            }
+            if let Some(pack) = line.strip_prefix("P ") {
+                let pack = pack.trim();
+                if pack.starts_with("pack-") && !pack.contains('/') {
+                    let name = pack.trim_end_matches(".pack");
+                    files.push(format!("objects/pack/{name}.pack"));
+                    files.push(format!("objects/pack/{name}.idx"));
+                }
+            }
+            queue.extend(
+                line.split_whitespace()
+                    .filter(|word| is_sha(word))
+                    .map(str::to_string),
+            );
+        }
+    }
+    queue.extend(index.iter().map(|(sha, _)| sha.clone()));
+
+    let mut seen = HashSet::new();
+    while let Some(sha) = queue.pop_front() {
+        if sha.chars().all(|c| c == '0') || !seen.insert(sha.clone()) {
+            continue;
+        }
+        if seen.len() > MAX_GIT_OBJECTS {
+            break;
+        }
+        if git(&directory, &["cat-file", "-e", &sha]).is_none() {
+            let path = format!("objects/{}/{}", &sha[..2], &sha[2..]);
+            match git_file(client, host, &format!("{base}{path}"), loot, &mut dump) {
+                Some(contents) => {
+                    let _ = loot.save(&git_dir, &path, &contents);
+                }
+                None => {
+                    dump.missing.push(sha);
+                    continue;
+                }
+            }
+        }
+        let kind = match git(&directory, &["cat-file", "-t", &sha]) {
+            Some(kind) => String::from_utf8_lossy(&kind).trim().to_string(),
+            None => {
+                dump.missing.push(sha);
+                continue;
+            }
+        };
+        dump.objects += 1;
+        let contents = match kind.as_str() {
+            "commit" | "tag" | "tree" => git(&directory, &["cat-file", "-p", &sha]),
+            _ => None,
+        };
+        let contents = String::from_utf8_lossy(&contents.unwrap_or_default()).to_string();
+        for line in contents.lines() {
+            let words: Vec<&str> = line.split_whitespace().collect();
+            let referenced = match (kind.as_str(), words.as_slice()) {
+                ("commit", ["tree" | "parent", sha]) | ("tag", ["object", sha]) => Some(sha),
+                ("tree", [_, "blob" | "tree", sha, ..]) => Some(sha),
+                _ => None,
+            };
+            if let Some(referenced) = referenced.filter(|sha| is_sha(sha)) {
+                queue.push_back(referenced.to_string());
+            }
+        }
+    }
+
+    // Prefer the index's view of the working tree, falling back on the tree of whatever HEAD points at
+    let mut entries = index;
+    if entries.is_empty() {
+        let listing =
+            git(&directory, &["ls-tree", "-r", "--full-tree", "HEAD"]).unwrap_or_default();
+        entries = String::from_utf8_lossy(&listing)
+            .lines()
+            .filter_map(|line| {
+                let (meta, path) = line.split_once('\t')?;
+                let sha = meta.split_whitespace().nth(2)?;
+                Some((sha.to_string(), path.to_string()))
+            })
+            .collect();
+    }
+    for (sha, path) in entries {
+        if !is_tree_path(&path) {
+            continue;
+        }
+        if let Some(contents) = git(&directory, &["cat-file", "blob", &sha]) {
+            if loot.save(&directory, &path, &contents).is_ok() {
+                dump.restored.push(path);
+            }
+        }
+    }
+
+    dump
+}
+
+// Download a file from an exposed .git in full, noting it instead if it is over the size cap, since a cut off pack or index is no use
+fn git_file(
+    client: &HttpClient,
+    host: &str,
+    path: &str,
+    loot: &LootDirs,
+    dump: &mut GitDump,
+) -> Option<Vec<u8>> {
+    let probe = fetch(client, host, path).filter(|probe| !looks_like_html(probe.body()))?;
+    match download(client, host, path, &probe, loot.max_size) {
+        Download::Complete(contents) => Some(contents),
+        Download::TooBig(size) => {
+            dump.too_big.push(format!("{path} ({size} bytes)"));
+            None
+        }
+        Download::Truncated(_) => {
+            dump.too_big.push(path.to_string());
+            None
+        }
+        Download::Failed => None,
+    }
+}
+
+// Run git against the rebuilt repository, returning its output if it succeeded
+fn git(directory: &str, args: &[&str]) -> Option<Vec<u8>> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(directory)
+        .args(args)
+        .output()
+        .ok()?;
+    match output.status.success() {
+        true => Some(output.stdout),
+        false => None,
+    }
+}
+
+// Whether a path from the server's index or trees stays inside the working tree, rather than escaping it or writing into .git
+fn is_tree_path(path: &str) -> bool {
+    !path.is_empty()
+        && !path.starts_with('/')
+        && !path.split('/').any(|part| part == ".." || part == ".git")
+}
+
+// Read the object IDs and paths out of a version 2 or 3 git index, which lists every file in the working tree
+fn git_index(body: &[u8]) -> Vec<(String, String)> {
+    let mut entries = vec![];
+    if body.len() < 12 || !body.starts_with(b"DIRC") {
+        return entries;
+    }
+    let version = u32::from_be_bytes([body[4], body[5], body[6], body[7]]);
+    if version != 2 && version != 3 {
+        return entries;
+    }
+    let count = u32::from_be_bytes([body[8], body[9], body[10], body[11]]) as usize;
+    let mut offset = 12;
+    for _ in 0..count {
+        // ctime, mtime, dev, ino, mode, uid, gid and size come before the object ID and flags
+        let sha_start = offset + 40;
+        let flags_start = sha_start + 20;
+        if flags_start + 2 > body.len() {
+            break;
+        }
+        let sha: String = body[sha_start..flags_start]
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect();
+        let flags = u16::from_be_bytes([body[flags_start], body[flags_start + 1]]);
+        let mut path_start = flags_start + 2;
+        if version == 3 && flags & 0x4000 != 0 {
+            path_start += 2;
+        }
+        let path_end = match body[path_start.min(body.len())..]
+            .iter()
+            .position(|&byte| byte == 0)
+        {
+            Some(length) => path_start + length,
+            None => break,
+        };
+        entries.push((
+            sha,
+            String::from_utf8_lossy(&body[path_start..path_end]).to_string(),
+        ));
+        // Entries are padded with NULs to a multiple of eight bytes
+        let length = path_end - offset;
+        offset += (length / 8 + 1) * 8;
+    }
+    entries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // What git add writes for README.md and src/main.rs
+    const INDEX_V2: &[u8] = b"\
+        \x44\x49\x52\x43\x00\x00\x00\x02\x00\x00\x00\x02\x6a\xd5\x3e\xa2\
+        \x1e\x21\x9d\x0f\x6a\xd5\x3e\xa2\x1e\x21\x9d\x0f\x00\x00\xfe\x00\
+        \x00\x12\xc0\x3e\x00\x00\x81\xa4\x00\x00\x00\x00\x00\x00\x00\x00\
+        \x00\x00\x00\x03\x45\xb9\x83\xbe\x36\xb7\x3c\x07\x88\xdc\x9c\xbc\
+        \xb7\x6c\xbb\x80\xfc\x7b\xb0\x57\x00\x09\x52\x45\x41\x44\x4d\x45\
+        \x2e\x6d\x64\x00\x6a\xd5\x3e\xa2\x1e\x21\x9d\x0f\x6a\xd5\x3e\xa2\
+        \x1e\x21\x9d\x0f\x00\x00\xfe\x00\x00\x12\xc0\x3f\x00\x00\x81\xa4\
+        \x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x0d\xf3\x28\xe4\xd9\
+        \xd0\x4c\x31\xd0\xd7\x0d\x16\xd2\x1a\x07\xd1\x61\x3b\xe9\xd5\x77\
+        \x00\x0b\x73\x72\x63\x2f\x6d\x61\x69\x6e\x2e\x72\x73\x00\x00\x00\
+        \x00\x00\x00\x00\x2a\x47\xc2\xfd\xd7\xb2\x49\xc6\xd5\x4c\x1b\xfe\
+        \x9a\xee\xf2\xe7\x87\xe9\x1f\xcc";
+
+    // The same index after git add -N new.txt and git update-index --index-version 3, so new.txt has extended flags
+    const INDEX_V3: &[u8] = b"\
+        \x44\x49\x52\x43\x00\x00\x00\x03\x00\x00\x00\x03\x6a\xd5\x3e\xa2\
+        \x1e\x21\x9d\x0f\x6a\xd5\x3e\xa2\x1e\x21\x9d\x0f\x00\x00\xfe\x00\
+        \x00\x12\xc0\x3e\x00\x00\x81\xa4\x00\x00\x00\x00\x00\x00\x00\x00\
+        \x00\x00\x00\x03\x45\xb9\x83\xbe\x36\xb7\x3c\x07\x88\xdc\x9c\xbc\
+        \xb7\x6c\xbb\x80\xfc\x7b\xb0\x57\x00\x09\x52\x45\x41\x44\x4d\x45\
+        \x2e\x6d\x64\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\
+        \x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x81\xa4\
+        \x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\xe6\x9d\xe2\x9b\
+        \xb2\xd1\xd6\x43\x4b\x8b\x29\xae\x77\x5a\xd8\xc2\xe4\x8c\x53\x91\
+        \x40\x07\x20\x00\x6e\x65\x77\x2e\x74\x78\x74\x00\x6a\xd5\x3e\xa2\
+        \x1e\x21\x9d\x0f\x6a\xd5\x3e\xa2\x1e\x21\x9d\x0f\x00\x00\xfe\x00\
+        \x00\x12\xc0\x3f\x00\x00\x81\xa4\x00\x00\x00\x00\x00\x00\x00\x00\
+        \x00\x00\x00\x0d\xf3\x28\xe4\xd9\xd0\x4c\x31\xd0\xd7\x0d\x16\xd2\
+        \x1a\x07\xd1\x61\x3b\xe9\xd5\x77\x00\x0b\x73\x72\x63\x2f\x6d\x61\
+        \x69\x6e\x2e\x72\x73\x00\x00\x00\x00\x00\x00\x00\xc7\x03\xdc\xc1\
+        \x57\x59\x60\x97\xc0\x58\xb7\x5f\x3d\x5d\x51\x4f\x35\x30\xcc\x3e";
+
+    // A version 2 index entry for the path, with everything but the object ID and flags zeroed
+    fn index_entry(path: &str, sha: u8) -> Vec<u8> {
+        let mut entry = vec![0; 40];
+        entry.extend([sha; 20]);
+        entry.extend((path.len() as u16).to_be_bytes());
+        entry.extend(path.as_bytes());
+        let length = entry.len();
+        entry.resize((length / 8 + 1) * 8, 0);
+        entry
+    }
+
+    fn index(paths: &[&str]) -> Vec<u8> {
+        let mut body = b"DIRC".to_vec();
+        body.extend(2u32.to_be_bytes());
+        body.extend((paths.len() as u32).to_be_bytes());
+        for (i, path) in paths.iter().enumerate() {
+            body.extend(index_entry(path, i as u8 + 1));
+        }
+        body
+    }
+
+    // A .DS_Store record: the name's length in UTF-16 units, the name, then the code, type and value
+    fn ds_store_record(name: &str, code: &[u8; 4], kind: &[u8; 4], value: &[u8]) -> Vec<u8> {
+        let units: Vec<u16> = name.encode_utf16().collect();
+        let mut record = (units.len() as u32).to_be_bytes().to_vec();
+        record.extend(units.iter().flat_map(|unit| unit.to_be_bytes()));
+        record.extend(code);
+        record.extend(kind);
+        record.extend(value);
+        record
+    }
+
+    fn response(raw: &str) -> HttpResponse {
+        HttpResponse::parse(raw.as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn git_index_reads_version_2() {
+        assert_eq!(
+            git_index(INDEX_V2),
+            vec![
+                (
+                    "45b983be36b73c0788dc9cbcb76cbb80fc7bb057".to_string(),
+                    "README.md".to_string()
+                ),
+                (
+                    "f328e4d9d04c31d0d70d16d21a07d1613be9d577".to_string(),
+                    "src/main.rs".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn git_index_reads_version_3_extended_flags() {
+        let paths: Vec<String> = git_index(INDEX_V3)
+            .into_iter()
+            .map(|(_, path)| path)
+            .collect();
+        assert_eq!(paths, ["README.md", "new.txt", "src/main.rs"]);
+        assert_eq!(
+            git_index(INDEX_V3)[1].0,
+            "e69de29bb2d1d6434b8b29ae775ad8c2e48c5391"
+        );
+    }
+
+    #[test]
+    fn git_index_stops_at_truncation() {
+        for length in 0..INDEX_V3.len() {
+            let entries = git_index(&INDEX_V3[..length]);
+            assert!(entries.len() <= 3);
+            assert!(entries.iter().all(|(sha, _)| sha.len() == 40));
+        }
+        assert_eq!(git_index(&INDEX_V2[..100]).len(), 1);
+        assert!(git_index(b"DIRC\x00\x00\x00\x04\x00\x00\x00\x01").is_empty());
+    }
+
+    #[test]
+    fn git_index_paths_outside_the_tree_are_not_restored() {
+        let body = index(&[
+            "../../etc/cron.d/x",
+            ".git/hooks/post-checkout",
+            "src/.git/config",
+            "/etc/passwd",
+            "a/../../b",
+            "docs/readme.md",
+        ]);
+        let entries = git_index(&body);
+        assert_eq!(entries.len(), 6);
+        let kept: Vec<&String> = entries
+            .iter()
+            .map(|(_, path)| path)
+            .filter(|path| is_tree_path(path))
+            .collect();
+        assert_eq!(kept, ["docs/readme.md"]);
+    }
+
+    #[test]
+    fn ds_store_names_from_finder_records() {
+        // Laid out the way Finder writes one: the Bud1 header, then a leaf block of records sorted by name
+        let mut body = vec![0, 0, 0, 1];
+        body.extend(b"Bud1");
+        body.extend([
+            0, 0, 0x10, 0, 0, 0, 0x08, 0, 0, 0, 0x10, 0, 0, 0, 0x10, 0x0c,
+        ]);
+        body.resize(0x0c00, 0);
+        body.extend([0, 0, 0, 0, 0, 0, 0, 4]);
+        body.extend(ds_store_record(".", b"vSrn", b"long", &[0, 0, 0, 1]));
+        body.extend(ds_store_record(
+            "backup.zip",
+            b"Iloc",
+            b"blob",
+            &[
+                0, 0, 0, 16, 0, 0, 0, 0x30, 0, 0, 0, 0x2c, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0, 0,
+            ],
+        ));
+        body.extend(ds_store_record("backup.zip", b"modD", b"dutc", &[0; 8]));
+        body.extend(ds_store_record(
+            "secret admin",
+            b"lsvP",
+            b"blob",
+            &[0, 0, 0, 0],
+        ));
+        body.extend(b"DSDB");
+        body.resize(0x1000, 0);
+        assert_eq!(ds_store_names(&body), [".", "backup.zip", "secret admin"]);
+    }
+
+    #[test]
+    fn ds_store_names_ignores_garbage() {
+        assert!(ds_store_names(b"").is_empty());
+        assert!(ds_store_names(&[0xff; 64]).is_empty());
+        assert!(ds_store_names(&[0, 0, 0, 200, 0, 0x61]).is_empty());
+    }
+
+    #[test]
+    fn is_backup_skips_the_catch_all_page() {
+        let catch_all = "HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\n\r\nNothing here, forgot your password?";
+        let baseline = response(catch_all);
+        assert!(is_backup("/index.php.bak", &response(catch_all), None));
+        assert!(!is_backup(
+            "/index.php.bak",
+            &response(catch_all),
+            Some(&baseline)
+        ));
+
+        let source = "HTTP/1.1 200 OK\r\n\r\n<?php $db_password = 'x';";
+        assert!(is_backup(
+            "/index.php.bak",
+            &response(source),
+            Some(&baseline)
+        ));
+        let html = "HTTP/1.1 200 OK\r\n\r\n<!DOCTYPE html><p>password</p>";
+        assert!(!is_backup("/index.php.bak", &response(html), None));
+    }
+
+    #[test]
+    fn is_backup_checks_archive_magic() {
+        assert!(is_backup(
+            "/backup.zip",
+            &response("HTTP/1.1 200 OK\r\n\r\nPK\x03\x04rest"),
+            None
+        ));
+        assert!(!is_backup(
+            "/backup.zip",
+            &response("HTTP/1.1 200 OK\r\n\r\nnot a zip"),
+            None
+        ));
+    }
+}
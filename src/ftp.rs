@@ -0,0 +1,287 @@
+use crate::error::RecoverableDiscoveryError;
+use std::error::Error;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{IpAddr, SocketAddr, TcpStream};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+// Don't wander forever through deep or looping directory trees
+const MAX_DEPTH: usize = 10;
+const MAX_ENTRIES: usize = 5000;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FtpEntry {
+    is_dir: bool,
+    path: String,
+    size: Option<u64>,
+}
+
+impl FtpEntry {
+    pub fn is_dir(&self) -> bool {
+        self.is_dir
+    }
+
+    pub fn path(&self) -> &String {
+        &self.path
+    }
+
+    pub fn size(&self) -> Option<u64> {
+        self.size
+    }
+}
+
+pub struct FtpClient {
+    control: BufReader<TcpStream>,
+    ip_address: IpAddr,
+    timeout: Duration,
+}
+
+impl FtpClient {
+    // Connect to the server and read its greeting
+    pub fn connect(
+        ip_address: IpAddr,
+        port: u16,
+        timeout: Duration,
+    ) -> Result<FtpClient, Box<dyn Error>> {
+        let stream = TcpStream::connect_timeout(&SocketAddr::new(ip_address, port), timeout)?;
+        stream.set_read_timeout(Some(timeout * 5))?;
+        stream.set_write_timeout(Some(timeout * 5))?;
+        let mut client = FtpClient {
+            control: BufReader::new(stream),
+            ip_address,
+            timeout,
+        };
+        client.expect(220)?;
+        Ok(client)
+    }
+
+    // Log in as anonymous, returning the server's reply to the password
+    pub fn login_anonymous(&mut self) -> Result<String, Box<dyn Error>> {
+        let (code, reply) = self.command("USER anonymous")?;
+        let reply = match code {
+            230 => reply,
+            331 => self.command("PASS anonymous@example.com")?.1,
+            _ => return Err(reply.into()),
+        };
+        if !reply.starts_with("230") {
+            return Err(reply.into());
+        }
+        self.command("TYPE I")?;
+        Ok(reply)
+    }
+
+    // Walk the whole tree from the root, breadth first
+    pub fn list_recursive(&mut self) -> Vec<FtpEntry> {
+        let mut entries: Vec<FtpEntry> = vec![];
+        let mut pending = vec![("/".to_string(), 0)];
+
+        while let Some((dir, depth)) = pending.pop() {
+            let children = match self.list(&dir) {
+                Ok(children) => children,
+                Err(_) => continue,
+            };
+            for child in children {
+                if entries.len() >= MAX_ENTRIES {
+                    return entries;
+                }
+                if child.is_dir && depth < MAX_DEPTH {
+                    pending.insert(0, (child.path.clone(), depth + 1));
+                }
+                entries.push(child);
+            }
+        }
+
+        entries
+    }
+
+    // List one directory, preferring the machine-readable MLSD and falling back to LIST
+    pub fn list(&mut self, dir: &str) -> Result<Vec<FtpEntry>, Box<dyn Error>> {
+        let (listing, machine_readable) = match self.transfer(&format!("MLSD {dir}")) {
+            Ok(listing) => (listing, true),
+            Err(_) => (self.transfer(&format!("LIST -a {dir}"))?, false),
+        };
+        let listing = String::from_utf8_lossy(&listing);
+
+        let entries = listing
+            .lines()
+            .filter_map(|line| match machine_readable {
+                true => parse_mlsd_line(line),
+                false => parse_list_line(line),
+            })
+            .filter(|(name, _, _)| safe_name(name))
+            .map(|(name, is_dir, size)| FtpEntry {
+                is_dir,
+                path: format!("{}/{name}", dir.trim_end_matches('/')),
+                size,
+            })
+            .collect();
+
+        Ok(entries)
+    }
+
+    // Download a file, refusing to read more than the size cap
+    pub fn retrieve(&mut self, path: &str, max_size: u64) -> Result<Vec<u8>, Box<dyn Error>> {
+        let mut data = self.open_data(&format!("RETR {path}"))?;
+        let mut contents = vec![];
+        (&mut data).take(max_size + 1).read_to_end(&mut contents)?;
+        drop(data);
+        self.read_reply()?;
+        if contents.len() as u64 > max_size {
+            return Err(RecoverableDiscoveryError::Response.into());
+        }
+        Ok(contents)
+    }
+
+    // See if anonymous users can upload, cleaning up the test file if they can
+    pub fn test_upload(&mut self) -> Result<bool, Box<dyn Error>> {
+        let seconds = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+        let name = format!("/imd_upload_test_{seconds}.txt");
+
+        let mut data = match self.open_data(&format!("STOR {name}")) {
+            Ok(data) => data,
+            Err(_) => return Ok(false),
+        };
+        data.write_all(b"imd upload test\n")?;
+        drop(data);
+        let (code, _) = self.read_reply()?;
+        let uploaded = code == 226 || code == 250;
+        if uploaded {
+            let _ = self.command(&format!("DELE {name}"));
+        }
+        Ok(uploaded)
+    }
+
+    // Run a command that sends its result over a data connection, and return everything it sent
+    fn transfer(&mut self, command: &str) -> Result<Vec<u8>, Box<dyn Error>> {
+        let mut data = self.open_data(command)?;
+        let mut contents = vec![];
+        data.read_to_end(&mut contents)?;
+        drop(data);
+        self.read_reply()?;
+        Ok(contents)
+    }
+
+    // Open a passive data connection and start the command that uses it
+    fn open_data(&mut self, command: &str) -> Result<TcpStream, Box<dyn Error>> {
+        let port = self.passive_port()?;
+        // Servers behind NAT often advertise an unreachable address, so always use the one we connected to
+        let data =
+            TcpStream::connect_timeout(&SocketAddr::new(self.ip_address, port), self.timeout)?;
+        data.set_read_timeout(Some(self.timeout * 5))?;
+        data.set_write_timeout(Some(self.timeout * 5))?;
+
+        let (code, reply) = self.command(command)?;
+        if code != 125 && code != 150 {
+            return Err(reply.into());
+        }
+        Ok(data)
+    }
+
+    // Ask for a passive mode port, with EPSV as the fallback for servers (or IPv6 targets) that need it
+    fn passive_port(&mut self) -> Result<u16, Box<dyn Error>> {
+        let (code, reply) = self.command("PASV")?;
+        if code == 227 {
+            let numbers: Vec<u16> = reply
+                .split(['(', ')'])
+                .nth(1)
+                .unwrap_or_default()
+                .split(',')
+                .filter_map(|n| n.trim().parse().ok())
+                .collect();
+            if let [_, _, _, _, high, low] = numbers[..] {
+                return Ok(high * 256 + low);
+            }
+        }
+
+        let (code, reply) = self.command("EPSV")?;
+        if code == 229 {
+            if let Some(port) = reply.split('|').nth(3).and_then(|p| p.parse().ok()) {
+                return Ok(port);
+            }
+        }
+
+        Err(reply.into())
+    }
+
+    // Send a command on the control connection and read the reply
+    fn command(&mut self, command: &str) -> Result<(u16, String), Box<dyn Error>> {
+        self.control
+            .get_mut()
+            .write_all(format!("{command}\r\n").as_bytes())?;
+        self.read_reply()
+    }
+
+    // Read a reply, including every line of a multi-line one
+    fn read_reply(&mut self) -> Result<(u16, String), Box<dyn Error>> {
+        let mut reply = String::new();
+        loop {
+            let mut line = String::new();
+            if self.control.read_line(&mut line)? == 0 {
+                return Err(RecoverableDiscoveryError::Response.into());
+            }
+            reply.push_str(&line);
+            // A reply ends on a line with the code followed by a space
+            if line.len() >= 4 && line.as_bytes()[3] == b' ' && line[..3].parse::<u16>().is_ok() {
+                let code = line[..3].parse()?;
+                return Ok((code, reply.trim_end().to_string()));
+            }
+        }
+    }
+
+    // Read a reply and make sure it has the code we expect
+    fn expect(&mut self, expected: u16) -> Result<String, Box<dyn Error>> {
+        let (code, reply) = self.read_reply()?;
+        if code != expected {
+            return Err(reply.into());
+        }
+        Ok(reply)
+    }
+}
+
+// MLSD lines look like "type=file;size=1234;modify=20200101000000; name"
+fn parse_mlsd_line(line: &str) -> Option<(String, bool, Option<u64>)> {
+    let (facts, name) = line.split_once(' ')?;
+    let mut is_dir = false;
+    let mut size = None;
+    for fact in facts.split(';') {
+        match fact.split_once('=') {
+            Some((key, value)) if key.eq_ignore_ascii_case("type") => {
+                let value = value.to_lowercase();
+                if value == "cdir" || value == "pdir" {
+                    return None;
+                }
+                is_dir = value == "dir";
+            }
+            Some((key, value)) if key.eq_ignore_ascii_case("size") => size = value.parse().ok(),
+            _ => (),
+        }
+    }
+    Some((name.to_string(), is_dir, size))
+}
+
+// LIST lines are either unix style "drwxr-xr-x 2 0 0 4096 Jan 01 00:00 name" or IIS style "01-01-20 12:00AM <DIR> name"
+fn parse_list_line(line: &str) -> Option<(String, bool, Option<u64>)> {
+    let columns: Vec<&str> = line.split_whitespace().collect();
+    let first = columns.first()?;
+
+    if first.starts_with(['d', '-', 'l']) && columns.len() >= 9 {
+        let name = columns[8..].join(" ");
+        // Symlinks are listed as "name -> target"
+        let name = name.split(" -> ").next()?.to_string();
+        return Some((name, first.starts_with('d'), columns[4].parse().ok()));
+    }
+
+    if first.chars().next()?.is_ascii_digit() && columns.len() >= 4 {
+        let name = columns[3..].join(" ");
+        return match columns[2] {
+            "<DIR>" => Some((name, true, None)),
+            size => Some((name, false, size.parse().ok())),
+        };
+    }
+
+    None
+}
+
+// Leave out the current and parent directories, and anything that could escape the loot directory when saved
+fn safe_name(name: &str) -> bool {
+    !name.is_empty() && name != "." && name != ".." && !name.contains('/') && !name.contains('\\')
+}
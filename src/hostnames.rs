@@ -0,0 +1,99 @@
+use crate::error::PanicDiscoveryError;
+use std::net::IpAddr;
+
+// What to do with hostnames imd finds on its own
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum HostnameMode {
+    #[default]
+    Auto,
+    Ignore,
+    Prompt,
+}
+
+impl HostnameMode {
+    // Parse a HostnameMode from the command line arguments
+    pub fn new(input: &str) -> Result<HostnameMode, PanicDiscoveryError> {
+        match input {
+            "auto" => Ok(HostnameMode::Auto),
+            "ignore" => Ok(HostnameMode::Ignore),
+            "prompt" => Ok(HostnameMode::Prompt),
+            _ => Err(PanicDiscoveryError::InvalidHostnameMode),
+        }
+    }
+}
+
+// Pull hostnames out of the redirects reported by nmap's http-title script and the names in its ssl-cert output
+pub fn from_port_scan(port_scan: &str) -> Vec<(String, String)> {
+    let mut found = vec![];
+
+    for line in port_scan.lines() {
+        let line = line.trim_start_matches(['|', '_', ' ']);
+
+        if let Some((_, url)) = line.split_once("Did not follow redirect to ") {
+            if let Some(hostname) = from_url(url) {
+                found.push((hostname, "HTTP redirect".to_string()));
+            }
+        }
+
+        if let Some((_, subject)) = line.split_once("Subject: ") {
+            let common_name = subject
+                .split(['/', ','])
+                .filter_map(|part| part.trim().strip_prefix("commonName="))
+                .filter_map(clean);
+            found.extend(common_name.map(|name| (name, "TLS certificate CN".to_string())));
+        }
+
+        if let Some((_, names)) = line.split_once("Subject Alternative Name: ") {
+            let alternative_names = names
+                .split(',')
+                .filter_map(|part| part.trim().strip_prefix("DNS:"))
+                .filter_map(clean);
+            found.extend(alternative_names.map(|name| (name, "TLS certificate SAN".to_string())));
+        }
+    }
+
+    dedup(found)
+}
+
+// Get the hostname from an absolute URL, E.G. the Location header of a redirect
+pub fn from_url(url: &str) -> Option<String> {
+    let (_, rest) = url.trim().split_once("://")?;
+    let authority = rest.split(['/', '?', '#']).next()?;
+    let host = authority.rsplit('@').next()?;
+    // Strip a port, but leave bracketed IPv6 addresses for `clean` to throw away
+    let host = match host.starts_with('[') {
+        true => host,
+        false => host.split(':').next()?,
+    };
+    clean(host)
+}
+
+// Keep only things that look like hostnames, which leaves out IP addresses, wildcards and localhost
+pub fn clean(name: &str) -> Option<String> {
+    let name = name
+        .trim()
+        .trim_start_matches("*.")
+        .trim_end_matches('.')
+        .to_lowercase();
+    if name.parse::<IpAddr>().is_ok() || !name.contains('.') || name.ends_with("localhost") {
+        return None;
+    }
+    if !name
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '.')
+    {
+        return None;
+    }
+    Some(name)
+}
+
+// Drop repeated hostnames, keeping the first source each was seen in
+pub fn dedup(found: Vec<(String, String)>) -> Vec<(String, String)> {
+    let mut unique: Vec<(String, String)> = vec![];
+    for (hostname, source) in found {
+        if !unique.iter().any(|(seen, _)| *seen == hostname) {
+            unique.push((hostname, source));
+        }
+    }
+    unique
+}
@@ -0,0 +1,338 @@
+use crate::error::RecoverableDiscoveryError;
+use std::error::Error;
+use std::io::{Read, Write};
+use std::net::{IpAddr, SocketAddr, TcpStream};
+use std::process::{Command, Stdio};
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+// Stop reading a response once it gets this big, nothing imd looks at needs more than that unless it asks for it
+const MAX_RESPONSE_SIZE: usize = 5 * 1024 * 1024;
+
+#[derive(Clone, Debug)]
+pub struct HttpClient {
+    ip_address: IpAddr,
+    max_response_size: usize,
+    port: u16,
+    scheme: String,
+    timeout: Duration,
+}
+
+impl HttpClient {
+    pub fn new(scheme: &str, ip_address: IpAddr, port: u16, timeout: Duration) -> HttpClient {
+        HttpClient {
+            ip_address,
+            max_response_size: MAX_RESPONSE_SIZE,
+            port,
+            scheme: scheme.to_string(),
+            timeout,
+        }
+    }
+
+    // Read at most this much of each response, headers included, for callers that need less or more than usual
+    pub fn with_max_response_size(mut self, max_response_size: usize) -> HttpClient {
+        self.max_response_size = max_response_size;
+        self
+    }
+
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    pub fn scheme(&self) -> &String {
+        &self.scheme
+    }
+
+    // Build the URL for a path on the given host
+    pub fn url(&self, host: &str, path: &str) -> String {
+        format!("{}://{host}:{}{path}", self.scheme, self.port)
+    }
+
+    // Convenience wrapper for the most common request imd makes
+    pub fn get(&self, host: &str, path: &str) -> Result<HttpResponse, Box<dyn Error>> {
+        self.request("GET", host, path, &[], &[])
+    }
+
+    // Send a request with the provided host header, extra headers and body, and parse the response
+    pub fn request(
+        &self,
+        method: &str,
+        host: &str,
+        path: &str,
+        headers: &[(&str, &str)],
+        body: &[u8],
+    ) -> Result<HttpResponse, Box<dyn Error>> {
+        let mut request = format!(
+            "{method} {path} HTTP/1.1\r\nHost: {host}\r\nUser-Agent: Mozilla/5.0 (imd)\r\nAccept: */*\r\nConnection: close\r\n"
+        );
+        for (name, value) in headers {
+            request.push_str(&format!("{name}: {value}\r\n"));
+        }
+        if !body.is_empty() {
+            request.push_str(&format!("Content-Length: {}\r\n", body.len()));
+        }
+        request.push_str("\r\n");
+        let mut request = request.into_bytes();
+        request.extend_from_slice(body);
+
+        let raw = match self.scheme.as_str() {
+            "https" => self.exchange_tls(host, &request)?,
+            _ => self.exchange_plain(&request)?,
+        };
+
+        HttpResponse::parse(&raw).ok_or_else(|| RecoverableDiscoveryError::Response.into())
+    }
+
+    // Send the request over a plain TCP connection and read until the server closes it
+    fn exchange_plain(&self, request: &[u8]) -> Result<Vec<u8>, Box<dyn Error>> {
+        let address = SocketAddr::new(self.ip_address, self.port);
+        let mut stream = TcpStream::connect_timeout(&address, self.timeout)?;
+        stream.set_read_timeout(Some(self.timeout))?;
+        stream.set_write_timeout(Some(self.timeout))?;
+        stream.write_all(request)?;
+
+        let mut response = vec![];
+        let mut buffer = [0u8; 8192];
+        while response.len() < self.max_response_size {
+            match stream.read(&mut buffer) {
+                Ok(0) | Err(_) => break,
+                Ok(size) => response.extend_from_slice(&buffer[..size]),
+            }
+        }
+
+        Ok(response)
+    }
+
+    // Let openssl handle the TLS layer and talk plain HTTP through it
+    fn exchange_tls(&self, host: &str, request: &[u8]) -> Result<Vec<u8>, Box<dyn Error>> {
+        let connect = SocketAddr::new(self.ip_address, self.port).to_string();
+        let mut args = vec!["s_client", "-quiet", "-connect", &connect];
+        // SNI only makes sense for names, openssl complains about IP addresses
+        if host.parse::<IpAddr>().is_err() {
+            args.extend(["-servername", host]);
+        }
+
+        let mut child = Command::new("openssl")
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(request)?;
+        }
+
+        // Read on another thread so that a server that never closes the connection can't hang discovery
+        let mut stdout = child
+            .stdout
+            .take()
+            .ok_or(RecoverableDiscoveryError::Response)?;
+        let (sender, receiver) = mpsc::channel();
+        let max_response_size = self.max_response_size;
+        thread::spawn(move || {
+            let mut response = vec![];
+            let mut buffer = [0u8; 8192];
+            while response.len() < max_response_size {
+                match stdout.read(&mut buffer) {
+                    Ok(0) | Err(_) => break,
+                    Ok(size) => response.extend_from_slice(&buffer[..size]),
+                }
+            }
+            let _ = sender.send(response);
+        });
+
+        let response = match receiver.recv_timeout(self.timeout * 10) {
+            Ok(response) => response,
+            Err(_) => {
+                let _ = child.kill();
+                receiver.recv().unwrap_or_default()
+            }
+        };
+        let _ = child.kill();
+        let _ = child.wait();
+
+        Ok(response)
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct HttpResponse {
+    body: Vec<u8>,
+    headers: Vec<(String, String)>,
+    status: u16,
+}
+
+impl HttpResponse {
+    // Parse a raw HTTP response, undoing chunked transfer encoding if the server used it
+    pub fn parse(raw: &[u8]) -> Option<HttpResponse> {
+        let split = raw.windows(4).position(|w| w == b"\r\n\r\n")?;
+        let head = String::from_utf8_lossy(&raw[..split]);
+        let body = &raw[split + 4..];
+
+        let mut lines = head.lines();
+        let status_line = lines.next()?;
+        if !status_line.starts_with("HTTP/") {
+            return None;
+        }
+        let status = status_line.split_whitespace().nth(1)?.parse().ok()?;
+
+        let headers: Vec<(String, String)> = lines
+            .filter_map(|line| line.split_once(':'))
+            .map(|(name, value)| (name.trim().to_string(), value.trim().to_string()))
+            .collect();
+
+        let mut response = HttpResponse {
+            body: body.to_vec(),
+            headers,
+            status,
+        };
+        if response
+            .header("Transfer-Encoding")
+            .is_some_and(|value| value.eq_ignore_ascii_case("chunked"))
+        {
+            response.body = dechunk(body);
+        }
+
+        Some(response)
+    }
+
+    pub fn body(&self) -> &Vec<u8> {
+        &self.body
+    }
+
+    // Find the first header with the given name, ignoring case
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header, _)| header.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn headers(&self) -> &Vec<(String, String)> {
+        &self.headers
+    }
+
+    pub fn status(&self) -> u16 {
+        self.status
+    }
+
+    // The body as text, replacing anything that isn't valid UTF-8
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.body).to_string()
+    }
+}
+
+// Reassemble a chunked body, keeping whatever arrived if the chunks are malformed
+fn dechunk(mut body: &[u8]) -> Vec<u8> {
+    let mut output = vec![];
+    while let Some(end) = body.windows(2).position(|w| w == b"\r\n") {
+        let size = String::from_utf8_lossy(&body[..end]);
+        let size = size.split(';').next().unwrap_or_default().trim();
+        let size = match usize::from_str_radix(size, 16) {
+            Ok(0) | Err(_) => break,
+            Ok(size) => size,
+        };
+        body = &body[end + 2..];
+        let size = size.min(body.len());
+        output.extend_from_slice(&body[..size]);
+        body = body.get(size + 2..).unwrap_or_default();
+    }
+    output
+}
+
+// Pull a value out of JSON without a JSON parser: strings and numbers as they are, and arrays of strings joined with commas
+pub fn json_value(json: &str, key: &str) -> Option<String> {
+    let (_, rest) = json.split_once(&format!("\"{key}\""))?;
+    let rest = rest.trim_start().strip_prefix(':')?.trim_start();
+    if let Some(string) = rest.strip_prefix('"') {
+        return json_string(string).map(|(value, _)| value);
+    }
+    if let Some(mut array) = rest.strip_prefix('[') {
+        let mut values = vec![];
+        while let Some(string) = array.trim_start().strip_prefix('"') {
+            let (value, after) = json_string(string)?;
+            values.push(value);
+            array = after.trim_start().trim_start_matches(',');
+        }
+        return Some(values.join(", "));
+    }
+    Some(rest.split([',', '}', ']']).next()?.trim().to_string())
+}
+
+// Split the objects out of a JSON array, keeping each one's text so json_value can be used on it
+pub fn json_objects(array: &str) -> Vec<&str> {
+    let mut objects = vec![];
+    let mut depth = 0;
+    let mut start = 0;
+    let mut in_string = false;
+    let mut escaped = false;
+    for (i, c) in array.char_indices() {
+        if in_string {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match c {
+            '"' => in_string = true,
+            '{' => {
+                if depth == 0 {
+                    start = i;
+                }
+                depth += 1;
+            }
+            '}' if depth > 0 => {
+                depth -= 1;
+                if depth == 0 {
+                    objects.push(&array[start..=i]);
+                }
+            }
+            ']' if depth == 0 => break,
+            _ => {}
+        }
+    }
+    objects
+}
+
+// Escape a value for a JSON string
+pub fn json_escape(value: &str) -> String {
+    let mut escaped = String::new();
+    for c in value.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\t' => escaped.push_str("\\t"),
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+// Read a JSON string up to its closing quote, returning it unescaped along with whatever follows it
+fn json_string(string: &str) -> Option<(String, &str)> {
+    let mut value = String::new();
+    let mut chars = string.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' => match chars.next()?.1 {
+                'n' => value.push('\n'),
+                't' => value.push('\t'),
+                'u' => {
+                    let code: String = chars.by_ref().take(4).map(|(_, c)| c).collect();
+                    value.push(char::from_u32(u32::from_str_radix(&code, 16).ok()?)?);
+                }
+                escaped => value.push(escaped),
+            },
+            '"' => return Some((value, &string[i + 1..])),
+            c => value.push(c),
+        }
+    }
+    None
+}
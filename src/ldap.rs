@@ -0,0 +1,170 @@
+use crate::scanner::OpenPort;
+use std::error::Error;
+use std::process::Command;
+
+// Ports LDAP is usually served on: plain and TLS, for the directory and the global catalog
+pub const LDAP_PORTS: [u16; 4] = [389, 636, 3268, 3269];
+
+// Ports that speak LDAP over TLS from the start
+const LDAPS_PORTS: [u16; 2] = [636, 3269];
+
+// Catches users in Active Directory and OpenLDAP alike
+pub const USER_FILTER: &str = "(|(objectClass=user)(objectClass=person)(objectClass=posixAccount))";
+pub const GROUP_FILTER: &str =
+    "(|(objectClass=group)(objectClass=groupOfNames)(objectClass=posixGroup))";
+
+// Naming contexts that hold the directory's plumbing rather than its users
+const PLUMBING: [&str; 4] = [
+    "CN=Configuration",
+    "CN=Schema",
+    "DC=DomainDnsZones",
+    "DC=ForestDnsZones",
+];
+
+// Words in descriptions that suggest someone left a password there
+const SECRETS: [&str; 4] = ["pass", "pwd", "cred", "secret"];
+
+// Whether the scan found LDAP on a port, either by the port it is on or by the service the scanner named
+pub fn is_ldap(open_port: &OpenPort) -> bool {
+    open_port.protocol() == "tcp"
+        && (LDAP_PORTS.contains(&open_port.port()) || open_port.service().contains("ldap"))
+}
+
+// Pick the port to query, preferring plain LDAP over TLS and the directory over the global catalog
+pub fn preferred_url(ip_string: &str, open_ports: &[OpenPort]) -> Option<String> {
+    let mut ports: Vec<&OpenPort> = open_ports.iter().filter(|p| is_ldap(p)).collect();
+    ports.sort_by_key(|p| {
+        let tls = LDAPS_PORTS.contains(&p.port()) || p.service().starts_with("ssl/");
+        (tls, LDAP_PORTS.iter().position(|port| *port == p.port()))
+    });
+    let port = ports.first()?;
+    let scheme = match LDAPS_PORTS.contains(&port.port()) || port.service().starts_with("ssl/") {
+        true => "ldaps",
+        false => "ldap",
+    };
+    Some(format!("{scheme}://{ip_string}:{}", port.port()))
+}
+
+// Run an anonymous ldapsearch, paging through large results and ignoring the server's certificate
+pub fn search(
+    url: &str,
+    base: &str,
+    scope: &str,
+    filter: &str,
+    attributes: &[&str],
+) -> Result<String, Box<dyn Error>> {
+    let out = Command::new("ldapsearch")
+        .env("LDAPTLS_REQCERT", "never")
+        .args(["-x", "-LLL", "-o", "ldif-wrap=no", "-E", "pr=1000/noprompt"])
+        .args(["-H", url, "-b", base, "-s", scope, filter])
+        .args(attributes)
+        .output()?;
+
+    Ok(String::from_utf8_lossy(&out.stdout).to_string())
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LdapEntry {
+    attributes: Vec<(String, String)>,
+    dn: String,
+}
+
+impl LdapEntry {
+    pub fn attributes(&self) -> &Vec<(String, String)> {
+        &self.attributes
+    }
+
+    pub fn dn(&self) -> &String {
+        &self.dn
+    }
+
+    // Every value of an attribute, matched case-insensitively the way LDAP does
+    pub fn values(&self, name: &str) -> Vec<&String> {
+        self.attributes
+            .iter()
+            .filter(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value)
+            .collect()
+    }
+
+    // The first value of the first attribute in the list that has one
+    pub fn first(&self, names: &[&str]) -> Option<&String> {
+        names
+            .iter()
+            .find_map(|name| self.values(name).into_iter().next())
+    }
+}
+
+// Parse unwrapped LDIF into entries, decoding base64 (`attr:: value`) values so they can be read and searched like the rest
+pub fn parse_ldif(ldif: &str) -> Vec<LdapEntry> {
+    let mut entries = vec![];
+    let mut entry = LdapEntry::default();
+
+    for line in ldif.lines().chain(std::iter::once("")) {
+        if line.trim().is_empty() {
+            if !entry.dn.is_empty() || !entry.attributes.is_empty() {
+                entries.push(std::mem::take(&mut entry));
+            }
+            continue;
+        }
+        if line.starts_with('#') {
+            continue;
+        }
+        let (key, value) = match line.split_once(':') {
+            Some((key, value)) => match value.strip_prefix(':') {
+                // Values that aren't safe to print as they are, like non-ASCII descriptions, come base64 encoded
+                Some(encoded) => match base64_decode(encoded.trim()) {
+                    Some(bytes) => (key.trim(), String::from_utf8_lossy(&bytes).to_string()),
+                    None => (key.trim(), encoded.trim().to_string()),
+                },
+                None => (key.trim(), value.trim().to_string()),
+            },
+            None => continue,
+        };
+        match key {
+            "dn" => entry.dn = value,
+            _ => entry.attributes.push((key.to_string(), value)),
+        }
+    }
+
+    entries
+}
+
+fn base64_decode(input: &str) -> Option<Vec<u8>> {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut output = vec![];
+    let mut bits: u32 = 0;
+    let mut count = 0;
+    for byte in input.bytes().filter(|byte| *byte != b'=') {
+        let value = ALPHABET.iter().position(|c| *c == byte)? as u32;
+        bits = (bits << 6) | value;
+        count += 6;
+        if count >= 8 {
+            count -= 8;
+            output.push((bits >> count) as u8);
+        }
+    }
+    Some(output)
+}
+
+// Pick the naming context that holds the directory's users out of the rootDSE
+pub fn naming_context(root_dse: &[LdapEntry]) -> Option<String> {
+    let root = root_dse.first()?;
+    if let Some(context) = root.first(&["defaultNamingContext"]) {
+        return Some(context.clone());
+    }
+    root.values("namingContexts")
+        .into_iter()
+        .find(|context| {
+            !PLUMBING
+                .iter()
+                .any(|plumbing| context.to_lowercase().starts_with(&plumbing.to_lowercase()))
+        })
+        .cloned()
+}
+
+// Whether a description looks like it holds a password
+pub fn has_secret(description: &str) -> bool {
+    let description = description.to_lowercase();
+    SECRETS.iter().any(|secret| description.contains(secret))
+}
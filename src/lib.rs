@@ -1,8 +1,34 @@
+pub mod content;
+pub mod crawl;
+pub mod db;
+pub mod dns;
 pub mod error;
+pub mod exposure;
+pub mod ftp;
+pub mod hostnames;
+pub mod http;
+pub mod ldap;
+pub mod mail;
+pub mod nfs;
+pub mod probe;
+pub mod remote;
+pub mod rpc;
+pub mod scanner;
+pub mod smb;
+pub mod snmp;
+pub mod ssh;
+pub mod vhosts;
+pub mod vulns;
+pub mod webtech;
+pub mod windows;
 use crossterm::style::Stylize;
 use error::{PanicDiscoveryError, RecoverableDiscoveryError};
+use hostnames::HostnameMode;
+use http::HttpClient;
 use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
 use nix::unistd::{self, Gid, Uid, User};
+use scanner::{NativeScanner, OpenPort, PortScanner};
+use smb::SmbTool;
 use std::collections::HashMap;
 use std::env;
 use std::error::Error;
@@ -11,7 +37,8 @@ use std::io::{BufRead, BufReader, Write};
 use std::net::IpAddr;
 use std::path::Path;
 use std::process::Command;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 const SUCCESS: &str = "✔️ Done";
 
@@ -57,6 +84,212 @@ impl CLITarget {
     }
 }
 
+#[derive(Clone, Debug, Default)]
+pub struct DiscoveryOptions {
+    ftp_max_size: u64,
+    hostname_mode: HostnameMode,
+    mail_usernames: Vec<String>,
+    mount_nfs: bool,
+    native_scanner: NativeScanner,
+    nuclei_templates: Option<String>,
+    scanner: PortScanner,
+    smb_tool: SmbTool,
+    smb_write_test: bool,
+    snmp_communities: Vec<String>,
+    vhost_wordlist: String,
+    vuln_scanner: vulns::VulnScanner,
+    web_extension_rules: Vec<content::ExtensionRule>,
+    web_follow_ups: Vec<webtech::FollowUp>,
+    web_loot_max_size: u64,
+    web_scanner: content::WebScanner,
+    web_threads: usize,
+    web_time_limit: Duration,
+}
+
+impl DiscoveryOptions {
+    pub fn with_ftp_max_size(mut self, ftp_max_size: u64) -> DiscoveryOptions {
+        self.ftp_max_size = ftp_max_size;
+        self
+    }
+
+    pub fn with_hostname_mode(mut self, hostname_mode: HostnameMode) -> DiscoveryOptions {
+        self.hostname_mode = hostname_mode;
+        self
+    }
+
+    pub fn with_mail_usernames(mut self, mail_usernames: Vec<String>) -> DiscoveryOptions {
+        self.mail_usernames = mail_usernames;
+        self
+    }
+
+    pub fn with_mount_nfs(mut self, mount_nfs: bool) -> DiscoveryOptions {
+        self.mount_nfs = mount_nfs;
+        self
+    }
+
+    pub fn with_native_scanner(mut self, native_scanner: NativeScanner) -> DiscoveryOptions {
+        self.native_scanner = native_scanner;
+        self
+    }
+
+    pub fn with_nuclei_templates(mut self, nuclei_templates: Option<String>) -> DiscoveryOptions {
+        self.nuclei_templates = nuclei_templates;
+        self
+    }
+
+    pub fn with_scanner(mut self, scanner: PortScanner) -> DiscoveryOptions {
+        self.scanner = scanner;
+        self
+    }
+
+    pub fn ftp_max_size(&self) -> u64 {
+        self.ftp_max_size
+    }
+
+    pub fn hostname_mode(&self) -> HostnameMode {
+        self.hostname_mode
+    }
+
+    pub fn with_smb_tool(mut self, smb_tool: SmbTool) -> DiscoveryOptions {
+        self.smb_tool = smb_tool;
+        self
+    }
+
+    pub fn with_smb_write_test(mut self, smb_write_test: bool) -> DiscoveryOptions {
+        self.smb_write_test = smb_write_test;
+        self
+    }
+
+    // Fall back to a short list of likely usernames if none were given
+    pub fn mail_usernames(&self) -> Vec<String> {
+        match self.mail_usernames.is_empty() {
+            true => mail::DEFAULT_USERNAMES
+                .iter()
+                .map(|u| u.to_string())
+                .collect(),
+            false => self.mail_usernames.clone(),
+        }
+    }
+
+    pub fn mount_nfs(&self) -> bool {
+        self.mount_nfs
+    }
+
+    pub fn native_scanner(&self) -> &NativeScanner {
+        &self.native_scanner
+    }
+
+    pub fn nuclei_templates(&self) -> Option<&String> {
+        self.nuclei_templates.as_ref()
+    }
+
+    pub fn scanner(&self) -> PortScanner {
+        self.scanner
+    }
+
+    pub fn with_snmp_communities(mut self, snmp_communities: Vec<String>) -> DiscoveryOptions {
+        self.snmp_communities = snmp_communities;
+        self
+    }
+
+    pub fn smb_tool(&self) -> SmbTool {
+        self.smb_tool
+    }
+
+    pub fn smb_write_test(&self) -> bool {
+        self.smb_write_test
+    }
+
+    // Fall back to the usual suspects if no communities were given
+    pub fn snmp_communities(&self) -> Vec<String> {
+        match self.snmp_communities.is_empty() {
+            true => snmp::DEFAULT_COMMUNITIES
+                .split(',')
+                .map(str::to_string)
+                .collect(),
+            false => self.snmp_communities.clone(),
+        }
+    }
+
+    pub fn with_vhost_wordlist(mut self, vhost_wordlist: String) -> DiscoveryOptions {
+        self.vhost_wordlist = vhost_wordlist;
+        self
+    }
+
+    pub fn vhost_wordlist(&self) -> &String {
+        &self.vhost_wordlist
+    }
+
+    pub fn with_vuln_scanner(mut self, vuln_scanner: vulns::VulnScanner) -> DiscoveryOptions {
+        self.vuln_scanner = vuln_scanner;
+        self
+    }
+
+    pub fn vuln_scanner(&self) -> vulns::VulnScanner {
+        self.vuln_scanner
+    }
+
+    pub fn with_web_extension_rules(
+        mut self,
+        web_extension_rules: Vec<content::ExtensionRule>,
+    ) -> DiscoveryOptions {
+        self.web_extension_rules = web_extension_rules;
+        self
+    }
+
+    pub fn web_extension_rules(&self) -> &Vec<content::ExtensionRule> {
+        &self.web_extension_rules
+    }
+
+    pub fn with_web_follow_ups(
+        mut self,
+        web_follow_ups: Vec<webtech::FollowUp>,
+    ) -> DiscoveryOptions {
+        self.web_follow_ups = web_follow_ups;
+        self
+    }
+
+    pub fn web_follow_ups(&self) -> &Vec<webtech::FollowUp> {
+        &self.web_follow_ups
+    }
+
+    pub fn with_web_loot_max_size(mut self, web_loot_max_size: u64) -> DiscoveryOptions {
+        self.web_loot_max_size = web_loot_max_size;
+        self
+    }
+
+    pub fn web_loot_max_size(&self) -> u64 {
+        self.web_loot_max_size
+    }
+
+    pub fn with_web_scanner(mut self, web_scanner: content::WebScanner) -> DiscoveryOptions {
+        self.web_scanner = web_scanner;
+        self
+    }
+
+    pub fn with_web_threads(mut self, web_threads: usize) -> DiscoveryOptions {
+        self.web_threads = web_threads;
+        self
+    }
+
+    pub fn with_web_time_limit(mut self, web_time_limit: Duration) -> DiscoveryOptions {
+        self.web_time_limit = web_time_limit;
+        self
+    }
+
+    pub fn web_scanner(&self) -> content::WebScanner {
+        self.web_scanner
+    }
+
+    pub fn web_threads(&self) -> usize {
+        self.web_threads
+    }
+
+    pub fn web_time_limit(&self) -> Duration {
+        self.web_time_limit
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct IMDUser {
     gid: Gid,
@@ -84,9 +317,12 @@ impl IMDUser {
 
 #[derive(Clone, Debug)]
 pub struct TargetMachine {
+    findings: Arc<Mutex<Vec<(bool, String)>>>,
     hostname: Option<String>,
+    hostnames: Arc<Mutex<Vec<String>>>,
     ip_address: IpAddr,
     mp: Arc<MultiProgress>,
+    output_tag: Option<String>,
     prefix: String,
 }
 
@@ -95,13 +331,47 @@ impl TargetMachine {
     pub fn new(cli: CLITarget, prefix_size: usize, mp: Arc<MultiProgress>) -> TargetMachine {
         let prefix = cli.create_prefix(prefix_size);
         TargetMachine {
+            findings: Arc::new(Mutex::new(vec![])),
+            hostnames: Arc::new(Mutex::new(cli.hostname.clone().into_iter().collect())),
             hostname: cli.hostname,
             ip_address: cli.ip_address,
             mp,
+            output_tag: None,
             prefix,
         }
     }
 
+    // Note something for the target machine's summary, flagging it if it is a likely way in
+    fn add_finding(&self, flagged: bool, finding: String) {
+        self.findings.lock().unwrap().push((flagged, finding));
+    }
+
+    // Remember a hostname for the target machine, returning false if it was already known
+    fn add_hostname(&self, hostname: &str) -> bool {
+        let mut hostnames = self.hostnames.lock().unwrap();
+        if hostnames.iter().any(|known| known == hostname) {
+            return false;
+        }
+        hostnames.push(hostname.to_string());
+        true
+    }
+
+    // Take on a hostname imd found by itself if the user wants it, adding it to /etc/hosts. Returns true if it is new
+    fn accept_hostname(&self, ip_string: &str, hostname: &str, mode: HostnameMode) -> bool {
+        let accepted = match mode {
+            HostnameMode::Auto => true,
+            HostnameMode::Ignore => false,
+            HostnameMode::Prompt => {
+                self.confirm(&format!("Found hostname {hostname}, add it to the target?"))
+            }
+        };
+        if !accepted || !self.add_hostname(hostname) {
+            return false;
+        }
+        let _ = self.with_hostname(hostname).add_to_hosts(ip_string);
+        true
+    }
+
     // Add hostname the /etc/hosts file if there is in fact a hostname to add
     fn add_to_hosts(&self, ip_string: &str) -> Result<(), Box<dyn Error>> {
         let hostname = match &self.hostname {
@@ -109,15 +379,20 @@ impl TargetMachine {
             None => return Ok(()),
         };
         let bar = add_new_bar(self.mp());
-        let message = self.prefix.clone() + " Adding to /etc/hosts";
+        let message = self.prefix.clone() + &format!(" Adding {hostname} to /etc/hosts");
         bar.set_message(message.clone());
 
         let host_file = File::open("/etc/hosts")?;
         let reader = BufReader::new(host_file);
         for line in reader.lines() {
             let line = line?;
+            let mut entries = line
+                .split('#')
+                .next()
+                .unwrap_or_default()
+                .split_whitespace();
             // If a line contains the ip address and hostname already, let the user know it is already there and exit
-            if line.contains(ip_string) && line.contains(hostname) {
+            if entries.next() == Some(ip_string) && entries.any(|entry| entry == hostname) {
                 bar.finish_with_message(format!(
                     "{} {}",
                     message,
@@ -137,6 +412,68 @@ impl TargetMachine {
         Ok(())
     }
 
+    // Connect to a port the scanner couldn't identify, record what it says, and probe it to find out what it is
+    fn banner_grab(
+        &self,
+        ip_string: &str,
+        user: Arc<IMDUser>,
+        open_port: &OpenPort,
+        timeout: Duration,
+    ) -> Result<Option<String>, Box<dyn Error>> {
+        let port = open_port.port();
+        let bar = add_new_bar(self.mp());
+        let message = self.prefix.clone() + &format!(" Port {port} banner: native protocol probes");
+        bar.set_message(message.clone());
+
+        let result = probe::identify(self.ip_address, port, timeout);
+        let service = result.service().cloned().unwrap_or("unknown".to_string());
+
+        let output_file = format!("{ip_string}/banner_port_{port}");
+        let mut f = create_file(&output_file, user)?;
+        writeln!(
+            f,
+            "Port {port}/{} was reported as '{}' {}",
+            open_port.protocol(),
+            open_port.service(),
+            open_port.version()
+        )?;
+        writeln!(
+            f,
+            "Identified as '{service}' from the response to: {}",
+            result.probe()
+        )?;
+        writeln!(
+            f,
+            "\nFirst bytes received:\n{}",
+            probe::hexdump(result.response())
+        )?;
+
+        let message = format!("{message} {}", SUCCESS.green());
+        bar.finish_with_message(message);
+
+        // Only hand back services the web steps know what to do with
+        Ok(match service.as_str() {
+            "http" => Some("http".to_string()),
+            "ssl/http" => Some("https".to_string()),
+            _ => None,
+        })
+    }
+
+    // Ask the user a yes or no question without the progress bars drawing over it
+    fn confirm(&self, question: &str) -> bool {
+        // Targets run in parallel, so make sure only one of them is asking at a time
+        static PROMPT: Mutex<()> = Mutex::new(());
+        let _prompt = PROMPT.lock().unwrap();
+
+        self.mp.suspend(|| {
+            print!("{} {question} [Y/n] ", self.prefix);
+            let _ = std::io::stdout().flush();
+            let mut answer = String::new();
+            let _ = std::io::stdin().read_line(&mut answer);
+            !answer.trim().to_lowercase().starts_with('n')
+        })
+    }
+
     // Create a directory owned by the provided user
     pub fn create_results_dir(
         &self,
@@ -147,17 +484,1610 @@ impl TargetMachine {
         let message = self.prefix.clone() + " Directory to store results in";
         bar.set_message(message.clone());
 
-        // If it fails, it's probably because the directory already exists (not 100%, but pretty likely), so report that and move on
-        if fs::create_dir(dir_name).is_err() {
-            bar.finish_with_message(format!(
-                "{} {}",
-                message,
-                RecoverableDiscoveryError::DirectoryExists
-            ));
-            return Ok(());
+        // If it fails, it's probably because the directory already exists (not 100%, but pretty likely), so report that and move on
+        if fs::create_dir(dir_name).is_err() {
+            bar.finish_with_message(format!(
+                "{} {}",
+                message,
+                RecoverableDiscoveryError::DirectoryExists
+            ));
+            return Ok(());
+        }
+
+        change_owner(dir_name, user)?;
+
+        let message = format!("{message} {}", SUCCESS.green());
+        bar.finish_with_message(message);
+
+        Ok(())
+    }
+
+    // Catchall method for running discovery on a target machine
+    pub fn discovery(
+        &self,
+        user: Arc<IMDUser>,
+        wordlist: Arc<String>,
+        options: Arc<DiscoveryOptions>,
+    ) {
+        let ip_string = self.ip_as_string();
+        let _ = self.ping(&ip_string);
+        let _ = self.add_to_hosts(&ip_string);
+        if self.create_results_dir(&ip_string, user.clone()).is_err() {
+            return;
+        }
+
+        // Fall back to the native scanner if nmap isn't installed on this machine
+        let use_nmap = options.scanner() == PortScanner::Nmap && command_exists("nmap");
+
+        let timeout = options.native_scanner().timeout();
+
+        let mut threads: Vec<std::thread::JoinHandle<()>> = vec![];
+
+        threads.push(std::thread::spawn({
+            let clone = self.clone();
+            let ip_string = ip_string.clone();
+            let options = options.clone();
+            let user = user.clone();
+            move || {
+                let all_ports = if use_nmap {
+                    clone.nmap_all_tcp_ports(&ip_string, user)
+                } else {
+                    clone
+                        .native_tcp_ports(&ip_string, user, options.native_scanner(), true)
+                        .map(|_| ())
+                };
+                if all_ports.is_err() {}
+            }
+        }));
+
+        // UDP scans are slow, so the UDP port scan and the steps that depend on it get their own thread
+        let udp_scan = std::thread::spawn({
+            let clone = self.clone();
+            let communities = options.snmp_communities();
+            let ip_string = ip_string.clone();
+            let user = user.clone();
+            move || {
+                let udp_ports = match use_nmap {
+                    true => clone
+                        .nmap_common_udp_ports(&ip_string, user.clone())
+                        .map(|udp_scan| scanner::parse_open_ports(&udp_scan))
+                        .unwrap_or_default(),
+                    false => vec![],
+                };
+
+                // Without nmap there is no UDP scan, but asking for SNMP is cheap enough to do anyway
+                let snmp_open = udp_ports
+                    .iter()
+                    .any(|p| p.protocol() == "udp" && p.port() == 161);
+                if snmp_open || !use_nmap {
+                    let _ = clone.snmp(&ip_string, user, &communities, timeout);
+                }
+
+                udp_ports
+            }
+        });
+
+        let port_scan = if use_nmap {
+            self.nmap_common_tcp_ports(&ip_string, user.clone())
+        } else {
+            self.native_tcp_ports(&ip_string, user.clone(), options.native_scanner(), false)
+        };
+        let port_scan = match port_scan {
+            Ok(port_scan) => port_scan,
+            Err(_) => return,
+        };
+
+        let open_ports = self.parse_port_scan(port_scan.clone());
+        let mut services = match self.web_detection(&ip_string, user.clone(), &open_ports, timeout)
+        {
+            Ok(services) => services,
+            Err(_) => scanner::web_services(&open_ports),
+        };
+
+        // Probe the ports the scanner couldn't put a name to, and send any websites found that way to the web steps
+        let mut banner_threads: Vec<std::thread::JoinHandle<Option<(String, String)>>> = vec![];
+        for open_port in open_ports
+            .iter()
+            .filter(|p| !p.identified() && p.protocol() == "tcp")
+        {
+            banner_threads.push(std::thread::spawn({
+                let clone = self.clone();
+                let ip_string = ip_string.clone();
+                let open_port = open_port.clone();
+                let user = user.clone();
+                move || match clone.banner_grab(&ip_string, user, &open_port, timeout) {
+                    Ok(Some(service)) => Some((service, open_port.port().to_string())),
+                    _ => None,
+                }
+            }));
+        }
+        for thread in banner_threads {
+            if let Some((service, port)) = thread.join().unwrap() {
+                let ports = services.entry(service).or_default();
+                if !ports.contains(&port) {
+                    ports.push(port);
+                }
+            }
+        }
+
+        // Windows and DNS servers both know names for the target, and DNS can use the domain Windows gives up
+        threads.push(std::thread::spawn({
+            let clone = self.clone();
+            let ip_string = ip_string.clone();
+            let mode = options.hostname_mode();
+            let open_ports = open_ports.clone();
+            let user = user.clone();
+            move || {
+                if open_ports
+                    .iter()
+                    .any(|p| p.protocol() == "tcp" && windows::WINDOWS_PORTS.contains(&p.port()))
+                {
+                    if let Ok(found) = clone.windows_info(&ip_string, user.clone(), &open_ports) {
+                        for hostname in found {
+                            clone.accept_hostname(&ip_string, &hostname, mode);
+                        }
+                    }
+                }
+
+                // Only wait on the slow UDP scan if DNS isn't already known to be open over TCP
+                let tcp_dns = open_ports
+                    .iter()
+                    .any(|p| p.protocol() == "tcp" && p.port() == dns::DNS_PORT);
+                if tcp_dns {
+                    let _ = clone.dns_records(&ip_string, user.clone(), mode);
+                }
+
+                let udp_ports = udp_scan.join().unwrap_or_default();
+                let udp_dns = udp_ports
+                    .iter()
+                    .any(|p| p.protocol() == "udp" && p.port() == dns::DNS_PORT);
+                if !tcp_dns && udp_dns {
+                    let _ = clone.dns_records(&ip_string, user, mode);
+                }
+            }
+        }));
+
+        for open_port in open_ports
+            .iter()
+            .filter(|p| p.protocol() == "tcp" && p.service() == "ftp")
+        {
+            threads.push(std::thread::spawn({
+                let clone = self.clone();
+                let ip_string = ip_string.clone();
+                let max_size = options.ftp_max_size();
+                let port = open_port.port();
+                let user = user.clone();
+                move || {
+                    if clone
+                        .ftp_anonymous(&ip_string, user, port, timeout, max_size)
+                        .is_err()
+                    {}
+                }
+            }));
+        }
+
+        if open_ports
+            .iter()
+            .any(|p| p.protocol() == "tcp" && smb::SMB_PORTS.contains(&p.port()))
+        {
+            threads.push(std::thread::spawn({
+                let clone = self.clone();
+                let ip_string = ip_string.clone();
+                let tool = options.smb_tool();
+                let user = user.clone();
+                let write_test = options.smb_write_test();
+                move || {
+                    if clone
+                        .smb_shares(&ip_string, user, tool, write_test)
+                        .is_err()
+                    {}
+                }
+            }));
+        }
+
+        for open_port in &open_ports {
+            let database = match db::Database::from_open_port(open_port) {
+                Some(database) => database,
+                None => continue,
+            };
+            threads.push(std::thread::spawn({
+                let clone = self.clone();
+                let ip_string = ip_string.clone();
+                let open_port = open_port.clone();
+                let user = user.clone();
+                move || {
+                    if clone
+                        .database(&ip_string, user, database, &open_port, timeout)
+                        .is_err()
+                    {}
+                }
+            }));
+        }
+
+        for open_port in &open_ports {
+            let service = match remote::RemoteService::from_open_port(open_port) {
+                Some(service) => service,
+                None => continue,
+            };
+            threads.push(std::thread::spawn({
+                let clone = self.clone();
+                let ip_string = ip_string.clone();
+                let open_port = open_port.clone();
+                let user = user.clone();
+                move || {
+                    if clone
+                        .remote_access(&ip_string, user, service, &open_port, timeout)
+                        .is_err()
+                    {}
+                }
+            }));
+        }
+
+        for open_port in &open_ports {
+            let protocol = match mail::MailProtocol::from_open_port(open_port) {
+                Some(protocol) => protocol,
+                None => continue,
+            };
+            threads.push(std::thread::spawn({
+                let clone = self.clone();
+                let ip_string = ip_string.clone();
+                let port = open_port.port();
+                let user = user.clone();
+                let usernames = options.mail_usernames();
+                move || {
+                    if clone
+                        .mail(&ip_string, user, protocol, port, timeout, &usernames)
+                        .is_err()
+                    {}
+                }
+            }));
+        }
+
+        for open_port in open_ports
+            .iter()
+            .filter(|p| p.protocol() == "tcp" && (p.service() == "ssh" || p.port() == 22))
+        {
+            threads.push(std::thread::spawn({
+                let clone = self.clone();
+                let ip_string = ip_string.clone();
+                let port = open_port.port();
+                let user = user.clone();
+                move || {
+                    if clone.ssh_info(&ip_string, user, port, timeout).is_err() {}
+                }
+            }));
+        }
+
+        // NFS exports are only listed when the portmapper is there to say where mountd is
+        let portmapper = open_ports
+            .iter()
+            .any(|p| p.protocol() == "tcp" && p.port() == rpc::PORTMAPPER_PORT);
+        let msrpc = open_ports
+            .iter()
+            .any(|p| p.protocol() == "tcp" && p.port() == rpc::MSRPC_PORT);
+        if portmapper || msrpc {
+            threads.push(std::thread::spawn({
+                let clone = self.clone();
+                let ip_string = ip_string.clone();
+                let mount_nfs = options.mount_nfs();
+                let user = user.clone();
+                move || {
+                    let programs = clone
+                        .rpc_services(&ip_string, user.clone(), portmapper, msrpc)
+                        .unwrap_or_default();
+                    // showmount needs mountd, unless rpcinfo couldn't tell us either way
+                    let mountd = rpc::versions(&programs, "mountd");
+                    if portmapper && (programs.is_empty() || !mountd.is_empty()) {
+                        let nfs_versions = rpc::versions(&programs, "nfs");
+                        let _ = clone.showmount_network_drives(
+                            &ip_string,
+                            user,
+                            mount_nfs,
+                            &nfs_versions,
+                        );
+                    }
+                }
+            }));
+        }
+
+        if let Some(url) = ldap::preferred_url(&ip_string, &open_ports) {
+            threads.push(std::thread::spawn({
+                let clone = self.clone();
+                let ip_string = ip_string.clone();
+                let user = user.clone();
+                move || {
+                    if clone.ldap_dump(&ip_string, user, &url).is_err() {}
+                }
+            }));
+        }
+
+        // Web steps run against the target as given, and again for every hostname discovered along the way
+        let mut web_targets = vec![self.clone()];
+        if let Ok(found) =
+            self.discover_hostnames(&ip_string, user.clone(), &port_scan, &services, timeout)
+        {
+            for hostname in found {
+                if self.accept_hostname(&ip_string, &hostname, options.hostname_mode()) {
+                    web_targets.push(self.with_hostname(&hostname));
+                }
+            }
+        }
+
+        for web_target in &web_targets {
+            threads.extend(web_target.web_steps(
+                &ip_string,
+                user.clone(),
+                &services,
+                wordlist.clone(),
+                options.clone(),
+            ));
+        }
+
+        // Virtual hosts need a domain to go in front of, so fuzz under each hostname that isn't already under another
+        let hostnames: Vec<String> = web_targets
+            .iter()
+            .filter_map(|web_target| web_target.hostname.clone())
+            .collect();
+        let domains = vhosts::fuzz_domains(&hostnames);
+        let words = Arc::new(vhosts::read_wordlist(options.vhost_wordlist()));
+        for web_target in web_targets {
+            if !web_target
+                .hostname
+                .as_ref()
+                .is_some_and(|hostname| domains.contains(hostname))
+            {
+                continue;
+            }
+            threads.push(std::thread::spawn({
+                let ip_string = ip_string.clone();
+                let options = options.clone();
+                let services = services.clone();
+                let user = user.clone();
+                let wordlist = wordlist.clone();
+                let words = words.clone();
+                move || {
+                    let mut found = vec![];
+                    for (service, ports) in services.iter() {
+                        for port in ports {
+                            if let Ok(hits) = web_target.vhosts(
+                                &ip_string,
+                                user.clone(),
+                                service,
+                                port,
+                                &words,
+                                timeout,
+                            ) {
+                                found.extend(hits);
+                            }
+                        }
+                    }
+                    found.sort();
+                    found.dedup();
+
+                    // Virtual hosts the user takes on get the same web scans as the target itself
+                    let mut vhost_threads = vec![];
+                    for hostname in found {
+                        if web_target.accept_hostname(
+                            &ip_string,
+                            &hostname,
+                            options.hostname_mode(),
+                        ) {
+                            vhost_threads.extend(web_target.with_hostname(&hostname).web_steps(
+                                &ip_string,
+                                user.clone(),
+                                &services,
+                                wordlist.clone(),
+                                options.clone(),
+                            ));
+                        }
+                    }
+                    for thread in vhost_threads {
+                        thread.join().unwrap();
+                    }
+                }
+            }));
+        }
+
+        for thread in threads {
+            thread.join().unwrap();
+        }
+
+        if self.summary(&ip_string, user).is_err() {}
+    }
+
+    // Spin up the threads that scan each web port
+    fn web_steps(
+        &self,
+        ip_string: &str,
+        user: Arc<IMDUser>,
+        services: &HashMap<String, Vec<String>>,
+        wordlist: Arc<String>,
+        options: Arc<DiscoveryOptions>,
+    ) -> Vec<std::thread::JoinHandle<()>> {
+        let mut threads: Vec<std::thread::JoinHandle<()>> = vec![];
+
+        for (service, ports) in services.iter() {
+            for port in ports {
+                // The content discovery thread waits on web_tech's fingerprint to pick extensions, rather than taking its own
+                let (signature_sender, signature_receiver) = std::sync::mpsc::channel();
+                // Spin up a thread to work out what the site is built with and follow up on it
+                threads.push(std::thread::spawn({
+                    let clone = self.clone();
+                    let ip_string = ip_string.to_string();
+                    let options = options.clone();
+                    let port = port.clone();
+                    let service = service.clone();
+                    let user = user.clone();
+                    move || {
+                        if clone
+                            .web_tech(
+                                &ip_string,
+                                user,
+                                &service,
+                                &port,
+                                &options,
+                                signature_sender,
+                            )
+                            .is_err()
+                        {}
+                    }
+                }));
+                // Spin up a thread for the vuln scan
+                threads.push(std::thread::spawn({
+                    let clone = self.clone();
+                    let ip_string = ip_string.to_string();
+                    let port = port.clone();
+                    let service = service.clone();
+                    let options = options.clone();
+                    let user = user.clone();
+                    move || {
+                        if clone
+                            .vuln_scan(&ip_string, user, &service, &port, &options)
+                            .is_err()
+                        {}
+                    }
+                }));
+                // Spin up a thread that crawls the site, scans for web dirs and files starting with what the crawl found, with extensions picked from the site's fingerprint, then probes them for exposed files
+                threads.push(std::thread::spawn({
+                    let clone = self.clone();
+                    let ip_string = ip_string.to_string();
+                    let port = port.clone();
+                    let service = service.clone();
+                    let user = user.clone();
+                    let options = options.clone();
+                    let wordlist = wordlist.clone();
+                    move || {
+                        let seeds = clone
+                            .web_crawl(&ip_string, user.clone(), &service, &port, &options)
+                            .unwrap_or_default();
+                        // web_tech hangs up without sending if fingerprinting fails, which leaves no rules to match
+                        let signature = signature_receiver.recv().unwrap_or_default();
+                        let scan = content::ContentScan::new(
+                            &ip_string,
+                            &wordlist,
+                            options.web_threads(),
+                            options.web_time_limit(),
+                        )
+                        .with_rules(options.web_extension_rules(), &signature)
+                        .with_seeds(seeds);
+                        let hits = clone
+                            .web_presence_scan(
+                                &ip_string,
+                                user.clone(),
+                                &service,
+                                &port,
+                                &scan,
+                                &options,
+                            )
+                            .unwrap_or_default();
+                        if clone
+                            .web_exposures(&ip_string, user, &service, &port, &hits, &options)
+                            .is_err()
+                        {}
+                    }
+                }));
+            }
+        }
+
+        threads
+    }
+
+    // Find hostnames the target machine goes by from web redirects and TLS certificates
+    fn discover_hostnames(
+        &self,
+        ip_string: &str,
+        user: Arc<IMDUser>,
+        port_scan: &str,
+        services: &HashMap<String, Vec<String>>,
+        timeout: Duration,
+    ) -> Result<Vec<String>, Box<dyn Error>> {
+        let bar = add_new_bar(self.mp());
+        let message = self.prefix.clone() + " Hostnames: web redirects and TLS certificates";
+        bar.set_message(message.clone());
+
+        let mut found = hostnames::from_port_scan(port_scan);
+
+        // The port scan only follows redirects on ports it recognized as web, so ask every web port directly too
+        for (protocol, ports) in services.iter() {
+            for port in ports {
+                let client = HttpClient::new(protocol, self.ip_address, port.parse()?, timeout);
+                let response = match client.get(&self.web_target(), "/") {
+                    Ok(response) => response,
+                    Err(_) => continue,
+                };
+                if let Some(hostname) = response.header("Location").and_then(hostnames::from_url) {
+                    found.push((hostname, format!("HTTP redirect on port {port}")));
+                }
+            }
+        }
+
+        let found = hostnames::dedup(found);
+        let known = self.hostnames.lock().unwrap().clone();
+
+        let output_file = format!("{ip_string}/discovered_hostnames");
+        let mut f = create_file(&output_file, user)?;
+        for (hostname, source) in &found {
+            writeln!(f, "{hostname: <40} {source}")?;
+        }
+
+        let message = format!("{message} {}", SUCCESS.green());
+        bar.finish_with_message(message);
+
+        Ok(found
+            .into_iter()
+            .map(|(hostname, _)| hostname)
+            .filter(|hostname| !known.contains(hostname))
+            .collect())
+    }
+
+    // Log in to FTP anonymously, list everything, download the small files and check whether uploads are allowed
+    fn ftp_anonymous(
+        &self,
+        ip_string: &str,
+        user: Arc<IMDUser>,
+        port: u16,
+        timeout: Duration,
+        max_size: u64,
+    ) -> Result<(), Box<dyn Error>> {
+        let bar = add_new_bar(self.mp());
+        let message = self.prefix.clone() + &format!(" Port {port} FTP: native anonymous login");
+        bar.set_message(message.clone());
+
+        let output_file = format!("{ip_string}/ftp_listing_port_{port}");
+        let mut f = create_file(&output_file, user.clone())?;
+
+        let mut client = ftp::FtpClient::connect(self.ip_address, port, timeout)?;
+        if let Err(e) = client.login_anonymous() {
+            writeln!(f, "Anonymous login refused: {e}")?;
+            bar.finish_with_message(format!("{message} {}", SUCCESS.green()));
+            return Ok(());
+        }
+        writeln!(f, "Anonymous login allowed")?;
+        self.add_finding(true, format!("FTP on port {port} allows anonymous login"));
+
+        let entries = client.list_recursive();
+        let loot_dir = match port {
+            21 => format!("{ip_string}/loot/ftp"),
+            _ => format!("{ip_string}/loot/ftp_port_{port}"),
+        };
+
+        writeln!(
+            f,
+            "\n{: <6}{: >12}  {: <12}PATH",
+            "TYPE", "SIZE", "DOWNLOADED"
+        )?;
+        for entry in &entries {
+            let size = entry
+                .size()
+                .map(|s| s.to_string())
+                .unwrap_or("?".to_string());
+            let downloaded = match entry.is_dir() || entry.size().is_some_and(|s| s > max_size) {
+                true => "no",
+                false => match self.ftp_download(
+                    &mut client,
+                    entry.path(),
+                    &loot_dir,
+                    user.clone(),
+                    max_size,
+                ) {
+                    Ok(()) => "yes",
+                    Err(_) => "failed",
+                },
+            };
+            writeln!(
+                f,
+                "{: <6}{size: >12}  {downloaded: <12}{}",
+                if entry.is_dir() { "dir" } else { "file" },
+                entry.path()
+            )?;
+        }
+
+        let writable = client.test_upload().unwrap_or(false);
+        writeln!(
+            f,
+            "\nAnonymous upload allowed: {}",
+            if writable { "yes" } else { "no" }
+        )?;
+        if writable {
+            self.add_finding(true, format!("FTP on port {port} allows anonymous uploads"));
+        }
+
+        let message = format!("{message} {}", SUCCESS.green());
+        bar.finish_with_message(message);
+
+        Ok(())
+    }
+
+    // Save a file from the FTP server under the loot directory, mirroring its path on the server
+    fn ftp_download(
+        &self,
+        client: &mut ftp::FtpClient,
+        path: &str,
+        loot_dir: &str,
+        user: Arc<IMDUser>,
+        max_size: u64,
+    ) -> Result<(), Box<dyn Error>> {
+        let contents = client.retrieve(path, max_size)?;
+        let local = format!("{loot_dir}{path}");
+        if let Some(parent) = Path::new(&local).parent() {
+            create_dirs(&parent.to_string_lossy(), user.clone())?;
+        }
+        let mut f = create_file(&local, user)?;
+        f.write_all(&contents)?;
+        Ok(())
+    }
+
+    // Return the IP address as a string
+    fn ip_as_string(&self) -> String {
+        self.ip_address.to_string()
+    }
+
+    // Return a clone of the MultiProgress container
+    fn mp(&self) -> Arc<MultiProgress> {
+        self.mp.clone()
+    }
+
+    // Record a mail server's banner and capabilities, and for SMTP find out which usernames it will confirm
+    fn mail(
+        &self,
+        ip_string: &str,
+        user: Arc<IMDUser>,
+        protocol: mail::MailProtocol,
+        port: u16,
+        timeout: Duration,
+        usernames: &[String],
+    ) -> Result<(), Box<dyn Error>> {
+        let bar = add_new_bar(self.mp());
+        let message = match protocol {
+            mail::MailProtocol::Smtp => {
+                self.prefix.clone()
+                    + &format!(
+                        " Port {port} SMTP: capabilities and VRFY/EXPN/RCPT user enumeration"
+                    )
+            }
+            _ => {
+                self.prefix.clone()
+                    + &format!(
+                        " Port {port} {}: banner and capabilities",
+                        protocol.as_str()
+                    )
+            }
+        };
+        bar.set_message(message.clone());
+
+        let report = match protocol {
+            mail::MailProtocol::Imap => mail::imap(self.ip_address, port, timeout)?,
+            mail::MailProtocol::Pop3 => mail::pop3(self.ip_address, port, timeout)?,
+            mail::MailProtocol::Smtp => mail::smtp(self.ip_address, port, timeout, usernames)?,
+        };
+
+        let output_file = format!("{ip_string}/mail_port_{port}");
+        let mut f = create_file(&output_file, user.clone())?;
+        writeln!(f, "Protocol: {}", protocol.as_str())?;
+        writeln!(f, "Banner: {}", report.banner())?;
+        writeln!(f, "\nCapabilities:")?;
+        for capability in report.capabilities() {
+            writeln!(f, "    {capability}")?;
+        }
+
+        if protocol == mail::MailProtocol::Smtp {
+            writeln!(f, "\nUser enumeration:")?;
+            for (method, status) in report.methods() {
+                writeln!(f, "    {method: <6}{status}")?;
+            }
+            writeln!(f, "\nConfirmed users:")?;
+            for (username, method) in report.users() {
+                writeln!(f, "    {username: <24}{method}")?;
+            }
+
+            if !report.users().is_empty() {
+                let usernames: Vec<String> =
+                    report.users().iter().map(|(u, _)| u.clone()).collect();
+                self.add_finding(
+                    true,
+                    format!(
+                        "SMTP on port {port} confirmed the users {}",
+                        usernames.join(", ")
+                    ),
+                );
+                save_usernames(ip_string, user, &usernames)?;
+            }
+        }
+
+        let message = format!("{message} {}", SUCCESS.green());
+        bar.finish_with_message(message);
+
+        Ok(())
+    }
+
+    // Discover open TCP ports without relying on nmap, either across the configured ports or every port
+    fn native_tcp_ports(
+        &self,
+        ip_string: &str,
+        user: Arc<IMDUser>,
+        scanner: &NativeScanner,
+        all_ports: bool,
+    ) -> Result<String, Box<dyn Error>> {
+        let bar = add_new_bar(self.mp());
+        let (message, output_file, ports) = if all_ports {
+            (
+                self.prefix.clone() + " All TCP ports: native connect scan",
+                format!("{ip_string}/all_tcp_ports"),
+                (1..=u16::MAX).collect(),
+            )
+        } else {
+            (
+                self.prefix.clone() + " Common TCP ports: native connect scan",
+                format!("{ip_string}/common_tcp_ports"),
+                scanner.ports().clone(),
+            )
+        };
+        bar.set_message(message.clone());
+
+        let results = scanner.scan(self.ip_address, &ports);
+        let command = scanner::format_results(self.ip_address, &results);
+
+        let mut f = create_file(&output_file, user)?;
+        writeln!(f, "{command}")?;
+
+        let message = format!("{message} {}", SUCCESS.green());
+        bar.finish_with_message(message);
+
+        Ok(command)
+    }
+
+    // See whether a database server lets us in without credentials, and what it holds if it does
+    fn database(
+        &self,
+        ip_string: &str,
+        user: Arc<IMDUser>,
+        database: db::Database,
+        open_port: &OpenPort,
+        timeout: Duration,
+    ) -> Result<(), Box<dyn Error>> {
+        let port = open_port.port();
+        let bar = add_new_bar(self.mp());
+        let message = self.prefix.clone()
+            + &format!(
+                " Port {port} {}: '{}'",
+                database.as_str(),
+                database.check_name()
+            );
+        bar.set_message(message.clone());
+
+        let tls = open_port.service().starts_with("ssl/");
+        let report = database.check(self.ip_address, port, tls, timeout)?;
+
+        let output_file = format!("{ip_string}/db_{}_port_{port}", database.as_str());
+        let mut f = create_file(&output_file, user)?;
+        writeln!(
+            f,
+            "Unauthenticated access: {}",
+            match (report.open(), report.untested()) {
+                (true, _) => "yes",
+                (false, true) => "not tested",
+                (false, false) => "no",
+            }
+        )?;
+        writeln!(f, "Status: {}", report.status())?;
+        if !report.inventory().is_empty() {
+            writeln!(f, "\nInventory:")?;
+            for item in report.inventory() {
+                writeln!(f, "    {item}")?;
+            }
+        }
+        writeln!(f, "\n{}", report.raw())?;
+
+        if report.open() {
+            self.add_finding(
+                true,
+                format!("{} on port {port}: {}", database.as_str(), report.status()),
+            );
+        }
+
+        let message = format!("{message} {}", SUCCESS.green());
+        bar.finish_with_message(message);
+
+        Ok(())
+    }
+
+    // Ask the target's DNS server for zone transfers of every domain we know of and for its own name
+    fn dns_records(
+        &self,
+        ip_string: &str,
+        user: Arc<IMDUser>,
+        mode: HostnameMode,
+    ) -> Result<(), Box<dyn Error>> {
+        let bar = add_new_bar(self.mp());
+        let message = self.prefix.clone() + " DNS: 'dig -x' and 'dig axfr'";
+        bar.set_message(message.clone());
+
+        let server = format!("@{ip_string}");
+        let reverse = run_command_with_args("dig", vec![&server, "-x", ip_string])?;
+        let mut found = dns::hostnames_from_records(&reverse);
+
+        let output_file = format!("{ip_string}/dns_records");
+        let mut f = create_file(&output_file, user)?;
+        writeln!(f, "===== Reverse lookup of {ip_string} =====\n{reverse}")?;
+
+        // A name from the reverse lookup can point to a zone we didn't know about yet
+        let known: Vec<String> = self
+            .hostnames
+            .lock()
+            .unwrap()
+            .iter()
+            .chain(found.iter())
+            .cloned()
+            .collect();
+        for domain in dns::candidate_domains(&known) {
+            let transfer = run_command_with_args("dig", vec!["axfr", &server, &domain])?;
+            writeln!(f, "===== Zone transfer of {domain} =====\n{transfer}")?;
+            if dns::axfr_succeeded(&transfer) {
+                self.add_finding(true, format!("DNS allows a zone transfer of {domain}"));
+                found.extend(dns::hostnames_from_records(&transfer));
+            }
+        }
+
+        found.sort();
+        found.dedup();
+        for hostname in found {
+            if self.accept_hostname(ip_string, &hostname, mode) {
+                self.add_finding(false, format!("DNS gave up the hostname {hostname}"));
+            }
+        }
+
+        let message = format!("{message} {}", SUCCESS.green());
+        bar.finish_with_message(message);
+
+        Ok(())
+    }
+
+    // Bind to LDAP anonymously, read the rootDSE and dump the users and groups if the server lets us search
+    fn ldap_dump(
+        &self,
+        ip_string: &str,
+        user: Arc<IMDUser>,
+        url: &str,
+    ) -> Result<(), Box<dyn Error>> {
+        let bar = add_new_bar(self.mp());
+        let message =
+            self.prefix.clone() + &format!(" LDAP: 'ldapsearch -x' anonymous bind on {url}");
+        bar.set_message(message.clone());
+
+        let root_dse = ldap::search(url, "", "base", "(objectClass=*)", &["*", "+"])?;
+        let entries = ldap::parse_ldif(&root_dse);
+
+        let output_file = format!("{ip_string}/ldap_dump");
+        let mut f = create_file(&output_file, user.clone())?;
+        writeln!(f, "===== rootDSE =====\n{root_dse}")?;
+
+        let base = match ldap::naming_context(&entries) {
+            Some(base) => base,
+            None => {
+                writeln!(
+                    f,
+                    "No naming context found, the anonymous bind was probably refused"
+                )?;
+                bar.finish_with_message(format!("{message} {}", SUCCESS.green()));
+                return Ok(());
+            }
+        };
+
+        let users = ldap::search(
+            url,
+            &base,
+            "sub",
+            ldap::USER_FILTER,
+            &["sAMAccountName", "uid", "cn", "description", "memberOf"],
+        )?;
+        let groups = ldap::search(
+            url,
+            &base,
+            "sub",
+            ldap::GROUP_FILTER,
+            &["cn", "description", "member", "memberUid"],
+        )?;
+        let users = ldap::parse_ldif(&users);
+        let groups = ldap::parse_ldif(&groups);
+
+        if users.is_empty() && groups.is_empty() {
+            writeln!(f, "Anonymous search of {base} returned nothing")?;
+            bar.finish_with_message(format!("{message} {}", SUCCESS.green()));
+            return Ok(());
+        }
+        self.add_finding(
+            true,
+            format!(
+                "LDAP allows anonymous search of {base} ({} users, {} groups)",
+                users.len(),
+                groups.len()
+            ),
+        );
+
+        let mut usernames = vec![];
+        writeln!(f, "===== Users in {base} =====")?;
+        writeln!(f, "{: <24}DESCRIPTION", "USERNAME")?;
+        for entry in &users {
+            let username = match entry.first(&["sAMAccountName", "uid", "cn"]) {
+                Some(username) => username.clone(),
+                None => continue,
+            };
+            let description = entry.first(&["description"]).cloned().unwrap_or_default();
+            writeln!(f, "{username: <24}{description}")?;
+            if ldap::has_secret(&description) {
+                self.add_finding(
+                    true,
+                    format!("LDAP user {username} has the description '{description}'"),
+                );
+            }
+            // Machine accounts end in $ and aren't worth trying passwords against
+            if !username.ends_with('$') {
+                usernames.push(username);
+            }
+        }
+
+        writeln!(f, "\n===== Groups in {base} =====")?;
+        writeln!(f, "{: <32}{: <9}DESCRIPTION", "GROUP", "MEMBERS")?;
+        for entry in &groups {
+            let name = entry.first(&["cn"]).cloned().unwrap_or(entry.dn().clone());
+            let members = entry.values("member").len() + entry.values("memberUid").len();
+            let description = entry.first(&["description"]).cloned().unwrap_or_default();
+            writeln!(f, "{name: <32}{members: <9}{description}")?;
+        }
+
+        save_usernames(ip_string, user, &usernames)?;
+
+        let message = format!("{message} {}", SUCCESS.green());
+        bar.finish_with_message(message);
+
+        Ok(())
+    }
+
+    // Record how a remote access service wants clients to authenticate, which says which credentials are worth finding
+    fn remote_access(
+        &self,
+        ip_string: &str,
+        user: Arc<IMDUser>,
+        service: remote::RemoteService,
+        open_port: &OpenPort,
+        timeout: Duration,
+    ) -> Result<(), Box<dyn Error>> {
+        let port = open_port.port();
+        let bar = add_new_bar(self.mp());
+        let message = self.prefix.clone()
+            + &format!(
+                " Port {port} {}: {}",
+                service.as_str().to_uppercase(),
+                service.check_name()
+            );
+        bar.set_message(message.clone());
+
+        let report = service.check(self.ip_address, open_port, timeout)?;
+
+        let output_file = format!("{ip_string}/{}_port_{port}", service.as_str());
+        let mut f = create_file(&output_file, user)?;
+        for (key, value) in report.details() {
+            writeln!(f, "{: <20}{value}", format!("{key}:"))?;
+        }
+        for (flagged, finding) in report.findings() {
+            self.add_finding(*flagged, finding.clone());
+        }
+
+        let message = format!("{message} {}", SUCCESS.green());
+        bar.finish_with_message(message);
+
+        Ok(())
+    }
+
+    // List the programs registered with the portmapper and the interfaces behind the MSRPC endpoint mapper
+    fn rpc_services(
+        &self,
+        ip_string: &str,
+        user: Arc<IMDUser>,
+        portmapper: bool,
+        msrpc: bool,
+    ) -> Result<Vec<rpc::RpcProgram>, Box<dyn Error>> {
+        let bar = add_new_bar(self.mp());
+        let message = self.prefix.clone() + " RPC services: 'rpcinfo -p' and 'rpcdump'";
+        bar.set_message(message.clone());
+
+        let output_file = format!("{ip_string}/rpc_services");
+        let mut f = create_file(&output_file, user)?;
+
+        let mut programs = vec![];
+        if portmapper {
+            let command = run_command_with_args("rpcinfo", vec!["-p", ip_string])?;
+            programs = rpc::parse_rpcinfo(&command);
+            writeln!(f, "===== Portmapper (rpcinfo -p) =====\n{command}")?;
+
+            let mut services: Vec<&String> = programs
+                .iter()
+                .map(|p| p.service())
+                .filter(|s| !s.is_empty() && *s != "portmapper")
+                .collect();
+            services.sort();
+            services.dedup();
+            if !services.is_empty() {
+                let services: Vec<&str> = services.iter().map(|s| s.as_str()).collect();
+                self.add_finding(
+                    false,
+                    format!("RPC programs registered: {}", services.join(", ")),
+                );
+            }
+        }
+
+        if msrpc {
+            // impacket's scripts are installed under different names depending on how it was installed
+            match ["impacket-rpcdump", "rpcdump.py"]
+                .into_iter()
+                .find(|tool| command_exists(tool))
+            {
+                Some(tool) => {
+                    let command = run_command_with_args(tool, vec![ip_string])?;
+                    let interfaces = rpc::parse_rpcdump(&command);
+                    writeln!(f, "===== MSRPC endpoint mapper ({tool}) =====")?;
+                    for interface in &interfaces {
+                        writeln!(f, "{interface}")?;
+                    }
+                    for (name, reason) in rpc::risky_interfaces(&interfaces) {
+                        self.add_finding(true, format!("MSRPC exposes {name} ({reason})"));
+                    }
+                    writeln!(f, "\n{command}")?;
+                }
+                None => writeln!(
+                    f,
+                    "===== MSRPC endpoint mapper =====\nSkipped, impacket's rpcdump is not installed"
+                )?,
+            }
+        }
+
+        let message = format!("{message} {}", SUCCESS.green());
+        bar.finish_with_message(message);
+
+        Ok(programs)
+    }
+
+    // Discover open TCP ports
+    fn nmap_all_tcp_ports(
+        &self,
+        ip_string: &str,
+        user: Arc<IMDUser>,
+    ) -> Result<(), Box<dyn Error>> {
+        let bar = add_new_bar(self.mp());
+        let message = self.prefix.clone() + " All TCP ports: 'nmap -p- -Pn'";
+        bar.set_message(message.clone());
+
+        let args = vec!["-p-", "-Pn", ip_string];
+        let command = run_command_with_args("nmap", args)?;
+
+        let output_file = format!("{ip_string}/all_tcp_ports");
+        let mut f = create_file(&output_file, user)?;
+        writeln!(f, "{command}")?;
+
+        let message = format!("{message} {}", SUCCESS.green());
+        bar.finish_with_message(message);
+
+        Ok(())
+    }
+
+    // Discover (with service information) common open TCP ports
+    fn nmap_common_tcp_ports(
+        &self,
+        ip_string: &str,
+        user: Arc<IMDUser>,
+    ) -> Result<String, Box<dyn Error>> {
+        let bar = add_new_bar(self.mp());
+        let message = self.prefix.clone()
+            + " Common TCP ports: 'nmap -sV -Pn --script (scripts)'";
+        bar.set_message(message.clone());
+
+        let args = vec![
+            "-sV",
+            "-Pn",
+            "--script",
+            "http-robots.txt",
+            "--script",
+            "http-title",
+            "--script",
+            "ssl-cert",
+            "--script",
+            "ftp-anon",
+            ip_string,
+        ];
+        let command = run_command_with_args("nmap", args)?;
+
+        let output_file = format!("{ip_string}/common_tcp_ports");
+        let mut f = create_file(&output_file, user)?;
+        writeln!(f, "{command}")?;
+
+        let message = format!("{message} {}", SUCCESS.green());
+        bar.finish_with_message(message);
+
+        Ok(command)
+    }
+
+    // Discover (with service information) common open UDP ports
+    fn nmap_common_udp_ports(
+        &self,
+        ip_string: &str,
+        user: Arc<IMDUser>,
+    ) -> Result<String, Box<dyn Error>> {
+        let bar = add_new_bar(self.mp());
+        let message = self.prefix.clone() + " Common UDP ports: 'nmap -sU -sV --top-ports 25 -Pn'";
+        bar.set_message(message.clone());
+
+        let args = vec![
+            "-sU",
+            "-sV",
+            "--version-intensity",
+            "0",
+            "--top-ports",
+            "25",
+            "-Pn",
+            ip_string,
+        ];
+        let command = run_command_with_args("nmap", args)?;
+
+        let output_file = format!("{ip_string}/common_udp_ports");
+        let mut f = create_file(&output_file, user)?;
+        writeln!(f, "{command}")?;
+
+        let message = format!("{message} {}", SUCCESS.green());
+        bar.finish_with_message(message);
+
+        Ok(command)
+    }
+
+    // Parse out the services from the nmap -sV scan
+    pub fn parse_port_scan(&self, port_scan: String) -> Vec<OpenPort> {
+        let bar = add_new_bar(self.mp());
+        let message = self.prefix.clone() + " Parsing port scan";
+        bar.set_message(message.clone());
+
+        let open_ports = scanner::parse_open_ports(&port_scan);
+
+        let message = format!("{message} {}", SUCCESS.green());
+        bar.finish_with_message(message);
+
+        open_ports
+    }
+
+    // Confirm the target machine is reachable via ping
+    fn ping(&self, ip_string: &str) -> Result<(), RecoverableDiscoveryError> {
+        let bar = add_new_bar(self.mp());
+        let message = self.prefix.clone() + " Verifying connectivity";
+        bar.set_message(message.clone());
+
+        let args = vec!["-c", "4", ip_string];
+        match run_command_with_args("ping", args) {
+            Err(_) => return Err(RecoverableDiscoveryError::Connection),
+            Ok(ping) => {
+                if ping.contains("100% packet loss") || ping.contains("100.0% packet loss") {
+                    bar.finish_with_message(format!(
+                        "{} {}",
+                        message,
+                        RecoverableDiscoveryError::Connection
+                    ));
+                    return Err(RecoverableDiscoveryError::Connection);
+                }
+            }
+        }
+
+        let message = format!("{message} {}", SUCCESS.green());
+        bar.finish_with_message(message);
+        Ok(())
+    }
+
+    // Discover network drives
+    fn showmount_network_drives(
+        &self,
+        ip_string: &str,
+        user: Arc<IMDUser>,
+        mount_nfs: bool,
+        nfs_versions: &[u32],
+    ) -> Result<(), Box<dyn Error>> {
+        let bar = add_new_bar(self.mp());
+        let message = self.prefix.clone() + " Network drives: 'showmount -e'";
+        bar.set_message(message.clone());
+
+        let args = vec!["-e", ip_string];
+        let command = run_command_with_args("showmount", args)?;
+        let output_file = format!("{ip_string}/nfs_shares");
+        let mut f = create_file(&output_file, user.clone())?;
+        writeln!(f, "{command}")?;
+
+        let message = format!("{message} {}", SUCCESS.green());
+        bar.finish_with_message(message);
+
+        let exports = nfs::parse_showmount(&command);
+        if exports.is_empty() {
+            return Ok(());
+        }
+        self.nfs_exports(ip_string, user, &exports, mount_nfs, nfs_versions)
+    }
+
+    // Work out who can mount each NFS export, and optionally mount the open ones read-only to see what's inside
+    fn nfs_exports(
+        &self,
+        ip_string: &str,
+        user: Arc<IMDUser>,
+        exports: &[nfs::NfsExport],
+        mount_nfs: bool,
+        nfs_versions: &[u32],
+    ) -> Result<(), Box<dyn Error>> {
+        let bar = add_new_bar(self.mp());
+        let message = match mount_nfs {
+            true => self.prefix.clone() + " NFS exports: read-only mount and listing",
+            false => self.prefix.clone() + " NFS exports: access list",
+        };
+        bar.set_message(message.clone());
+
+        let networks = nfs::local_networks();
+        let output_file = format!("{ip_string}/nfs_exports");
+        let mut f = create_file(&output_file, user)?;
+
+        if !nfs_versions.is_empty() {
+            let versions: Vec<String> = nfs_versions.iter().map(|v| v.to_string()).collect();
+            writeln!(
+                f,
+                "NFS versions registered with the portmapper: {}\n",
+                versions.join(", ")
+            )?;
+        }
+        writeln!(f, "{: <40}{: <12}CLIENTS", "EXPORT", "MOUNTABLE")?;
+        for export in exports {
+            let open = export.is_open_to(&networks);
+            writeln!(
+                f,
+                "{: <40}{: <12}{}",
+                export.path(),
+                if open { "yes" } else { "no" },
+                export.clients().join(",")
+            )?;
+            if open {
+                self.add_finding(
+                    true,
+                    format!(
+                        "NFS export '{}' can be mounted from this machine ({})",
+                        export.path(),
+                        export.clients().join(",")
+                    ),
+                );
+            }
+        }
+
+        if mount_nfs {
+            let mounts_dir = format!("{ip_string}/nfs_mounts");
+            for (i, export) in exports.iter().enumerate() {
+                if !export.is_open_to(&networks) {
+                    continue;
+                }
+                let mount_point = Path::new(&mounts_dir).join(i.to_string());
+                writeln!(f, "\n===== {} =====", export.path())?;
+
+                // The mount is undone when it goes out of scope at the end of each loop, whatever happens while listing
+                let mount = match nfs::NfsMount::new(
+                    ip_string,
+                    export.path(),
+                    &mount_point,
+                    nfs_versions.last().copied(),
+                ) {
+                    Ok(mount) => mount,
+                    Err(e) => {
+                        writeln!(f, "Could not mount: {e}")?;
+                        continue;
+                    }
+                };
+                let entries = nfs::walk(mount.mount_point());
+                self.nfs_listing(&mut f, export, &entries)?;
+            }
+            let _ = fs::remove_dir(&mounts_dir);
+        }
+
+        let message = format!("{message} {}", SUCCESS.green());
+        bar.finish_with_message(message);
+
+        Ok(())
+    }
+
+    // Record what's in a mounted export, who owns it, and whether it looks like it could be written to
+    fn nfs_listing(
+        &self,
+        f: &mut File,
+        export: &nfs::NfsExport,
+        entries: &[nfs::NfsEntry],
+    ) -> Result<(), Box<dyn Error>> {
+        let mut owners: Vec<((u32, u32), usize)> = vec![];
+        for entry in entries {
+            match owners
+                .iter_mut()
+                .find(|(owner, _)| *owner == (entry.uid(), entry.gid()))
+            {
+                Some((_, count)) => *count += 1,
+                None => owners.push(((entry.uid(), entry.gid()), 1)),
+            }
+        }
+        owners.sort();
+
+        writeln!(f, "Ownership (uid:gid entries):")?;
+        for ((uid, gid), count) in &owners {
+            writeln!(f, "    {uid}:{gid} {count}")?;
+        }
+
+        // Files owned by a regular user can be read and written by creating a local user with the same uid
+        let spoofable: Vec<String> = owners
+            .iter()
+            .filter(|((uid, _), _)| *uid != 0 && *uid != 65534)
+            .map(|((uid, gid), _)| format!("{uid}:{gid}"))
+            .collect();
+        if !spoofable.is_empty() {
+            self.add_finding(
+                true,
+                format!(
+                    "NFS export '{}' has files owned by uid:gid {}, try a local user with a matching uid",
+                    export.path(),
+                    spoofable.join(", ")
+                ),
+            );
+        }
+
+        let world_writable: Vec<&String> = entries
+            .iter()
+            .filter(|entry| entry.is_dir() && entry.is_world_writable())
+            .map(|entry| entry.path())
+            .collect();
+        // A directory its owner can write to is writable by anyone who spoofs the owner's uid, unless the owner is root or
+        // nobody, which root squashing keeps out of reach
+        let mut owner_writable: Vec<(u32, usize)> = vec![];
+        for entry in entries.iter().filter(|entry| {
+            entry.is_dir() && entry.mode() & 0o200 != 0 && entry.uid() != 0 && entry.uid() != 65534
+        }) {
+            match owner_writable
+                .iter_mut()
+                .find(|(uid, _)| *uid == entry.uid())
+            {
+                Some((_, count)) => *count += 1,
+                None => owner_writable.push((entry.uid(), 1)),
+            }
+        }
+        owner_writable.sort();
+        let mut writable: Vec<String> = owner_writable
+            .iter()
+            .map(|(uid, count)| format!("writable by spoofing uid {uid} ({count} directories)"))
+            .collect();
+        if !world_writable.is_empty() {
+            writable.push(format!(
+                "writable by anyone ({} world-writable directories)",
+                world_writable.len()
+            ));
+        }
+        match writable.is_empty() {
+            true => writeln!(
+                f,
+                "Write access: no directories writable by anyone or by spoofing a regular uid"
+            )?,
+            false => writeln!(f, "Write access: {}", writable.join(", "))?,
+        }
+        for path in world_writable {
+            writeln!(f, "    {path}")?;
+        }
+
+        writeln!(f, "\nListing ({} entries):", entries.len())?;
+        for entry in entries {
+            writeln!(f, "{}", entry.display())?;
+        }
+
+        Ok(())
+    }
+
+    // List SMB shares and what a null or guest session can do with them
+    fn smb_shares(
+        &self,
+        ip_string: &str,
+        user: Arc<IMDUser>,
+        tool: SmbTool,
+        write_test: bool,
+    ) -> Result<(), Box<dyn Error>> {
+        let bar = add_new_bar(self.mp());
+        let message = self.prefix.clone()
+            + &format!(" SMB shares: '{}' null and guest sessions", tool.as_str());
+        bar.set_message(message.clone());
+
+        let (shares, command) = tool.enumerate(ip_string, write_test)?;
+
+        let output_file = format!("{ip_string}/smb_shares");
+        let mut f = create_file(&output_file, user)?;
+        writeln!(
+            f,
+            "{: <8}{: <24}{: <14}COMMENT",
+            "SESSION", "SHARE", "ACCESS"
+        )?;
+        for share in &shares {
+            writeln!(
+                f,
+                "{: <8}{: <24}{: <14}{}",
+                share.session(),
+                share.name(),
+                share.access(),
+                share.comment()
+            )?;
+            if share.readable() || share.writable() {
+                self.add_finding(
+                    true,
+                    format!(
+                        "SMB share '{}' is {} with a {} session",
+                        share.name(),
+                        share.access(),
+                        share.session()
+                    ),
+                );
+            }
+        }
+        // Say plainly whether imd left a mark on the target's shares
+        if tool == SmbTool::Smbclient {
+            let note = match write_test {
+                true => "Write access was tested by making and removing an imd-write-test-* directory in each disk share",
+                false => "Write access was not tested, --smb-write-test makes and removes a directory in each disk share to check",
+            };
+            writeln!(f, "\n{note}")?;
+        }
+        writeln!(f, "\n{command}")?;
+
+        let message = format!("{message} {}", SUCCESS.green());
+        bar.finish_with_message(message);
+
+        Ok(())
+    }
+
+    // Find an SNMP community the agent accepts, then walk the parts of the MIB that tend to leak the most
+    fn snmp(
+        &self,
+        ip_string: &str,
+        user: Arc<IMDUser>,
+        communities: &[String],
+        timeout: Duration,
+    ) -> Result<(), Box<dyn Error>> {
+        let bar = add_new_bar(self.mp());
+        let message = self.prefix.clone() + " SNMP: community check and 'snmpwalk'";
+        bar.set_message(message.clone());
+
+        let output_file = format!("{ip_string}/snmp_walk");
+        let mut f = create_file(&output_file, user)?;
+
+        let (community, version, description) =
+            match snmp::find_community(self.ip_address, communities, timeout) {
+                Some(found) => found,
+                None => {
+                    writeln!(
+                        f,
+                        "No community accepted, tried: {}",
+                        communities.join(", ")
+                    )?;
+                    bar.finish_with_message(format!("{message} {}", SUCCESS.green()));
+                    return Ok(());
+                }
+            };
+        writeln!(
+            f,
+            "Community '{community}' accepted with SNMPv{version}: {description}"
+        )?;
+        self.add_finding(
+            true,
+            format!("SNMP accepts the '{community}' community (v{version})"),
+        );
+
+        let mut walk = String::new();
+        for (name, oid) in snmp::WALKS {
+            let args = vec!["-v", &version, "-c", &community, "-On", ip_string, oid];
+            let output = run_command_with_args("snmpwalk", args)?;
+            walk.push_str(&format!("\n===== {name} ({oid}) =====\n{output}"));
+        }
+
+        let interesting = snmp::interesting(&walk);
+        if !interesting.is_empty() {
+            writeln!(f, "\nWorth a look:")?;
+        }
+        for (flagged, detail) in interesting {
+            writeln!(f, "{} {detail}", if flagged { "[!]" } else { "[-]" })?;
+            if flagged {
+                self.add_finding(true, format!("SNMP {detail}"));
+            }
+        }
+        writeln!(f, "{walk}")?;
+
+        let message = format!("{message} {}", SUCCESS.green());
+        bar.finish_with_message(message);
+
+        Ok(())
+    }
+
+    // Record what an SSH server will tell anyone: its banner, algorithms, host keys and the ways it lets users log in
+    fn ssh_info(
+        &self,
+        ip_string: &str,
+        user: Arc<IMDUser>,
+        port: u16,
+        timeout: Duration,
+    ) -> Result<(), Box<dyn Error>> {
+        let bar = add_new_bar(self.mp());
+        let message = self.prefix.clone()
+            + &format!(" Port {port} SSH: algorithms, host keys and auth methods");
+        bar.set_message(message.clone());
+
+        let algorithms = ssh::algorithms(self.ip_address, port, timeout)?;
+        let auth_methods = ssh::auth_methods(ip_string, port).unwrap_or_default();
+        let fingerprints = ssh::host_key_fingerprints(ip_string, port).unwrap_or_default();
+
+        let output_file = match port {
+            22 => format!("{ip_string}/ssh_info"),
+            _ => format!("{ip_string}/ssh_info_port_{port}"),
+        };
+        let mut f = create_file(&output_file, user)?;
+        writeln!(f, "Banner: {}", algorithms.banner())?;
+
+        let password = auth_methods
+            .iter()
+            .any(|m| m == "password" || m == "keyboard-interactive");
+        let auth = match auth_methods.is_empty() {
+            true => "unknown".to_string(),
+            false => auth_methods.join(", "),
+        };
+        writeln!(f, "Auth methods: {auth}")?;
+        match (auth_methods.is_empty(), password) {
+            (true, _) => (),
+            (false, true) => {
+                let finding = format!("SSH on port {port} accepts passwords ({auth})");
+                self.add_finding(false, finding);
+            }
+            (false, false) => {
+                let finding = format!(
+                    "SSH on port {port} only accepts {auth}, so brute forcing passwords is pointless"
+                );
+                self.add_finding(false, finding);
+            }
+        }
+
+        if let Some(cve) = ssh::user_enumeration(algorithms.banner()) {
+            writeln!(f, "Username enumeration: {cve}")?;
+            self.add_finding(
+                true,
+                format!(
+                    "SSH on port {port} ({}) allows username enumeration ({cve})",
+                    algorithms.banner()
+                ),
+            );
+        }
+
+        let weak = algorithms.weak();
+        if !weak.is_empty() {
+            writeln!(f, "Weak algorithms: {}", weak.join(", "))?;
         }
 
-        change_owner(dir_name, user)?;
+        writeln!(f, "\nHost keys:\n{}", fingerprints.trim_end())?;
+        writeln!(f, "\nAlgorithms:")?;
+        for (name, list) in algorithms.name_lists() {
+            writeln!(f, "{name: <32}{}", list.join(", "))?;
+        }
 
         let message = format!("{message} {}", SUCCESS.green());
         bar.finish_with_message(message);
@@ -165,104 +2095,175 @@ impl TargetMachine {
         Ok(())
     }
 
-    // Catchall method for running discovery on a target machine
-    pub fn discovery(&self, user: Arc<IMDUser>, wordlist: Arc<String>) {
-        let ip_string = self.ip_as_string();
-        let _ = self.ping(&ip_string);
-        let _ = self.add_to_hosts(&ip_string);
-        if self.create_results_dir(&ip_string, user.clone()).is_err() {
-            return;
+    // Write up everything worth a closer look, flagged findings first, and show it once the target is done
+    fn summary(&self, ip_string: &str, user: Arc<IMDUser>) -> Result<(), Box<dyn Error>> {
+        let mut findings = self.findings.lock().unwrap().clone();
+        if findings.is_empty() {
+            return Ok(());
         }
+        findings.sort_by_key(|(flagged, _)| !flagged);
 
-        let mut threads: Vec<std::thread::JoinHandle<()>> = vec![];
+        let output_file = format!("{ip_string}/summary");
+        let mut f = create_file(&output_file, user)?;
 
-        threads.push(std::thread::spawn({
-            let clone = self.clone();
-            let ip_string = ip_string.clone();
-            let user = user.clone();
-            move || {
-                if clone.nmap_all_tcp_ports(&ip_string, user).is_err() {}
+        let bar = add_new_bar(self.mp());
+        bar.finish_with_message(
+            self.prefix.clone() + &format!(" Summary: written to '{output_file}'"),
+        );
+        for (flagged, finding) in findings {
+            let bar = add_new_bar(self.mp());
+            if flagged {
+                writeln!(f, "[!] {finding}")?;
+                bar.finish_with_message(format!(
+                    "{}   {}",
+                    self.prefix,
+                    format!("[!] {finding}").red().bold()
+                ));
+            } else {
+                writeln!(f, "[-] {finding}")?;
+                bar.finish_with_message(format!("{}   [-] {finding}", self.prefix));
             }
-        }));
+        }
 
-        threads.push(std::thread::spawn({
-            let clone = self.clone();
-            let ip_string = ip_string.clone();
-            let user = user.clone();
-            move || {
-                if clone.showmount_network_drives(&ip_string, user).is_err() {}
-            }
-        }));
+        Ok(())
+    }
 
-        let port_scan = match self.nmap_common_tcp_ports(&ip_string, user.clone()) {
-            Ok(port_scan) => port_scan,
-            Err(_) => return,
-        };
+    // Crawl a web port from robots.txt, the sitemap and the home page, returning the paths found as seeds for content discovery
+    fn web_crawl(
+        &self,
+        ip_string: &str,
+        user: Arc<IMDUser>,
+        protocol: &str,
+        port: &str,
+        options: &DiscoveryOptions,
+    ) -> Result<Vec<String>, Box<dyn Error>> {
+        let bar = add_new_bar(self.mp());
+        let message =
+            self.prefix.clone() + &format!(" Port {port} crawl: robots.txt, sitemap.xml and links");
+        bar.set_message(message.clone());
 
-        let services = Arc::new(self.parse_port_scan(port_scan));
+        let timeout = options.native_scanner().timeout();
+        let client = HttpClient::new(protocol, self.ip_address, port.parse()?, timeout);
+        let report = crawl::crawl(&client, &self.web_target());
 
-        for (service, ports) in services.iter() {
-            for port in ports {
-                // Spin up a thread for the vuln scan
-                threads.push(std::thread::spawn({
-                    let clone = self.clone();
-                    let ip_string = ip_string.clone();
-                    let port = port.clone();
-                    let service = service.clone();
-                    let user = user.clone();
-                    move || {
-                        if clone.vuln_scan(&ip_string, user, &service, &port).is_err() {}
-                    }
-                }));
-                // Spin up a thread for the web dir and file scanning
-                threads.push(std::thread::spawn({
-                    let clone = self.clone();
-                    let ip_string = ip_string.clone();
-                    let port = port.clone();
-                    let service = service.clone();
-                    let user = user.clone();
-                    let wordlist = wordlist.clone();
-                    move || {
-                        if clone
-                            .web_presence_scan(&ip_string, user, &service, &port, &wordlist)
-                            .is_err()
-                        {}
-                    }
-                }));
-            }
+        let output_file = self.output_file(ip_string, "web_crawl", port);
+        let mut f = create_file(&output_file, user)?;
+        writeln!(f, "===== Pages fetched =====")?;
+        for (page, status) in report.pages() {
+            writeln!(f, "{status: <5}{page}")?;
+        }
+        writeln!(f, "\n===== robots.txt =====")?;
+        for path in report.robots() {
+            writeln!(f, "{path}")?;
+        }
+        writeln!(f, "\n===== Sitemap =====")?;
+        for path in report.sitemap() {
+            writeln!(f, "{path}")?;
+        }
+        writeln!(f, "\n===== Forms =====")?;
+        for (page, method, action) in report.forms() {
+            writeln!(f, "{page}: {method} {action}")?;
+        }
+        writeln!(f, "\n===== Scripts =====")?;
+        for script in report.scripts() {
+            writeln!(f, "{script}")?;
+        }
+        writeln!(f, "\n===== Emails =====")?;
+        for email in report.emails() {
+            writeln!(f, "{email}")?;
+        }
+        writeln!(f, "\n===== Comments =====")?;
+        for (page, comment) in report.comments() {
+            writeln!(f, "{page}: {comment}")?;
+        }
+        writeln!(f, "\n===== Links =====")?;
+        for link in report.links() {
+            writeln!(f, "{link}")?;
         }
 
-        for thread in threads {
-            thread.join().unwrap();
+        if !report.robots().is_empty() {
+            let finding = format!(
+                "Port {port} robots.txt lists {}",
+                report.robots().join(", ")
+            );
+            self.add_finding(false, finding);
+        }
+        if !report.emails().is_empty() {
+            let finding = format!("Port {port} pages mention {}", report.emails().join(", "));
+            self.add_finding(false, finding);
         }
-    }
 
-    // Return the IP address as a string
-    fn ip_as_string(&self) -> String {
-        self.ip_address.to_string()
-    }
+        let message = format!("{message} {}", SUCCESS.green());
+        bar.finish_with_message(message);
 
-    // Return a clone of the MultiProgress container
-    fn mp(&self) -> Arc<MultiProgress> {
-        self.mp.clone()
+        Ok(report.seeds())
     }
 
-    // Discover open TCP ports
-    fn nmap_all_tcp_ports(
+    // Probe the web root and the directories content discovery found for exposed version control, .DS_Store, .env and backups, saving what turns up
+    fn web_exposures(
         &self,
         ip_string: &str,
         user: Arc<IMDUser>,
+        protocol: &str,
+        port: &str,
+        hits: &[content::ContentHit],
+        options: &DiscoveryOptions,
     ) -> Result<(), Box<dyn Error>> {
         let bar = add_new_bar(self.mp());
-        let message = self.prefix.clone() + " All TCP ports: 'nmap -p- -Pn'";
+        let message =
+            self.prefix.clone() + &format!(" Port {port} exposures: .git, .svn, .env and backups");
         bar.set_message(message.clone());
 
-        let args = vec!["-p-", "-Pn", ip_string];
-        let command = run_command_with_args("nmap", args)?;
-
-        let output_file = format!("{ip_string}/all_tcp_ports");
+        let web_target = self.web_target();
+        let timeout = options.native_scanner().timeout();
+        let client = HttpClient::new(protocol, self.ip_address, port.parse()?, timeout);
+        let (directories, files) = exposure::targets(hits);
+        let loot = exposure::LootDirs::new(
+            &self.output_file(ip_string, "loot/git", port),
+            &self.output_file(ip_string, "loot/web", port),
+            options.web_loot_max_size(),
+            user.clone(),
+        );
+        let report = exposure::probe(&client, &web_target, &directories, &files, &loot);
+
+        let output_file = self.output_file(ip_string, "web_exposures", port);
         let mut f = create_file(&output_file, user)?;
-        writeln!(f, "{command}")?;
+        writeln!(
+            f,
+            "Probed {} directories and {} files\n",
+            report.directories().len(),
+            report.files().len()
+        )?;
+        writeln!(f, "===== Exposed =====")?;
+        writeln!(f, "{: <11}{: >10}  PATH", "KIND", "SIZE")?;
+        for artifact in report.artifacts() {
+            writeln!(f, "{}", artifact.line())?;
+            let finding = format!(
+                "{} exposes {} at {}",
+                client.url(&web_target, "").trim_end_matches('/'),
+                artifact.kind().as_str(),
+                artifact.path()
+            );
+            self.add_finding(artifact.kind().serious(), finding);
+        }
+        if let Some(dump) = report.git() {
+            writeln!(f, "\n===== .git =====")?;
+            writeln!(f, "Source:    {}", dump.url())?;
+            writeln!(f, "Rebuilt:   {}", dump.directory())?;
+            writeln!(f, "Objects:   {}", dump.objects())?;
+            writeln!(f, "Missing:   {}", dump.missing().len())?;
+            for file in dump.too_big() {
+                writeln!(f, "Too big:   {file}")?;
+            }
+            writeln!(f, "\nFiles:")?;
+            for path in dump.restored() {
+                writeln!(f, "{path}")?;
+            }
+        }
+        writeln!(f, "\n===== Probed =====")?;
+        for path in report.directories().iter().chain(report.files()) {
+            writeln!(f, "{path}")?;
+        }
 
         let message = format!("{message} {}", SUCCESS.green());
         bar.finish_with_message(message);
@@ -270,170 +2271,409 @@ impl TargetMachine {
         Ok(())
     }
 
-    // Discover (with service information) common open TCP ports
-    fn nmap_common_tcp_ports(
+    // Discover web dirs and files with whichever content discovery scanner is chosen and installed, returning what it found
+    pub fn web_presence_scan(
         &self,
         ip_string: &str,
         user: Arc<IMDUser>,
-    ) -> Result<String, Box<dyn Error>> {
+        protocol: &str,
+        port: &str,
+        scan: &content::ContentScan,
+        options: &DiscoveryOptions,
+    ) -> Result<Vec<content::ContentHit>, Box<dyn Error>> {
         let bar = add_new_bar(self.mp());
-        let message = self.prefix.clone()
-            + " Common TCP ports: 'nmap -sV -Pn --script (scripts)'";
+        let scanner = options
+            .web_scanner()
+            .installed()
+            .unwrap_or(options.web_scanner());
+        let message = self.prefix.clone() + &format!(" Port {port} web: '{}'", scanner.as_str());
         bar.set_message(message.clone());
 
-        let args = vec![
-            "-sV",
-            "-Pn",
-            "--script",
-            "http-robots.txt",
-            "--script",
-            "http-title",
-            "--script",
-            "ssl-cert",
-            "--script",
-            "ftp-anon",
-            ip_string,
-        ];
-        let command = run_command_with_args("nmap", args)?;
+        let web_target = self.web_target();
+        let full_target = format!("{protocol}://{web_target}:{port}");
 
-        let output_file = format!("{ip_string}/common_tcp_ports");
+        let (hits, command) = scanner.run(scan, &full_target)?;
+        let interesting: Vec<&content::ContentHit> =
+            hits.iter().filter(|hit| hit.interesting()).collect();
+
+        let output_file = self.output_file(ip_string, "web_dirs_and_files", port);
+        let mut f = create_file(&output_file, user.clone())?;
+        let none = "none".to_string();
+        let or_none = |list: &Vec<String>, separator: &str| match list.is_empty() {
+            true => none.clone(),
+            false => list.join(separator),
+        };
+        writeln!(f, "Command:     {command}")?;
+        writeln!(
+            f,
+            "Seeds:       {} paths from web_crawl",
+            scan.seeds().len()
+        )?;
+        writeln!(
+            f,
+            "Fingerprint: {}",
+            match scan.fingerprint().is_empty() {
+                true => &none,
+                false => scan.fingerprint(),
+            }
+        )?;
+        writeln!(f, "Rules:       {}", or_none(scan.rules(), ", "))?;
+        writeln!(f, "Extensions:  {}", or_none(scan.extensions(), ","))?;
+        writeln!(f, "Wordlists:   {}\n", or_none(scan.wordlists(), ", "))?;
+        writeln!(f, "===== Interesting =====")?;
+        for hit in &interesting {
+            writeln!(f, "{}", hit.line())?;
+        }
+        writeln!(f, "\n===== All =====")?;
+        writeln!(f, "{: <5}{: >10}  URL", "CODE", "SIZE")?;
+        for hit in &hits {
+            writeln!(f, "{}", hit.line())?;
+        }
+
+        let output_file = self.output_file(ip_string, "web_paths", port) + ".json";
         let mut f = create_file(&output_file, user)?;
-        writeln!(f, "{command}")?;
+        let objects: Vec<String> = hits.iter().map(|hit| hit.json()).collect();
+        writeln!(f, "[\n{}\n]", objects.join(",\n"))?;
+
+        if !interesting.is_empty() {
+            let urls: Vec<&str> = interesting
+                .iter()
+                .take(5)
+                .map(|hit| hit.url().as_str())
+                .collect();
+            let finding = format!(
+                "Port {port} has {} interesting paths, including {}",
+                interesting.len(),
+                urls.join(", ")
+            );
+            self.add_finding(false, finding);
+        }
 
         let message = format!("{message} {}", SUCCESS.green());
         bar.finish_with_message(message);
 
-        Ok(command)
+        Ok(hits)
     }
 
-    // Parse out the services from the nmap -sV scan
-    pub fn parse_port_scan(&self, port_scan: String) -> HashMap<String, Vec<String>> {
+    // Fingerprint the technology behind a web port, hand the result to content discovery, then run the follow-ups configured for what was found
+    fn web_tech(
+        &self,
+        ip_string: &str,
+        user: Arc<IMDUser>,
+        protocol: &str,
+        port: &str,
+        options: &DiscoveryOptions,
+        signature: std::sync::mpsc::Sender<String>,
+    ) -> Result<(), Box<dyn Error>> {
         let bar = add_new_bar(self.mp());
-        let message = self.prefix.clone() + " Parsing port scan";
+        let message = self.prefix.clone()
+            + &format!(" Port {port} web tech: headers, favicon and known paths");
         bar.set_message(message.clone());
 
-        // Prep the scan string for searching by splitting it to a vector of lines, trimming each line, and removing lines that start with "|" or "SF:"
-        let port_scan: Vec<String> = port_scan
-            .split('\n')
-            .map(|s| s.trim().to_string())
-            .filter(|s| !s.starts_with('|'))
-            .filter(|s| !s.starts_with("SF:"))
-            .collect();
-
-        // Define a list of services that we will do something about
-        let services = vec!["http", "ssl/http"];
-        let mut services_map: HashMap<String, Vec<String>> = HashMap::new();
+        let web_target = self.web_target();
+        let timeout = options.native_scanner().timeout();
+        let client = HttpClient::new(protocol, self.ip_address, port.parse()?, timeout);
+        let mut fingerprint = webtech::fingerprint(&client, &web_target)?;
+
+        let whatweb = match command_exists("whatweb") {
+            true => {
+                let url = client.url(&web_target, "/");
+                let output =
+                    run_command_with_args("whatweb", vec!["--color=never", "-a", "1", &url])?;
+                fingerprint.add_whatweb(&output);
+                Some(output)
+            }
+            false => None,
+        };
+        let _ = signature.send(fingerprint.signature());
+
+        let output_file = self.output_file(ip_string, "web_tech", port);
+        let mut f = create_file(&output_file, user.clone())?;
+        let none = "none".to_string();
+        writeln!(
+            f,
+            "Server:         {}",
+            fingerprint.server().unwrap_or(&none)
+        )?;
+        writeln!(
+            f,
+            "X-Powered-By:   {}",
+            fingerprint.powered_by().unwrap_or(&none)
+        )?;
+        writeln!(
+            f,
+            "Generator:      {}",
+            fingerprint.generator().unwrap_or(&none)
+        )?;
+        writeln!(
+            f,
+            "Favicon hash:   {}",
+            fingerprint
+                .favicon_hash()
+                .map(|hash| hash.to_string())
+                .unwrap_or(none.clone())
+        )?;
+        writeln!(f, "\n===== Technologies =====")?;
+        for (technology, evidence) in fingerprint.evidence() {
+            writeln!(f, "{: <12}{evidence}", technology.as_str())?;
+        }
 
-        // Iterate over the port scan and find ports that host services we are looking for. Add those service / port pairs to the map
-        for line in port_scan {
-            for service in &services {
-                if line.contains(service) && line.contains("open") {
-                    let port = line.split('/').collect::<Vec<&str>>()[0];
-                    let service = match service {
-                        &"ssl/http" => "https",
-                        _ => service,
-                    };
-                    services_map
-                        .entry(service.to_string())
-                        .or_default()
-                        .push(port.to_string());
+        for technology in fingerprint.technologies() {
+            self.add_finding(
+                false,
+                format!("{web_target}:{port} runs {}", technology.as_str()),
+            );
+            for follow_up in options.web_follow_ups() {
+                if !follow_up.applies_to(technology) {
+                    continue;
+                }
+                writeln!(
+                    f,
+                    "\n===== {} ({}) =====",
+                    follow_up.as_str(),
+                    technology.as_str()
+                )?;
+                if let Some(command) = follow_up.command() {
+                    if !command_exists(command) {
+                        writeln!(f, "{command} is not installed")?;
+                        continue;
+                    }
+                }
+                let report = match follow_up.run(&client, &web_target, technology) {
+                    Ok(report) => report,
+                    Err(e) => {
+                        writeln!(f, "Failed: {e}")?;
+                        continue;
+                    }
+                };
+                writeln!(f, "{}", report.output())?;
+                for (flagged, finding) in report.findings() {
+                    self.add_finding(*flagged, finding.clone());
+                }
+                if !report.usernames().is_empty() {
+                    save_usernames(ip_string, user.clone(), report.usernames())?;
                 }
             }
         }
 
+        if let Some(whatweb) = whatweb {
+            writeln!(f, "\n===== whatweb =====\n{whatweb}")?;
+        }
+
         let message = format!("{message} {}", SUCCESS.green());
         bar.finish_with_message(message);
 
-        services_map
+        Ok(())
     }
 
-    // Confirm the target machine is reachable via ping
-    fn ping(&self, ip_string: &str) -> Result<(), RecoverableDiscoveryError> {
+    // Actively check every open TCP port for a website, rather than trusting the name the port scanner gave it
+    fn web_detection(
+        &self,
+        ip_string: &str,
+        user: Arc<IMDUser>,
+        open_ports: &[OpenPort],
+        timeout: Duration,
+    ) -> Result<HashMap<String, Vec<String>>, Box<dyn Error>> {
         let bar = add_new_bar(self.mp());
-        let message = self.prefix.clone() + " Verifying connectivity";
+        let message = self.prefix.clone() + " Web detection: native HTTP and TLS checks";
         bar.set_message(message.clone());
 
-        let args = vec!["-c", "4", ip_string];
-        match run_command_with_args("ping", args) {
-            Err(_) => return Err(RecoverableDiscoveryError::Connection),
-            Ok(ping) => {
-                if ping.contains("100% packet loss") || ping.contains("100.0% packet loss") {
-                    bar.finish_with_message(format!(
-                        "{} {}",
-                        message,
-                        RecoverableDiscoveryError::Connection
-                    ));
-                    return Err(RecoverableDiscoveryError::Connection);
+        let mut threads: Vec<std::thread::JoinHandle<(OpenPort, probe::WebCheck)>> = vec![];
+        for open_port in open_ports.iter().filter(|p| p.protocol() == "tcp") {
+            threads.push(std::thread::spawn({
+                let ip_address = self.ip_address;
+                let open_port = open_port.clone();
+                move || {
+                    let check = probe::detect_web(ip_address, open_port.port(), timeout);
+                    (open_port, check)
+                }
+            }));
+        }
+
+        let output_file = format!("{ip_string}/web_detection");
+        let mut f = create_file(&output_file, user)?;
+        let mut services_map: HashMap<String, Vec<String>> = HashMap::new();
+
+        for thread in threads {
+            let (open_port, check) = thread.join().unwrap();
+            let port = open_port.port();
+            // Ports that didn't answer either check get the benefit of the doubt if the port scanner named them as web
+            let (protocol, method) = match check.protocol() {
+                Some(protocol) => (Some(protocol.clone()), check.method().clone()),
+                None if !check.responded() => {
+                    let named = scanner::web_services(std::slice::from_ref(&open_port));
+                    match named.into_keys().next() {
+                        Some(protocol) => (
+                            Some(protocol),
+                            format!(
+                                "{}, so trusting the port scan's service name",
+                                check.method()
+                            ),
+                        ),
+                        None => (None, check.method().clone()),
+                    }
                 }
+                None => (None, check.method().clone()),
+            };
+
+            writeln!(
+                f,
+                "{: <10}{: <9}{method} (port scan: {})",
+                format!("{port}/tcp"),
+                protocol.clone().unwrap_or("not-web".to_string()),
+                open_port.service()
+            )?;
+
+            if let Some(protocol) = protocol {
+                services_map
+                    .entry(protocol)
+                    .or_default()
+                    .push(port.to_string());
             }
         }
 
         let message = format!("{message} {}", SUCCESS.green());
         bar.finish_with_message(message);
-        Ok(())
+
+        Ok(services_map)
     }
 
-    // Discover network drives
-    fn showmount_network_drives(
+    // Name the output file for a per-port step, keeping the results for discovered hostnames apart from the originals
+    fn output_file(&self, ip_string: &str, name: &str, port: &str) -> String {
+        match &self.output_tag {
+            Some(tag) => format!("{ip_string}/{name}_{tag}_port_{port}"),
+            None => format!("{ip_string}/{name}_port_{port}"),
+        }
+    }
+
+    // Fuzz the Host header with subdomains of the target's hostname, returning the virtual hosts that answer differently
+    fn vhosts(
         &self,
         ip_string: &str,
         user: Arc<IMDUser>,
-    ) -> Result<(), Box<dyn Error>> {
+        protocol: &str,
+        port: &str,
+        words: &[String],
+        timeout: Duration,
+    ) -> Result<Vec<String>, Box<dyn Error>> {
+        let domain = self.web_target();
         let bar = add_new_bar(self.mp());
-        let message = self.prefix.clone() + " Network drives: 'showmount -e'";
+        let message = self.prefix.clone() + &format!(" Port {port} vhosts: 'Host: FUZZ.{domain}'");
         bar.set_message(message.clone());
 
-        let args = vec!["-e", ip_string];
-        let command = run_command_with_args("showmount", args)?;
-        let output_file = format!("{ip_string}/nfs_shares");
+        if words.is_empty() {
+            bar.finish_with_message(format!("{message} {}", RecoverableDiscoveryError::Wordlist));
+            return Ok(vec![]);
+        }
+
+        let client = HttpClient::new(protocol, self.ip_address, port.parse()?, timeout);
+        let report = vhosts::fuzz(&client, &domain, words)?;
+
+        let output_file = self.output_file(ip_string, "vhosts", port);
         let mut f = create_file(&output_file, user)?;
-        writeln!(f, "{command}")?;
+        writeln!(f, "Subdomains tried: {}", report.tried())?;
+        writeln!(
+            f,
+            "Baseline:         status {}, size {}",
+            report.baseline_status(),
+            report
+                .baseline_size()
+                .map(|size| size.to_string())
+                .unwrap_or("changes every request, comparing status only".to_string())
+        )?;
+        writeln!(f, "\n===== Virtual hosts =====")?;
+        for hit in report.hits() {
+            writeln!(
+                f,
+                "{: <40}status {: <5}size {}",
+                hit.hostname(),
+                hit.status(),
+                hit.size()
+            )?;
+        }
+
+        let hostnames: Vec<String> = report
+            .hits()
+            .iter()
+            .map(|hit| hit.hostname().clone())
+            .collect();
+        if !hostnames.is_empty() {
+            let finding = format!(
+                "Port {port} answers for virtual hosts {}",
+                hostnames.join(", ")
+            );
+            self.add_finding(true, finding);
+        }
 
         let message = format!("{message} {}", SUCCESS.green());
         bar.finish_with_message(message);
 
-        Ok(())
+        Ok(hostnames)
     }
 
-    // Discover web dirs and files
-    pub fn web_presence_scan(
+    // Ask a Windows machine for its NetBIOS name, DNS domain and FQDN over SMB, RDP and LDAP
+    fn windows_info(
         &self,
         ip_string: &str,
         user: Arc<IMDUser>,
-        protocol: &str,
-        port: &str,
-        wordlist: &str,
-    ) -> Result<(), Box<dyn Error>> {
+        open_ports: &[OpenPort],
+    ) -> Result<Vec<String>, Box<dyn Error>> {
         let bar = add_new_bar(self.mp());
         let message = self.prefix.clone()
-            + &format!(" Port {port} web: 'feroxbuster -q --thorough'");
+            + " Windows names: 'nmap --script smb-os-discovery,rdp-ntlm-info,ldap-rootdse'";
         bar.set_message(message.clone());
 
-        let web_target = self.web_target();
-        let full_target = format!("{protocol}://{web_target}:{port}");
+        let ports: Vec<String> = open_ports
+            .iter()
+            .filter(|p| p.protocol() == "tcp" && windows::WINDOWS_PORTS.contains(&p.port()))
+            .map(|p| p.port().to_string())
+            .collect();
+        let ports = ports.join(",");
 
         let args = vec![
-            "-q",
-            "--thorough",
-            "--time-limit",
-            "10m",
-            "--no-state",
-            "-w",
-            wordlist,
-            "-u",
-            &full_target,
+            "-Pn",
+            "-p",
+            &ports,
+            "--script",
+            "smb-os-discovery,rdp-ntlm-info,ldap-rootdse",
+            ip_string,
         ];
-        let command = run_command_with_args("feroxbuster", args)?;
-        let command = command.replace("\n\n", "\n");
+        let command = run_command_with_args("nmap", args)?;
+        let info = windows::WindowsInfo::parse(&command);
 
-        let output_file = format!("{ip_string}/web_dirs_and_files_port_{port}");
+        let output_file = format!("{ip_string}/windows_info");
         let mut f = create_file(&output_file, user)?;
-        writeln!(f, "{command}")?;
+        let unknown = "unknown".to_string();
+        writeln!(
+            f,
+            "NetBIOS name:   {}",
+            info.netbios_name().unwrap_or(&unknown)
+        )?;
+        writeln!(
+            f,
+            "NetBIOS domain: {}",
+            info.netbios_domain().unwrap_or(&unknown)
+        )?;
+        writeln!(
+            f,
+            "DNS domain:     {}",
+            info.dns_domain().unwrap_or(&unknown)
+        )?;
+        writeln!(f, "FQDN:           {}", info.fqdn().unwrap_or(&unknown))?;
+        writeln!(f, "\n{command}")?;
 
         let message = format!("{message} {}", SUCCESS.green());
         bar.finish_with_message(message);
 
-        Ok(())
+        Ok(info.hostnames())
+    }
+
+    // Copy the target machine, but aimed at a different hostname with its own output files
+    fn with_hostname(&self, hostname: &str) -> TargetMachine {
+        let mut clone = self.clone();
+        clone.hostname = Some(hostname.to_string());
+        clone.output_tag = Some(hostname.to_string());
+        clone
     }
 
     // Return the hostname if it exists, or the IP address as a string if not
@@ -444,28 +2684,67 @@ impl TargetMachine {
         }
     }
 
-    // Check for common web vulnerabilities or misconfigurations
+    // Check for common web vulnerabilities or misconfigurations with nikto, nuclei or both
     fn vuln_scan(
         &self,
         ip_string: &str,
         user: Arc<IMDUser>,
         protocol: &str,
         port: &str,
+        options: &DiscoveryOptions,
     ) -> Result<(), Box<dyn Error>> {
         let bar = add_new_bar(self.mp());
-        let message = self.prefix.clone()
-            + &format!(" Port {port} vulns: 'nikto -host'");
+        let tools = options.vuln_scanner().tools();
+        let message =
+            self.prefix.clone() + &format!(" Port {port} vulns: '{}'", tools.join("' and '"));
         bar.set_message(message.clone());
 
         let web_target = self.web_target();
         let full_target = format!("{protocol}://{web_target}:{port}");
 
-        let args = vec!["-host", &full_target, "-maxtime", "60"];
-        let command = run_command_with_args("nikto", args)?;
+        let mut findings = vec![];
+        let mut raw = String::new();
+        for tool in tools {
+            if !command_exists(tool) {
+                raw.push_str(&format!("===== {tool} =====\n{tool} is not installed\n\n"));
+                continue;
+            }
+            let result = match tool {
+                "nuclei" => {
+                    vulns::nuclei(&full_target, options.nuclei_templates().map(String::as_str))
+                }
+                _ => vulns::nikto(&full_target, ip_string),
+            };
+            // Keep what the other scanner found if this one fails
+            let (found, output) = match result {
+                Ok(result) => result,
+                Err(e) => {
+                    raw.push_str(&format!("===== {tool} =====\n{tool} failed: {e}\n\n"));
+                    continue;
+                }
+            };
+            findings.extend(found);
+            raw.push_str(&format!("===== {tool} =====\n{output}\n"));
+        }
 
-        let output_file = format!("{ip_string}/web_vulns_port_{port}");
+        let output_file = self.output_file(ip_string, "web_vulns", port);
         let mut f = create_file(&output_file, user)?;
-        writeln!(f, "{command}")?;
+        writeln!(f, "===== Findings =====")?;
+        for finding in findings.iter().filter(|finding| !finding.low_priority()) {
+            writeln!(f, "{}", finding.line())?;
+            if finding.worth_summarizing() {
+                self.add_finding(
+                    finding.serious(),
+                    format!("Port {port}: {}", finding.line()),
+                );
+            }
+        }
+        // Missing security headers and the like are kept, but out of the way
+        writeln!(f, "\n===== Low priority =====")?;
+        for finding in findings.iter().filter(|finding| finding.low_priority()) {
+            writeln!(f, "{}", finding.line())?;
+        }
+        writeln!(f, "\n{raw}")?;
 
         let message = format!("{message} {}", SUCCESS.green());
         bar.finish_with_message(message);
@@ -488,6 +2767,27 @@ pub fn change_owner(object: &str, new_owner: Arc<IMDUser>) -> Result<(), Box<dyn
     Ok(())
 }
 
+// Check whether a command can be found in any of the directories on the PATH
+pub fn command_exists(command: &str) -> bool {
+    match env::var_os("PATH") {
+        Some(paths) => env::split_paths(&paths).any(|dir| dir.join(command).is_file()),
+        None => false,
+    }
+}
+
+// Create a directory and any missing parents, all owned by the provided user
+pub fn create_dirs(dir_name: &str, user: Arc<IMDUser>) -> Result<(), Box<dyn Error>> {
+    let mut path = std::path::PathBuf::new();
+    for component in Path::new(dir_name).components() {
+        path.push(component);
+        if !path.exists() {
+            fs::create_dir(&path)?;
+            change_owner(&path.to_string_lossy(), user.clone())?;
+        }
+    }
+    Ok(())
+}
+
 // Create a file owned by the provided to store the results of a command
 pub fn create_file(filename: &str, user: Arc<IMDUser>) -> Result<File, Box<dyn Error>> {
     let f = File::create(filename)?;
@@ -523,6 +2823,35 @@ pub fn run_command_with_args(command: &str, args: Vec<&str>) -> Result<String, B
     Ok(String::from_utf8(out.stdout)?)
 }
 
+// Add usernames to the target's users.txt, which several steps contribute to, keeping one of each
+pub fn save_usernames(
+    ip_string: &str,
+    user: Arc<IMDUser>,
+    usernames: &[String],
+) -> Result<(), Box<dyn Error>> {
+    static USERS_LOCK: Mutex<()> = Mutex::new(());
+    let _lock = USERS_LOCK.lock().unwrap();
+
+    let filename = format!("{ip_string}/users.txt");
+    let mut known: Vec<String> = fs::read_to_string(&filename)
+        .unwrap_or_default()
+        .lines()
+        .map(str::to_string)
+        .collect();
+    for username in usernames {
+        if !username.is_empty() && !known.contains(username) {
+            known.push(username.clone());
+        }
+    }
+
+    let mut f = create_file(&filename, user)?;
+    for username in known {
+        writeln!(f, "{username}")?;
+    }
+
+    Ok(())
+}
+
 // Wrapper for parsing an IP address such that we can use it for CLI parsing
 fn wrap_ip_address_parse(ip_address: &str) -> Result<IpAddr, PanicDiscoveryError> {
     match ip_address.parse::<IpAddr>() {
@@ -0,0 +1,285 @@
+use crate::error::RecoverableDiscoveryError;
+use crate::scanner::OpenPort;
+use std::error::Error;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{IpAddr, SocketAddr, TcpStream};
+use std::time::Duration;
+
+// Usernames tried against SMTP when the user doesn't provide their own
+pub const DEFAULT_USERNAMES: [&str; 16] = [
+    "root",
+    "admin",
+    "administrator",
+    "postmaster",
+    "mail",
+    "www-data",
+    "user",
+    "test",
+    "guest",
+    "info",
+    "support",
+    "sales",
+    "backup",
+    "ftp",
+    "nobody",
+    "webmaster",
+];
+
+// Nobody is called this, so a server that confirms it confirms everyone
+const MADE_UP_USER: &str = "imd-no-such-user-4f1c";
+
+// SMTP commands that can confirm a user exists
+const METHODS: [&str; 3] = ["VRFY", "EXPN", "RCPT"];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MailProtocol {
+    Imap,
+    Pop3,
+    Smtp,
+}
+
+impl MailProtocol {
+    // Work out which mail protocol (if any) the port scan found, by service name and then by the usual ports
+    pub fn from_open_port(open_port: &OpenPort) -> Option<MailProtocol> {
+        if open_port.protocol() != "tcp" {
+            return None;
+        }
+        match (open_port.service().as_str(), open_port.port()) {
+            ("smtp" | "submission", _) => Some(MailProtocol::Smtp),
+            ("pop3", _) => Some(MailProtocol::Pop3),
+            ("imap", _) => Some(MailProtocol::Imap),
+            ("" | "unknown", 25 | 587) => Some(MailProtocol::Smtp),
+            ("" | "unknown", 110) => Some(MailProtocol::Pop3),
+            ("" | "unknown", 143) => Some(MailProtocol::Imap),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        match self {
+            MailProtocol::Imap => "IMAP",
+            MailProtocol::Pop3 => "POP3",
+            MailProtocol::Smtp => "SMTP",
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MailReport {
+    banner: String,
+    capabilities: Vec<String>,
+    methods: Vec<(String, String)>,
+    users: Vec<(String, String)>,
+}
+
+impl MailReport {
+    pub fn banner(&self) -> &String {
+        &self.banner
+    }
+
+    pub fn capabilities(&self) -> &Vec<String> {
+        &self.capabilities
+    }
+
+    // Each user enumeration method and how the server handled it
+    pub fn methods(&self) -> &Vec<(String, String)> {
+        &self.methods
+    }
+
+    // Confirmed usernames, along with the method that confirmed them
+    pub fn users(&self) -> &Vec<(String, String)> {
+        &self.users
+    }
+}
+
+struct MailClient {
+    reader: BufReader<TcpStream>,
+}
+
+impl MailClient {
+    fn connect(
+        ip_address: IpAddr,
+        port: u16,
+        timeout: Duration,
+    ) -> Result<MailClient, Box<dyn Error>> {
+        let stream = TcpStream::connect_timeout(&SocketAddr::new(ip_address, port), timeout)?;
+        stream.set_read_timeout(Some(timeout * 5))?;
+        stream.set_write_timeout(Some(timeout * 5))?;
+        Ok(MailClient {
+            reader: BufReader::new(stream),
+        })
+    }
+
+    fn send(&mut self, command: &str) -> Result<(), Box<dyn Error>> {
+        self.reader
+            .get_mut()
+            .write_all(format!("{command}\r\n").as_bytes())?;
+        Ok(())
+    }
+
+    fn read_line(&mut self) -> Result<String, Box<dyn Error>> {
+        let mut line = String::new();
+        if self.reader.read_line(&mut line)? == 0 {
+            return Err(RecoverableDiscoveryError::Response.into());
+        }
+        Ok(line.trim_end().to_string())
+    }
+
+    // SMTP replies carry on over lines like "250-..." and end on one like "250 ..."
+    fn smtp_reply(&mut self) -> Result<(u16, Vec<String>), Box<dyn Error>> {
+        let mut lines = vec![];
+        loop {
+            let line = self.read_line()?;
+            let done = line.len() < 4 || line.as_bytes()[3] == b' ';
+            lines.push(line);
+            if done {
+                let code = lines.last().unwrap().get(..3).unwrap_or_default().parse()?;
+                return Ok((code, lines));
+            }
+        }
+    }
+
+    fn smtp_command(&mut self, command: &str) -> Result<(u16, Vec<String>), Box<dyn Error>> {
+        self.send(command)?;
+        self.smtp_reply()
+    }
+
+    // Read lines up to and including the first one the check says ends the response
+    fn read_until(&mut self, end: impl Fn(&str) -> bool) -> Result<Vec<String>, Box<dyn Error>> {
+        let mut lines = vec![];
+        loop {
+            let line = self.read_line()?;
+            let done = end(&line);
+            lines.push(line);
+            if done {
+                return Ok(lines);
+            }
+        }
+    }
+}
+
+// Record the SMTP banner and EHLO capabilities, then confirm usernames with whichever of VRFY, EXPN and RCPT TO work
+pub fn smtp(
+    ip_address: IpAddr,
+    port: u16,
+    timeout: Duration,
+    usernames: &[String],
+) -> Result<MailReport, Box<dyn Error>> {
+    let mut client = MailClient::connect(ip_address, port, timeout)?;
+    let mut report = MailReport {
+        banner: client.smtp_reply()?.1.join("\n"),
+        ..Default::default()
+    };
+
+    let (code, lines) = client.smtp_command("EHLO imd.local")?;
+    report.capabilities = match code {
+        250 => lines
+            .iter()
+            .skip(1)
+            .map(|l| l.get(4..).unwrap_or_default().to_string())
+            .collect(),
+        // Old servers only speak HELO
+        _ => {
+            client.smtp_command("HELO imd.local")?;
+            vec![]
+        }
+    };
+
+    for method in METHODS {
+        if method == "RCPT" {
+            client.smtp_command("RSET")?;
+            let (code, _) = client.smtp_command("MAIL FROM:<imd@imd.local>")?;
+            if code != 250 {
+                report
+                    .methods
+                    .push((method.to_string(), format!("MAIL FROM refused ({code})")));
+                continue;
+            }
+        }
+
+        // A method is only useful if it tells a real user from a made up one
+        let code = smtp_check(&mut client, method, MADE_UP_USER)?;
+        let status = match code {
+            200..=299 => "accepts any username".to_string(),
+            500..=504 => format!("not supported ({code})"),
+            550..=559 => "usable".to_string(),
+            _ => format!("unclear ({code})"),
+        };
+        let usable = status == "usable";
+        report.methods.push((method.to_string(), status));
+        if !usable {
+            continue;
+        }
+
+        for username in usernames {
+            if report.users.iter().any(|(user, _)| user == username) {
+                continue;
+            }
+            if (200..=299).contains(&smtp_check(&mut client, method, username)?) {
+                report.users.push((username.clone(), method.to_string()));
+            }
+        }
+    }
+
+    let _ = client.smtp_command("QUIT");
+    Ok(report)
+}
+
+fn smtp_check(
+    client: &mut MailClient,
+    method: &str,
+    username: &str,
+) -> Result<u16, Box<dyn Error>> {
+    let command = match method {
+        "RCPT" => format!("RCPT TO:<{username}>"),
+        _ => format!("{method} {username}"),
+    };
+    Ok(client.smtp_command(&command)?.0)
+}
+
+// Record the POP3 banner and what the server says it can do
+pub fn pop3(
+    ip_address: IpAddr,
+    port: u16,
+    timeout: Duration,
+) -> Result<MailReport, Box<dyn Error>> {
+    let mut client = MailClient::connect(ip_address, port, timeout)?;
+    let mut report = MailReport {
+        banner: client.read_line()?,
+        ..Default::default()
+    };
+
+    client.send("CAPA")?;
+    let first = client.read_line()?;
+    if first.starts_with("+OK") {
+        report.capabilities = client.read_until(|line| line == ".")?;
+        report.capabilities.pop();
+    }
+
+    let _ = client.send("QUIT");
+    Ok(report)
+}
+
+// Record the IMAP banner and what the server says it can do
+pub fn imap(
+    ip_address: IpAddr,
+    port: u16,
+    timeout: Duration,
+) -> Result<MailReport, Box<dyn Error>> {
+    let mut client = MailClient::connect(ip_address, port, timeout)?;
+    let mut report = MailReport {
+        banner: client.read_line()?,
+        ..Default::default()
+    };
+
+    client.send("a1 CAPABILITY")?;
+    let lines = client.read_until(|line| line.starts_with("a1 "))?;
+    report.capabilities = lines
+        .iter()
+        .filter_map(|line| line.strip_prefix("* CAPABILITY "))
+        .flat_map(|capabilities| capabilities.split_whitespace().map(str::to_string))
+        .collect();
+
+    let _ = client.send("a2 LOGOUT");
+    Ok(report)
+}
@@ -13,7 +13,10 @@ fn main() {
         let machine = machine.clone();
         let user = conf.user();
         let wordlist = conf.wordlist();
-        threads.push(thread::spawn(move || machine.discovery(user, wordlist)));
+        let options = conf.options();
+        threads.push(thread::spawn(move || {
+            machine.discovery(user, wordlist, options)
+        }));
     }
 
     for thread in threads {
@@ -0,0 +1,242 @@
+use crate::run_command_with_args;
+use std::error::Error;
+use std::fs;
+use std::net::Ipv4Addr;
+use std::os::unix::fs::{MetadataExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+
+// Stop listing an export after this many entries, some of them are whole filesystems
+const MAX_ENTRIES: usize = 10000;
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct NfsExport {
+    clients: Vec<String>,
+    path: String,
+}
+
+impl NfsExport {
+    pub fn clients(&self) -> &Vec<String> {
+        &self.clients
+    }
+
+    pub fn path(&self) -> &String {
+        &self.path
+    }
+
+    // Whether anyone can mount the export, rather than a specific list of machines
+    pub fn is_open_to_everyone(&self) -> bool {
+        self.clients
+            .iter()
+            .any(|client| client == "*" || client == "(everyone)" || client == "everyone")
+    }
+
+    // Whether this machine is allowed to mount the export, based on its own IPv4 networks
+    pub fn is_open_to(&self, networks: &[(Ipv4Addr, u8)]) -> bool {
+        self.is_open_to_everyone()
+            || self.clients.iter().any(|client| {
+                networks
+                    .iter()
+                    .any(|(address, _)| client_matches(client, *address))
+            })
+    }
+}
+
+// Parse the output of `showmount -e` into exports and the clients allowed to mount them
+pub fn parse_showmount(output: &str) -> Vec<NfsExport> {
+    output
+        .lines()
+        .filter(|line| line.starts_with('/'))
+        .filter_map(|line| {
+            // Clients come last, so split from the right in case the path has a space in it
+            let (path, clients) = line.trim().rsplit_once(char::is_whitespace)?;
+            Some(NfsExport {
+                clients: clients.split(',').map(str::to_string).collect(),
+                path: path.trim().to_string(),
+            })
+        })
+        .collect()
+}
+
+// Find the IPv4 addresses and prefix lengths of this machine's interfaces
+pub fn local_networks() -> Vec<(Ipv4Addr, u8)> {
+    let output = run_command_with_args("ip", vec!["-o", "-4", "addr", "show"]).unwrap_or_default();
+    output
+        .lines()
+        .filter_map(|line| {
+            let mut columns = line.split_whitespace();
+            columns.find(|column| *column == "inet")?;
+            let (address, prefix) = columns.next()?.split_once('/')?;
+            Some((address.parse().ok()?, prefix.parse().ok()?))
+        })
+        .filter(|(address, _): &(Ipv4Addr, u8)| !address.is_loopback())
+        .collect()
+}
+
+// Check a single client specification from an export list against an address: an IP, a CIDR block or a wildcard
+fn client_matches(client: &str, address: Ipv4Addr) -> bool {
+    if let Some((network, prefix)) = client.split_once('/') {
+        let (network, prefix) = match (network.parse::<Ipv4Addr>(), prefix.parse::<u32>()) {
+            (Ok(network), Ok(prefix)) if prefix <= 32 => (network, prefix),
+            _ => return false,
+        };
+        let mask = u32::MAX.checked_shl(32 - prefix).unwrap_or(0);
+        return u32::from(network) & mask == u32::from(address) & mask;
+    }
+
+    if client.contains('*') {
+        let prefix = client.trim_end_matches('*');
+        return address.to_string().starts_with(prefix);
+    }
+
+    client.parse::<Ipv4Addr>() == Ok(address)
+}
+
+// A read-only NFS mount that is unmounted and cleaned up when it goes out of scope, even if listing it fails
+pub struct NfsMount {
+    mount_point: PathBuf,
+}
+
+impl NfsMount {
+    pub fn new(
+        ip_string: &str,
+        export: &str,
+        mount_point: &Path,
+        version: Option<u32>,
+    ) -> Result<NfsMount, Box<dyn Error>> {
+        fs::create_dir_all(mount_point)?;
+        // Build the guard before mounting so the directory is cleaned up even if the mount fails
+        let mount = NfsMount {
+            mount_point: mount_point.to_path_buf(),
+        };
+
+        let source = format!("{ip_string}:{export}");
+        let target = mount_point.to_string_lossy().to_string();
+        // Ask for the highest NFS version the portmapper says is there, rather than letting mount guess
+        let mut options = "ro,nolock,soft,timeo=50,retrans=2".to_string();
+        if let Some(version) = version {
+            options.push_str(&format!(",vers={version}"));
+        }
+        let status = std::process::Command::new("mount")
+            .args(["-t", "nfs", "-o", &options])
+            .args([source.as_str(), target.as_str()])
+            .output()?;
+        if !status.status.success() {
+            return Err(String::from_utf8_lossy(&status.stderr)
+                .trim()
+                .to_string()
+                .into());
+        }
+
+        Ok(mount)
+    }
+
+    pub fn mount_point(&self) -> &PathBuf {
+        &self.mount_point
+    }
+}
+
+impl Drop for NfsMount {
+    fn drop(&mut self) {
+        let target = self.mount_point.to_string_lossy().to_string();
+        let _ = run_command_with_args("umount", vec!["-f", "-l", &target]);
+        let _ = fs::remove_dir(&self.mount_point);
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NfsEntry {
+    gid: u32,
+    mode: u32,
+    path: String,
+    size: u64,
+    uid: u32,
+}
+
+impl NfsEntry {
+    pub fn gid(&self) -> u32 {
+        self.gid
+    }
+
+    pub fn mode(&self) -> u32 {
+        self.mode
+    }
+
+    pub fn path(&self) -> &String {
+        &self.path
+    }
+
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    pub fn uid(&self) -> u32 {
+        self.uid
+    }
+
+    pub fn is_dir(&self) -> bool {
+        self.mode & 0o170000 == 0o040000
+    }
+
+    // Anyone at all could write here if the export were mounted read-write
+    pub fn is_world_writable(&self) -> bool {
+        self.mode & 0o002 != 0 && self.mode & 0o170000 != 0o120000
+    }
+
+    // Render the entry like `ls -ln`
+    pub fn display(&self) -> String {
+        let kind = match self.mode & 0o170000 {
+            0o040000 => 'd',
+            0o120000 => 'l',
+            _ => '-',
+        };
+        let permissions: String = (0..9)
+            .map(|i| {
+                let bit = self.mode & (0o400 >> i) != 0;
+                match (bit, i % 3) {
+                    (false, _) => '-',
+                    (true, 0) => 'r',
+                    (true, 1) => 'w',
+                    (true, _) => 'x',
+                }
+            })
+            .collect();
+        format!(
+            "{kind}{permissions} {: >6} {: >6} {: >12} {}",
+            self.uid, self.gid, self.size, self.path
+        )
+    }
+}
+
+// List everything under the mount point, without following symlinks off of the export
+pub fn walk(root: &Path) -> Vec<NfsEntry> {
+    let mut entries = vec![];
+    let mut pending = vec![root.to_path_buf()];
+
+    while let Some(path) = pending.pop() {
+        if entries.len() >= MAX_ENTRIES {
+            break;
+        }
+        let metadata = match fs::symlink_metadata(&path) {
+            Ok(metadata) => metadata,
+            Err(_) => continue,
+        };
+        let relative = path.strip_prefix(root).unwrap_or(&path);
+        entries.push(NfsEntry {
+            gid: metadata.gid(),
+            mode: metadata.permissions().mode(),
+            path: format!("/{}", relative.to_string_lossy()),
+            size: metadata.len(),
+            uid: metadata.uid(),
+        });
+
+        if metadata.is_dir() {
+            if let Ok(children) = fs::read_dir(&path) {
+                let mut children: Vec<PathBuf> = children.flatten().map(|c| c.path()).collect();
+                children.sort();
+                pending.extend(children.into_iter().rev());
+            }
+        }
+    }
+
+    entries
+}
@@ -0,0 +1,291 @@
+use crate::http::HttpClient;
+use std::io::{Read, Write};
+use std::net::{IpAddr, SocketAddr, TcpStream};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+// Probes sent to services that don't announce themselves, in the order they are tried
+const PROBES: [(&str, &[u8]); 4] = [
+    ("HTTP GET", b"GET / HTTP/1.0\r\n\r\n"),
+    ("SSH", b"SSH-2.0-imd\r\n"),
+    ("SMTP EHLO", b"EHLO imd\r\n"),
+    ("Redis PING", b"*1\r\n$4\r\nPING\r\n"),
+];
+
+#[derive(Clone, Debug, Default)]
+pub struct ProbeResult {
+    probe: String,
+    response: Vec<u8>,
+    service: Option<String>,
+}
+
+impl ProbeResult {
+    // Name of the probe that got the response, or "banner" if the service spoke first
+    pub fn probe(&self) -> &String {
+        &self.probe
+    }
+
+    pub fn response(&self) -> &Vec<u8> {
+        &self.response
+    }
+
+    pub fn service(&self) -> Option<&String> {
+        self.service.as_ref()
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct WebCheck {
+    method: String,
+    protocol: Option<String>,
+    responded: bool,
+}
+
+impl WebCheck {
+    // How the port was classified, for the record
+    pub fn method(&self) -> &String {
+        &self.method
+    }
+
+    // Either "http" or "https" if the port hosts a website
+    pub fn protocol(&self) -> Option<&String> {
+        self.protocol.as_ref()
+    }
+
+    // Whether the port said anything at all to either check
+    pub fn responded(&self) -> bool {
+        self.responded
+    }
+}
+
+// Classify a port as http, https or not a website by actually talking to it, TLS first since plain HTTP servers ignore a ClientHello
+pub fn detect_web(ip_address: IpAddr, port: u16, timeout: Duration) -> WebCheck {
+    let tls_response = exchange(ip_address, port, Some(&tls_client_hello()), timeout);
+    if tls_response.as_ref().is_some_and(|r| is_tls_response(r)) {
+        let client = HttpClient::new("https", ip_address, port, timeout);
+        return match client.get(&ip_address.to_string(), "/") {
+            Ok(response) => WebCheck {
+                method: format!(
+                    "TLS handshake, then HTTPS request answered with {}",
+                    response.status()
+                ),
+                protocol: Some("https".to_string()),
+                responded: true,
+            },
+            Err(_) => WebCheck {
+                method: "TLS handshake, but no answer to an HTTPS request".to_string(),
+                protocol: None,
+                responded: true,
+            },
+        };
+    }
+
+    let client = HttpClient::new("http", ip_address, port, timeout);
+    match client.get(&ip_address.to_string(), "/") {
+        Ok(response) => WebCheck {
+            method: format!("plain HTTP request answered with {}", response.status()),
+            protocol: Some("http".to_string()),
+            responded: true,
+        },
+        Err(_) => {
+            let responded = tls_response.is_some()
+                || exchange(ip_address, port, Some(PROBES[0].1), timeout).is_some();
+            WebCheck {
+                method: match responded {
+                    true => "responded to neither HTTP nor TLS".to_string(),
+                    false => "no response to HTTP or TLS".to_string(),
+                },
+                protocol: None,
+                responded,
+            }
+        }
+    }
+}
+
+// Work out what is listening on a port by waiting for a banner, then sending a handful of protocol probes
+pub fn identify(ip_address: IpAddr, port: u16, timeout: Duration) -> ProbeResult {
+    let mut first_response = None;
+
+    if let Some(banner) = exchange(ip_address, port, None, timeout) {
+        let result = ProbeResult {
+            probe: "banner".to_string(),
+            service: classify(&banner).map(str::to_string),
+            response: banner,
+        };
+        if result.service.is_some() {
+            return result;
+        }
+        first_response = Some(result);
+    }
+
+    for (name, payload) in PROBES {
+        if let Some(response) = exchange(ip_address, port, Some(payload), timeout) {
+            let result = ProbeResult {
+                probe: name.to_string(),
+                service: classify(&response).map(str::to_string),
+                response,
+            };
+            if result.service.is_some() {
+                return result;
+            }
+            first_response.get_or_insert(result);
+        }
+    }
+
+    if let Some(response) = exchange(ip_address, port, Some(&tls_client_hello()), timeout) {
+        if is_tls_response(&response) {
+            // Something speaks TLS here, see if it is a website hiding behind it
+            let client = HttpClient::new("https", ip_address, port, timeout);
+            let service = match client.get(&ip_address.to_string(), "/") {
+                Ok(_) => "ssl/http",
+                Err(_) => "ssl/unknown",
+            };
+            return ProbeResult {
+                probe: "TLS ClientHello".to_string(),
+                response,
+                service: Some(service.to_string()),
+            };
+        }
+    }
+
+    first_response.unwrap_or_default()
+}
+
+// Check whether a port completes the start of a TLS handshake
+pub fn is_tls(ip_address: IpAddr, port: u16, timeout: Duration) -> bool {
+    exchange(ip_address, port, Some(&tls_client_hello()), timeout)
+        .is_some_and(|response| is_tls_response(&response))
+}
+
+// Connect to a port, optionally send a payload, and return the first bytes the service responds with
+pub fn exchange(
+    ip_address: IpAddr,
+    port: u16,
+    payload: Option<&[u8]>,
+    timeout: Duration,
+) -> Option<Vec<u8>> {
+    let address = SocketAddr::new(ip_address, port);
+    let mut stream = TcpStream::connect_timeout(&address, timeout).ok()?;
+    stream.set_read_timeout(Some(timeout)).ok()?;
+    stream.set_write_timeout(Some(timeout)).ok()?;
+
+    if let Some(payload) = payload {
+        stream.write_all(payload).ok()?;
+    }
+
+    let mut buffer = [0u8; 2048];
+    match stream.read(&mut buffer) {
+        Ok(size) if size > 0 => Some(buffer[..size].to_vec()),
+        _ => None,
+    }
+}
+
+// Name the service based on what it sent back, using nmap's names
+pub fn classify(response: &[u8]) -> Option<&'static str> {
+    let text = String::from_utf8_lossy(response);
+    let lower = text.to_lowercase();
+
+    if text.starts_with("HTTP/") {
+        Some("http")
+    } else if text.starts_with("SSH-") {
+        Some("ssh")
+    } else if text.starts_with("220") && lower.contains("ftp") {
+        Some("ftp")
+    } else if (text.starts_with("220") && lower.contains("smtp")) || text.starts_with("250") {
+        Some("smtp")
+    } else if text.starts_with("+OK") {
+        Some("pop3")
+    } else if text.starts_with("* OK") {
+        Some("imap")
+    } else if text.starts_with("RFB ") {
+        Some("vnc")
+    } else if text.starts_with("+PONG")
+        || text.starts_with("-NOAUTH")
+        || text.starts_with("-DENIED")
+    {
+        Some("redis")
+    } else if lower.contains("mysql") || lower.contains("mariadb") {
+        Some("mysql")
+    } else {
+        None
+    }
+}
+
+// A TLS server answers a ClientHello with either a handshake or an alert record
+fn is_tls_response(response: &[u8]) -> bool {
+    matches!(response, [0x15 | 0x16, 0x03, ..])
+}
+
+// Build a minimal TLS 1.2 ClientHello offering common cipher suites, enough to get any TLS server to answer
+pub fn tls_client_hello() -> Vec<u8> {
+    let ciphers: [u16; 17] = [
+        0x1301, 0x1302, 0x1303, 0xc02f, 0xc030, 0xc02b, 0xc02c, 0xc013, 0xc014, 0x009c, 0x009d,
+        0x002f, 0x0035, 0x0033, 0x0039, 0x000a, 0x00ff,
+    ];
+    let groups: [u16; 3] = [0x001d, 0x0017, 0x0018];
+    let signatures: [u16; 9] = [
+        0x0403, 0x0503, 0x0603, 0x0804, 0x0805, 0x0806, 0x0401, 0x0501, 0x0601,
+    ];
+
+    let mut extensions = vec![];
+    // supported_groups
+    extensions.extend(u16_list_extension(0x000a, &groups));
+    // ec_point_formats, uncompressed only
+    extensions.extend([0x00, 0x0b, 0x00, 0x02, 0x01, 0x00]);
+    // signature_algorithms
+    extensions.extend(u16_list_extension(0x000d, &signatures));
+
+    let mut hello = vec![0x03, 0x03];
+    let seed = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos();
+    hello.extend((0..32).map(|i| (seed >> (i % 16 * 8)) as u8 ^ (i as u8).wrapping_mul(37)));
+    hello.push(0x00);
+    hello.extend(((ciphers.len() * 2) as u16).to_be_bytes());
+    ciphers.iter().for_each(|c| hello.extend(c.to_be_bytes()));
+    hello.extend([0x01, 0x00]);
+    hello.extend((extensions.len() as u16).to_be_bytes());
+    hello.extend(extensions);
+
+    let mut handshake = vec![0x01];
+    handshake.extend(&(hello.len() as u32).to_be_bytes()[1..]);
+    handshake.extend(hello);
+
+    let mut record = vec![0x16, 0x03, 0x01];
+    record.extend((handshake.len() as u16).to_be_bytes());
+    record.extend(handshake);
+    record
+}
+
+// Encode a TLS extension whose body is a length-prefixed list of 16 bit values
+fn u16_list_extension(extension: u16, values: &[u16]) -> Vec<u8> {
+    let list_len = (values.len() * 2) as u16;
+    let mut output = extension.to_be_bytes().to_vec();
+    output.extend((list_len + 2).to_be_bytes());
+    output.extend(list_len.to_be_bytes());
+    values.iter().for_each(|v| output.extend(v.to_be_bytes()));
+    output
+}
+
+// Lay bytes out the way `xxd` would, to make binary responses readable in output files
+pub fn hexdump(bytes: &[u8]) -> String {
+    bytes
+        .chunks(16)
+        .enumerate()
+        .map(|(i, chunk)| {
+            let hex: Vec<String> = chunk.iter().map(|b| format!("{b:02x}")).collect();
+            let text: String = chunk
+                .iter()
+                .map(|&b| {
+                    if (0x20..0x7f).contains(&b) {
+                        b as char
+                    } else {
+                        '.'
+                    }
+                })
+                .collect();
+            format!("{:08x}: {: <48} {text}", i * 16, hex.join(" "))
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
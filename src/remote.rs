@@ -0,0 +1,325 @@
+use crate::error::RecoverableDiscoveryError;
+use crate::http::HttpClient;
+use crate::scanner::OpenPort;
+use std::error::Error;
+use std::io::{Read, Write};
+use std::net::{IpAddr, SocketAddr, TcpStream};
+use std::time::Duration;
+
+// RDP security protocols, as requested in and chosen by the connection negotiation
+const RDP_PROTOCOLS: [(u32, &str); 3] = [(0, "Standard RDP"), (1, "TLS"), (3, "CredSSP (NLA)")];
+
+// Remote access services that hint at which credentials would be worth having
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RemoteService {
+    Rdp,
+    Vnc,
+    Winrm,
+}
+
+impl RemoteService {
+    // Work out which remote access service (if any) the port scan found, by service name and then by the usual ports
+    pub fn from_open_port(open_port: &OpenPort) -> Option<RemoteService> {
+        if open_port.protocol() != "tcp" {
+            return None;
+        }
+        match (open_port.service().as_str(), open_port.port()) {
+            ("ms-wbt-server", _) | (_, 3389) => Some(RemoteService::Rdp),
+            (service, _) if service.starts_with("vnc") => Some(RemoteService::Vnc),
+            (_, 5900..=5906) => Some(RemoteService::Vnc),
+            ("wsman" | "wsmans", _) | (_, 5985 | 5986) => Some(RemoteService::Winrm),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        match self {
+            RemoteService::Rdp => "rdp",
+            RemoteService::Vnc => "vnc",
+            RemoteService::Winrm => "winrm",
+        }
+    }
+
+    // What imd asks the server for, for the progress bar
+    pub fn check_name(&self) -> &str {
+        match self {
+            RemoteService::Rdp => "security negotiation",
+            RemoteService::Vnc => "RFB handshake",
+            RemoteService::Winrm => "POST /wsman",
+        }
+    }
+
+    pub fn check(
+        &self,
+        ip_address: IpAddr,
+        open_port: &OpenPort,
+        timeout: Duration,
+    ) -> Result<RemoteReport, Box<dyn Error>> {
+        match self {
+            RemoteService::Rdp => rdp(ip_address, open_port.port(), timeout),
+            RemoteService::Vnc => vnc(ip_address, open_port.port(), timeout),
+            RemoteService::Winrm => {
+                let tls = open_port.port() == 5986
+                    || open_port.service().starts_with("ssl/")
+                    || open_port.service() == "wsmans";
+                winrm(ip_address, open_port.port(), tls, timeout)
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RemoteReport {
+    details: Vec<(String, String)>,
+    findings: Vec<(bool, String)>,
+}
+
+impl RemoteReport {
+    pub fn details(&self) -> &Vec<(String, String)> {
+        &self.details
+    }
+
+    // Lines for the target's summary, flagged if they are a likely way in
+    pub fn findings(&self) -> &Vec<(bool, String)> {
+        &self.findings
+    }
+
+    fn detail(&mut self, key: &str, value: String) {
+        self.details.push((key.to_string(), value));
+    }
+}
+
+// Offer each security protocol on its own and see which ones the server will settle on
+fn rdp(ip_address: IpAddr, port: u16, timeout: Duration) -> Result<RemoteReport, Box<dyn Error>> {
+    let mut report = RemoteReport::default();
+    let mut accepted = vec![];
+    let mut nla_required = false;
+
+    let mut answered = false;
+
+    for (protocol, name) in RDP_PROTOCOLS {
+        // One probe getting reset or timing out says nothing about the others
+        let (kind, value) = match rdp_negotiate(ip_address, port, protocol, timeout) {
+            Ok(response) => response,
+            Err(e) => {
+                report.detail(name, format!("no response ({e})"));
+                continue;
+            }
+        };
+        answered = true;
+        let result = match (kind, value) {
+            // Servers that don't negotiate at all only speak standard RDP
+            (None, _) if protocol == 0 => "accepted".to_string(),
+            (None, _) => "not offered".to_string(),
+            (Some(2), _) => "accepted".to_string(),
+            (Some(3), 5) => {
+                nla_required = true;
+                "refused, NLA required".to_string()
+            }
+            (Some(3), 6) => {
+                nla_required = true;
+                "refused, TLS with NLA required".to_string()
+            }
+            (Some(3), 1) => "refused, TLS required".to_string(),
+            (Some(3), 2) => "refused, the server does not allow TLS".to_string(),
+            (Some(kind), value) => format!("unexpected response ({kind}, {value})"),
+        };
+        if result == "accepted" {
+            accepted.push(name);
+        }
+        report.detail(name, result);
+    }
+
+    if !answered {
+        report.detail("NLA required", "unknown, no probe was answered".to_string());
+        return Ok(report);
+    }
+    report.detail(
+        "NLA required",
+        if nla_required { "yes" } else { "no" }.to_string(),
+    );
+    let finding = match nla_required {
+        true => format!("RDP on port {port} requires NLA, so credentials are needed to see a login screen"),
+        false => format!(
+            "RDP on port {port} doesn't require NLA ({}), so the login screen is reachable without credentials",
+            accepted.join(", ")
+        ),
+    };
+    report.findings.push((false, finding));
+    Ok(report)
+}
+
+// Send an X.224 connection request with an RDP negotiation request, returning the negotiation response type and value
+fn rdp_negotiate(
+    ip_address: IpAddr,
+    port: u16,
+    protocols: u32,
+    timeout: Duration,
+) -> Result<(Option<u8>, u32), Box<dyn Error>> {
+    let mut stream = TcpStream::connect_timeout(&SocketAddr::new(ip_address, port), timeout)?;
+    stream.set_read_timeout(Some(timeout * 5))?;
+    stream.set_write_timeout(Some(timeout * 5))?;
+
+    let mut request = vec![
+        0x03, 0x00, 0x00, 0x13, 0x0e, 0xe0, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x08, 0x00,
+    ];
+    request.extend(protocols.to_le_bytes());
+    stream.write_all(&request)?;
+
+    let mut header = [0u8; 4];
+    stream.read_exact(&mut header)?;
+    let length = u16::from_be_bytes([header[2], header[3]]) as usize;
+    if header[0] != 0x03 || length < 11 {
+        return Err(RecoverableDiscoveryError::Response.into());
+    }
+    let mut body = vec![0u8; length - 4];
+    stream.read_exact(&mut body)?;
+
+    // The negotiation response (if any) follows the seven byte X.224 connection confirm
+    match body.get(7..15) {
+        Some(negotiation) => Ok((
+            Some(negotiation[0]),
+            u32::from_le_bytes(negotiation[4..8].try_into()?),
+        )),
+        None => Ok((None, 0)),
+    }
+}
+
+// Read the RFB version and the security types the server offers
+fn vnc(ip_address: IpAddr, port: u16, timeout: Duration) -> Result<RemoteReport, Box<dyn Error>> {
+    let mut stream = TcpStream::connect_timeout(&SocketAddr::new(ip_address, port), timeout)?;
+    stream.set_read_timeout(Some(timeout * 5))?;
+    stream.set_write_timeout(Some(timeout * 5))?;
+
+    let mut version = [0u8; 12];
+    stream.read_exact(&mut version)?;
+    if !version.starts_with(b"RFB ") {
+        return Err(RecoverableDiscoveryError::Response.into());
+    }
+    let version_text = String::from_utf8_lossy(&version).trim().to_string();
+    let minor: u32 = version_text
+        .rsplit('.')
+        .next()
+        .unwrap_or_default()
+        .parse()
+        .unwrap_or(3);
+    // Answer with the highest version both sides know, which decides how security types are sent
+    let reply: &[u8] = match minor {
+        8.. => b"RFB 003.008\n",
+        7 => b"RFB 003.007\n",
+        _ => b"RFB 003.003\n",
+    };
+    stream.write_all(reply)?;
+
+    let types = match minor >= 7 {
+        true => {
+            let mut count = [0u8; 1];
+            stream.read_exact(&mut count)?;
+            let mut types = vec![0u8; count[0] as usize];
+            stream.read_exact(&mut types)?;
+            types
+        }
+        // Version 3.3 servers pick the one type themselves
+        false => {
+            let mut chosen = [0u8; 4];
+            stream.read_exact(&mut chosen)?;
+            vec![u32::from_be_bytes(chosen) as u8]
+        }
+    };
+
+    let names: Vec<String> = types.iter().map(|t| vnc_security_type(*t)).collect();
+    let none = types.contains(&1);
+    let mut report = RemoteReport::default();
+    report.detail("Protocol version", version_text);
+    report.detail(
+        "Security types",
+        match names.is_empty() {
+            true => "none offered (connection refused)".to_string(),
+            false => names.join(", "),
+        },
+    );
+    report.detail(
+        "No authentication",
+        if none { "yes" } else { "no" }.to_string(),
+    );
+    if none {
+        report.findings.push((
+            true,
+            format!("VNC on port {port} allows connections with no authentication"),
+        ));
+    } else if types.contains(&2) {
+        report.findings.push((
+            false,
+            format!(
+                "VNC on port {port} uses VNC password authentication, look for saved VNC passwords"
+            ),
+        ));
+    }
+    Ok(report)
+}
+
+fn vnc_security_type(security_type: u8) -> String {
+    match security_type {
+        0 => "Invalid".to_string(),
+        1 => "None".to_string(),
+        2 => "VNC Authentication".to_string(),
+        5..=15 => format!("RealVNC ({security_type})"),
+        16 => "Tight".to_string(),
+        18 => "TLS".to_string(),
+        19 => "VeNCrypt".to_string(),
+        30..=35 => format!("Apple ({security_type})"),
+        _ => format!("Unknown ({security_type})"),
+    }
+}
+
+// WinRM answers an unauthenticated POST to /wsman with the authentication schemes it accepts
+fn winrm(
+    ip_address: IpAddr,
+    port: u16,
+    tls: bool,
+    timeout: Duration,
+) -> Result<RemoteReport, Box<dyn Error>> {
+    let scheme = if tls { "https" } else { "http" };
+    let client = HttpClient::new(scheme, ip_address, port, timeout);
+    let headers = [("Content-Type", "application/soap+xml;charset=UTF-8")];
+    let response = client.request("POST", &ip_address.to_string(), "/wsman", &headers, b"")?;
+
+    let schemes: Vec<String> = response
+        .headers()
+        .iter()
+        .filter(|(name, _)| name.eq_ignore_ascii_case("WWW-Authenticate"))
+        .filter_map(|(_, value)| value.split_whitespace().next())
+        .map(str::to_string)
+        .collect();
+
+    let mut report = RemoteReport::default();
+    report.detail("URL", client.url(&ip_address.to_string(), "/wsman"));
+    report.detail("Status", response.status().to_string());
+    report.detail(
+        "Auth schemes",
+        match schemes.is_empty() {
+            true => "none advertised".to_string(),
+            false => schemes.join(", "),
+        },
+    );
+
+    let answers = response.status() == 401 || response.status() == 200;
+    report.detail(
+        "WinRM answers",
+        if answers { "yes" } else { "no" }.to_string(),
+    );
+    if answers {
+        let finding = format!(
+            "WinRM answers on port {port} ({}), valid credentials there give a shell with evil-winrm",
+            schemes.join(", ")
+        );
+        report.findings.push((false, finding));
+    }
+    if !tls && schemes.iter().any(|s| s.eq_ignore_ascii_case("Basic")) {
+        report.findings.push((
+            true,
+            format!("WinRM on port {port} accepts Basic authentication over plain HTTP"),
+        ));
+    }
+    Ok(report)
+}
@@ -0,0 +1,99 @@
+// The ONC RPC portmapper and the Microsoft RPC endpoint mapper
+pub const PORTMAPPER_PORT: u16 = 111;
+pub const MSRPC_PORT: u16 = 135;
+
+// Interfaces the endpoint mapper can list that are known ways to coerce authentication or worse
+const RISKY_INTERFACES: [(&str, &str); 3] = [
+    (
+        "MS-RPRN",
+        "print spooler, PrinterBug coercion and PrintNightmare",
+    ),
+    ("MS-PAR", "print spooler, PrintNightmare"),
+    ("MS-EFSR", "EFS, PetitPotam coercion"),
+];
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RpcProgram {
+    port: u16,
+    program: u32,
+    protocol: String,
+    service: String,
+    version: u32,
+}
+
+impl RpcProgram {
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    pub fn program(&self) -> u32 {
+        self.program
+    }
+
+    pub fn protocol(&self) -> &String {
+        &self.protocol
+    }
+
+    pub fn service(&self) -> &String {
+        &self.service
+    }
+
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+}
+
+// Parse the table `rpcinfo -p` prints: program, version, protocol, port and (when known) service name
+pub fn parse_rpcinfo(output: &str) -> Vec<RpcProgram> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let columns: Vec<&str> = line.split_whitespace().collect();
+            if columns.len() < 4 {
+                return None;
+            }
+            Some(RpcProgram {
+                port: columns[3].parse().ok()?,
+                program: columns[0].parse().ok()?,
+                protocol: columns[2].to_string(),
+                service: columns.get(4).unwrap_or(&"").to_string(),
+                version: columns[1].parse().ok()?,
+            })
+        })
+        .collect()
+}
+
+// The versions registered for a service, lowest first and without repeats for TCP and UDP
+pub fn versions(programs: &[RpcProgram], service: &str) -> Vec<u32> {
+    let mut versions: Vec<u32> = programs
+        .iter()
+        .filter(|program| program.service == service)
+        .map(|program| program.version)
+        .collect();
+    versions.sort();
+    versions.dedup();
+    versions
+}
+
+// Pull the interface names out of impacket's rpcdump output, which lists one "Protocol:" line per endpoint
+pub fn parse_rpcdump(output: &str) -> Vec<String> {
+    let mut protocols: Vec<String> = vec![];
+    for line in output.lines() {
+        if let Some(protocol) = line.trim().strip_prefix("Protocol:") {
+            let protocol = protocol.trim().to_string();
+            if protocol != "N/A" && !protocols.contains(&protocol) {
+                protocols.push(protocol);
+            }
+        }
+    }
+    protocols
+}
+
+// The interfaces from rpcdump worth calling out, and why
+pub fn risky_interfaces(protocols: &[String]) -> Vec<(&'static str, &'static str)> {
+    RISKY_INTERFACES
+        .iter()
+        .filter(|(name, _)| protocols.iter().any(|p| p.contains(&format!("[{name}]"))))
+        .copied()
+        .collect()
+}
@@ -0,0 +1,351 @@
+use crate::error::PanicDiscoveryError;
+use std::collections::{HashMap, HashSet};
+use std::io::{Read, Write};
+use std::net::{IpAddr, SocketAddr, TcpStream};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+// Ports the native scanner checks when the user doesn't provide their own list
+pub const DEFAULT_PORTS: &str = "21-23,25,53,80,81,88,110,111,135,139,143,389,443,445,465,587,593,636,873,993,995,1433,1521,2049,2375,3000,3128,3268,3306,3389,4443,5000,5432,5900,5985,5986,6379,8000,8008,8080,8081,8443,8888,9000,9090,9200,11211,27017";
+
+// Tool used to discover open TCP ports
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PortScanner {
+    #[default]
+    Nmap,
+    Native,
+}
+
+impl PortScanner {
+    // Parse a PortScanner from the command line arguments
+    pub fn new(input: &str) -> Result<PortScanner, PanicDiscoveryError> {
+        match input {
+            "nmap" => Ok(PortScanner::Nmap),
+            "native" => Ok(PortScanner::Native),
+            _ => Err(PanicDiscoveryError::InvalidScanner),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OpenPort {
+    identified: bool,
+    port: u16,
+    protocol: String,
+    service: String,
+    state: String,
+    version: String,
+}
+
+impl OpenPort {
+    // Whether the scanner was confident about the service, rather than guessing or giving up
+    pub fn identified(&self) -> bool {
+        self.identified
+    }
+
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    pub fn protocol(&self) -> &String {
+        &self.protocol
+    }
+
+    pub fn service(&self) -> &String {
+        &self.service
+    }
+
+    pub fn state(&self) -> &String {
+        &self.state
+    }
+
+    pub fn version(&self) -> &String {
+        &self.version
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct NativeScanner {
+    concurrency: usize,
+    ports: Vec<u16>,
+    timeout: Duration,
+}
+
+impl Default for NativeScanner {
+    fn default() -> NativeScanner {
+        NativeScanner {
+            concurrency: 200,
+            ports: parse_port_list(DEFAULT_PORTS).unwrap(),
+            timeout: Duration::from_millis(1000),
+        }
+    }
+}
+
+impl NativeScanner {
+    pub fn new(concurrency: usize, ports: Vec<u16>, timeout: Duration) -> NativeScanner {
+        NativeScanner {
+            concurrency: concurrency.max(1),
+            ports,
+            timeout,
+        }
+    }
+
+    pub fn concurrency(&self) -> usize {
+        self.concurrency
+    }
+
+    pub fn ports(&self) -> &Vec<u16> {
+        &self.ports
+    }
+
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    // Connect to each of the provided ports, grabbing a banner from the ones that accept the connection
+    pub fn scan(&self, ip_address: IpAddr, ports: &[u16]) -> Vec<(u16, Option<String>)> {
+        let ports = Arc::new(ports.to_vec());
+        let next = Arc::new(AtomicUsize::new(0));
+        let found = Arc::new(Mutex::new(vec![]));
+        let mut threads: Vec<std::thread::JoinHandle<()>> = vec![];
+
+        for _ in 0..self.concurrency.min(ports.len()) {
+            threads.push(thread::spawn({
+                let found = found.clone();
+                let next = next.clone();
+                let ports = ports.clone();
+                let timeout = self.timeout;
+                move || {
+                    // Each worker pulls the next unscanned port until there are none left
+                    while let Some(&port) = ports.get(next.fetch_add(1, Ordering::SeqCst)) {
+                        let address = SocketAddr::new(ip_address, port);
+                        if let Ok(mut stream) = TcpStream::connect_timeout(&address, timeout) {
+                            let banner = grab_banner(&mut stream, timeout);
+                            found.lock().unwrap().push((port, banner));
+                        }
+                    }
+                }
+            }));
+        }
+
+        for thread in threads {
+            thread.join().unwrap();
+        }
+
+        let mut found = found.lock().unwrap().clone();
+        found.sort_by_key(|(port, _)| *port);
+        found
+    }
+}
+
+// Parse the open ports out of an nmap style port table, which both nmap and the native scanner produce
+pub fn parse_open_ports(port_scan: &str) -> Vec<OpenPort> {
+    // nmap prints a fingerprint block starting with "SF-Port<N>-TCP" for every service it couldn't recognize
+    let fingerprinted: HashSet<String> = port_scan
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix("SF-Port"))
+        .filter_map(|line| line.split_once('-'))
+        .map(|(port, protocol)| {
+            format!(
+                "{port}/{}",
+                protocol
+                    .split(':')
+                    .next()
+                    .unwrap_or_default()
+                    .to_lowercase()
+            )
+        })
+        .collect();
+
+    port_scan
+        .lines()
+        .map(str::trim)
+        .filter_map(|line| {
+            let mut columns = line.split_whitespace();
+            let (port, protocol) = columns.next()?.split_once('/')?;
+            let state = columns.next()?;
+            if !state.starts_with("open") {
+                return None;
+            }
+            let service = columns.next().unwrap_or("unknown");
+            let version = columns.collect::<Vec<&str>>().join(" ");
+            let identified = !(service.ends_with('?')
+                || service == "unknown"
+                || service == "tcpwrapped"
+                || fingerprinted.contains(&format!("{port}/{protocol}")));
+            Some(OpenPort {
+                identified,
+                port: port.parse().ok()?,
+                protocol: protocol.to_string(),
+                service: service.trim_end_matches('?').to_string(),
+                state: state.to_string(),
+                version,
+            })
+        })
+        .collect()
+}
+
+// Group the ports hosting websites by the protocol used to reach them
+pub fn web_services(open_ports: &[OpenPort]) -> HashMap<String, Vec<String>> {
+    let mut services_map: HashMap<String, Vec<String>> = HashMap::new();
+    for open_port in open_ports {
+        if open_port.protocol != "tcp" || !open_port.service.contains("http") {
+            continue;
+        }
+        let service = match open_port.service.starts_with("ssl/") {
+            true => "https",
+            false => "http",
+        };
+        services_map
+            .entry(service.to_string())
+            .or_default()
+            .push(open_port.port.to_string());
+    }
+    services_map
+}
+
+// Read whatever the service says first, nudging it with an HTTP request if it waits for the client to talk
+pub fn grab_banner(stream: &mut TcpStream, timeout: Duration) -> Option<String> {
+    stream.set_read_timeout(Some(timeout)).ok()?;
+    stream.set_write_timeout(Some(timeout)).ok()?;
+    let mut buffer = [0u8; 1024];
+
+    if let Ok(size @ 1..) = stream.read(&mut buffer) {
+        return Some(printable(&buffer[..size]));
+    }
+
+    stream.write_all(b"HEAD / HTTP/1.0\r\n\r\n").ok()?;
+    match stream.read(&mut buffer) {
+        Ok(size @ 1..) => Some(printable(&buffer[..size])),
+        _ => None,
+    }
+}
+
+// Format the results of a native scan the same way nmap lays out its port table so the same parsing applies to both
+pub fn format_results(ip_address: IpAddr, results: &[(u16, Option<String>)]) -> String {
+    let mut output = format!(
+        "imd native TCP connect scan report for {ip_address}\nPORT      STATE SERVICE         VERSION\n"
+    );
+    for (port, banner) in results {
+        let banner = banner.clone().unwrap_or_default();
+        let service = guess_service(*port, &banner);
+        let port = format!("{port}/tcp");
+        output.push_str(&format!("{port: <9} open  {service: <15} {banner}\n"));
+    }
+    output.push_str(&format!("\n{} open TCP ports found\n", results.len()));
+    output
+}
+
+// Name a service the way nmap would, based first on what it says and then on the port it lives on
+pub fn guess_service(port: u16, banner: &str) -> &'static str {
+    let by_port = well_known_service(port);
+    let lower = banner.to_lowercase();
+
+    // A TLS service will usually answer a plaintext probe with a complaint, so trust the port for those
+    if by_port.starts_with("ssl/") {
+        return by_port;
+    }
+
+    if banner.starts_with("SSH-") {
+        "ssh"
+    } else if banner.starts_with("HTTP/") {
+        "http"
+    } else if banner.starts_with("220") && lower.contains("ftp") {
+        "ftp"
+    } else if banner.starts_with("220") && lower.contains("smtp") {
+        "smtp"
+    } else if banner.starts_with("+OK") {
+        "pop3"
+    } else if banner.starts_with("* OK") {
+        "imap"
+    } else if banner.starts_with("RFB ") {
+        "vnc"
+    } else if banner.starts_with("-ERR") || banner.starts_with("-NOAUTH") {
+        "redis"
+    } else if lower.contains("mysql") || lower.contains("mariadb") {
+        "mysql"
+    } else {
+        by_port
+    }
+}
+
+// Map a port to the service nmap would usually name for it
+fn well_known_service(port: u16) -> &'static str {
+    match port {
+        21 => "ftp",
+        22 => "ssh",
+        23 => "telnet",
+        25 => "smtp",
+        53 => "domain",
+        80 | 81 | 3000 | 5000 | 8000 | 8008 | 8080 | 8081 | 8888 | 9000 | 9090 | 9200 => "http",
+        88 => "kerberos-sec",
+        110 => "pop3",
+        111 => "rpcbind",
+        135 | 593 => "msrpc",
+        139 => "netbios-ssn",
+        143 => "imap",
+        389 | 3268 => "ldap",
+        443 | 4443 | 8443 => "ssl/http",
+        445 => "microsoft-ds",
+        465 => "ssl/smtp",
+        587 => "submission",
+        636 => "ssl/ldap",
+        873 => "rsync",
+        993 => "ssl/imap",
+        995 => "ssl/pop3",
+        1433 => "ms-sql-s",
+        1521 => "oracle-tns",
+        2049 => "nfs",
+        2375 => "docker",
+        3128 => "squid-http",
+        3306 => "mysql",
+        3389 => "ms-wbt-server",
+        5432 => "postgresql",
+        5900 => "vnc",
+        5985 => "wsman",
+        5986 => "wsmans",
+        6379 => "redis",
+        11211 => "memcache",
+        27017 => "mongodb",
+        _ => "unknown",
+    }
+}
+
+// Parse a comma separated list of ports and port ranges, E.G. 22,80,8000-8100
+pub fn parse_port_list(input: &str) -> Result<Vec<u16>, PanicDiscoveryError> {
+    let mut ports = vec![];
+    for part in input.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+        let (start, end) = part.split_once('-').unwrap_or((part, part));
+        let start = start.trim().parse::<u16>();
+        let end = end.trim().parse::<u16>();
+        match (start, end) {
+            (Ok(start), Ok(end)) if start > 0 && start <= end => ports.extend(start..=end),
+            _ => return Err(PanicDiscoveryError::InvalidPorts),
+        }
+    }
+    if ports.is_empty() {
+        return Err(PanicDiscoveryError::InvalidPorts);
+    }
+    ports.sort_unstable();
+    ports.dedup();
+    Ok(ports)
+}
+
+// Keep the first line of a banner, swapping anything unprintable for a dot
+fn printable(bytes: &[u8]) -> String {
+    let text: String = bytes
+        .iter()
+        .map(|&b| match b {
+            b'\n' | b'\r' => '\n',
+            0x20..=0x7e => b as char,
+            _ => '.',
+        })
+        .collect();
+    text.lines()
+        .map(str::trim)
+        .find(|s| !s.is_empty())
+        .unwrap_or_default()
+        .to_string()
+}
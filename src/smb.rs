@@ -0,0 +1,261 @@
+use crate::error::PanicDiscoveryError;
+use crate::run_command_with_args;
+use std::error::Error;
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Ports SMB is reachable on
+pub const SMB_PORTS: [u16; 2] = [139, 445];
+
+// Credentials tried against SMB: a null session, then the guest account
+const SESSIONS: [(&str, &str, &str); 2] = [("null", "", ""), ("guest", "guest", "")];
+
+// Tool used to enumerate SMB shares
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SmbTool {
+    Enum4linuxNg,
+    #[default]
+    Smbclient,
+    Smbmap,
+}
+
+impl SmbTool {
+    // Parse an SmbTool from the command line arguments
+    pub fn new(input: &str) -> Result<SmbTool, PanicDiscoveryError> {
+        match input {
+            "enum4linux-ng" => Ok(SmbTool::Enum4linuxNg),
+            "smbclient" => Ok(SmbTool::Smbclient),
+            "smbmap" => Ok(SmbTool::Smbmap),
+            _ => Err(PanicDiscoveryError::InvalidSmbTool),
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        match self {
+            SmbTool::Enum4linuxNg => "enum4linux-ng",
+            SmbTool::Smbclient => "smbclient",
+            SmbTool::Smbmap => "smbmap",
+        }
+    }
+
+    // List the shares visible to each of the anonymous sessions, along with the raw tool output. smbclient only tries writing
+    // to shares when write_test is set
+    pub fn enumerate(
+        &self,
+        ip_string: &str,
+        write_test: bool,
+    ) -> Result<(Vec<SmbShare>, String), Box<dyn Error>> {
+        let mut shares: Vec<SmbShare> = vec![];
+        let mut raw = String::new();
+
+        for (session, username, password) in SESSIONS {
+            let (found, output) = match self {
+                SmbTool::Enum4linuxNg => enum4linux_ng(ip_string, username, password)?,
+                SmbTool::Smbclient => smbclient(ip_string, username, password, write_test)?,
+                SmbTool::Smbmap => smbmap(ip_string, username, password)?,
+            };
+            raw.push_str(&format!("===== {session} session =====\n{output}\n"));
+            shares.extend(found.into_iter().map(|mut share| {
+                share.session = session.to_string();
+                share
+            }));
+        }
+
+        Ok((shares, raw))
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SmbShare {
+    comment: String,
+    name: String,
+    readable: bool,
+    session: String,
+    writable: bool,
+}
+
+impl SmbShare {
+    pub fn comment(&self) -> &String {
+        &self.comment
+    }
+
+    pub fn name(&self) -> &String {
+        &self.name
+    }
+
+    pub fn readable(&self) -> bool {
+        self.readable
+    }
+
+    // Which anonymous session (null or guest) saw the share
+    pub fn session(&self) -> &String {
+        &self.session
+    }
+
+    pub fn writable(&self) -> bool {
+        self.writable
+    }
+
+    // Describe the access the session has, the way smbmap does
+    pub fn access(&self) -> &str {
+        match (self.readable, self.writable) {
+            (true, true) => "READ, WRITE",
+            (true, false) => "READ ONLY",
+            (false, true) => "WRITE ONLY",
+            (false, false) => "NO ACCESS",
+        }
+    }
+}
+
+// List shares with smbclient, then try to list the contents of each disk share to see if it is readable and, when asked to,
+// make a directory in it to see if it is writable
+fn smbclient(
+    ip_string: &str,
+    username: &str,
+    password: &str,
+    write_test: bool,
+) -> Result<(Vec<SmbShare>, String), Box<dyn Error>> {
+    // An empty username and password is a null session, -N on its own logs in as the local user
+    let credentials = format!("{username}%{password}");
+    let auth = ["-N", "-U", &credentials];
+
+    let service = format!("//{ip_string}");
+    let mut args = vec!["-g", "-L", &service];
+    args.extend(auth);
+    let mut output = run_command_with_args("smbclient", args)?;
+
+    let mut shares = vec![];
+    for line in output.clone().lines() {
+        // -g prints shares as "<type>|<name>|<comment>", so names with spaces in them survive
+        let columns: Vec<&str> = line.splitn(3, '|').collect();
+        if columns.len() < 2 || !["Disk", "IPC", "Printer"].contains(&columns[0]) {
+            continue;
+        }
+
+        let name = columns[1];
+        let share = format!("//{ip_string}/{name}");
+        let (readable, writable) = match columns[0] {
+            "Disk" => {
+                let mut args = vec![share.as_str(), "-c", "ls"];
+                args.extend(auth);
+                let (listed, listing) = smbclient_command(&args);
+                output.push_str(&format!("\n--- ls {share} ---\n{listing}"));
+                let readable = listed && !listing.trim().is_empty();
+
+                let written = match write_test {
+                    true => {
+                        let directory = format!(
+                            "imd-write-test-{}-{}",
+                            std::process::id(),
+                            SystemTime::now()
+                                .duration_since(UNIX_EPOCH)
+                                .map(|time| time.subsec_nanos())
+                                .unwrap_or_default()
+                        );
+                        let commands = format!("mkdir {directory}; rmdir {directory}");
+                        let mut args = vec![share.as_str(), "-c", &commands];
+                        args.extend(auth);
+                        let (written, attempt) = smbclient_command(&args);
+                        output.push_str(&format!(
+                            "\n--- mkdir and rmdir {directory} in {share} ---\n{attempt}"
+                        ));
+                        written
+                    }
+                    false => false,
+                };
+
+                (readable, written)
+            }
+            _ => (false, false),
+        };
+
+        shares.push(SmbShare {
+            comment: columns.get(2).unwrap_or(&"").to_string(),
+            name: name.to_string(),
+            readable,
+            writable,
+            ..Default::default()
+        });
+    }
+
+    Ok((shares, output))
+}
+
+// Run smbclient with its errors kept, since a failed tree connect only shows up on stderr, and whether the command worked
+fn smbclient_command(args: &[&str]) -> (bool, String) {
+    match Command::new("smbclient").args(args).output() {
+        Ok(out) => {
+            let text = format!(
+                "{}{}",
+                String::from_utf8_lossy(&out.stdout),
+                String::from_utf8_lossy(&out.stderr)
+            );
+            (out.status.success() && !text.contains("NT_STATUS_"), text)
+        }
+        Err(e) => (false, e.to_string()),
+    }
+}
+
+// smbmap works out the permissions on each share itself
+fn smbmap(
+    ip_string: &str,
+    username: &str,
+    password: &str,
+) -> Result<(Vec<SmbShare>, String), Box<dyn Error>> {
+    let args = vec!["-H", ip_string, "-u", username, "-p", password];
+    let output = run_command_with_args("smbmap", args)?;
+
+    let shares = output
+        .lines()
+        .filter_map(|line| {
+            let line = line.trim();
+            let (access, readable, writable) = if line.contains("READ, WRITE") {
+                ("READ, WRITE", true, true)
+            } else if line.contains("READ ONLY") {
+                ("READ ONLY", true, false)
+            } else if line.contains("WRITE ONLY") {
+                ("WRITE ONLY", false, true)
+            } else if line.contains("NO ACCESS") {
+                ("NO ACCESS", false, false)
+            } else {
+                return None;
+            };
+            let (name, comment) = line.split_once(access)?;
+            Some(SmbShare {
+                comment: comment.trim().to_string(),
+                name: name.trim().to_string(),
+                readable,
+                writable,
+                ..Default::default()
+            })
+        })
+        .collect();
+
+    Ok((shares, output))
+}
+
+// enum4linux-ng tests mapping and listing each share it finds
+fn enum4linux_ng(
+    ip_string: &str,
+    username: &str,
+    password: &str,
+) -> Result<(Vec<SmbShare>, String), Box<dyn Error>> {
+    let args = vec!["-S", "-u", username, "-p", password, ip_string];
+    let output = run_command_with_args("enum4linux-ng", args)?;
+
+    let mut shares: Vec<SmbShare> = vec![];
+    for line in output.lines().map(str::trim) {
+        if let Some(name) = line.strip_prefix("[*] Testing share ") {
+            shares.push(SmbShare {
+                name: name.trim().to_string(),
+                ..Default::default()
+            });
+        } else if let (Some(share), Some((_, listing))) =
+            (shares.last_mut(), line.split_once("Listing: "))
+        {
+            share.readable = listing.starts_with("OK");
+        }
+    }
+
+    Ok((shares, output))
+}
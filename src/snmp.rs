@@ -0,0 +1,239 @@
+use std::net::{IpAddr, SocketAddr, UdpSocket};
+use std::time::Duration;
+
+// Communities tried when the user doesn't provide their own
+pub const DEFAULT_COMMUNITIES: &str = "public,private,community,manager";
+
+// sysDescr.0, which every SNMP agent answers
+const SYS_DESCR: [u32; 9] = [1, 3, 6, 1, 2, 1, 1, 1, 0];
+
+// Subtrees walked once a community works: (description, OID)
+pub const WALKS: [(&str, &str); 9] = [
+    ("System", "1.3.6.1.2.1.1"),
+    ("Process names", "1.3.6.1.2.1.25.4.2.1.2"),
+    ("Process paths", "1.3.6.1.2.1.25.4.2.1.4"),
+    ("Process parameters", "1.3.6.1.2.1.25.4.2.1.5"),
+    ("Installed software", "1.3.6.1.2.1.25.6.3.1.2"),
+    ("Windows users", "1.3.6.1.4.1.77.1.2.25"),
+    ("Listening TCP ports", "1.3.6.1.2.1.6.13.1.3"),
+    ("IP addresses", "1.3.6.1.2.1.4.20.1.1"),
+    ("NET-SNMP extend output", "1.3.6.1.4.1.8072.1.3.2"),
+];
+
+const PROCESS_NAMES: &str = ".1.3.6.1.2.1.25.4.2.1.2.";
+const PROCESS_PARAMETERS: &str = ".1.3.6.1.2.1.25.4.2.1.5.";
+const EXTEND_OUTPUT: &str = ".1.3.6.1.4.1.8072.1.3.2.";
+const WINDOWS_USERS: &str = ".1.3.6.1.4.1.77.1.2.25.";
+
+// Option and setting names in process arguments and extend output that hint at credentials, matched as whole words of
+// the name or its ending so --keyboard and monkey don't count
+const SECRETS: [&str; 9] = [
+    "apikey", "cred", "creds", "pass", "passwd", "password", "pwd", "secret", "token",
+];
+
+// Try each community with an SNMPv2c and then SNMPv1 GET of sysDescr, returning the first that works and what it said
+pub fn find_community(
+    ip_address: IpAddr,
+    communities: &[String],
+    timeout: Duration,
+) -> Option<(String, String, String)> {
+    for community in communities {
+        for (version, name) in [(1, "2c"), (0, "1")] {
+            if let Some(descr) = get(ip_address, community, version, &SYS_DESCR, timeout) {
+                return Some((community.clone(), name.to_string(), descr));
+            }
+        }
+    }
+    None
+}
+
+// Send a single GET and return the value of the first varbind as text
+pub fn get(
+    ip_address: IpAddr,
+    community: &str,
+    version: u8,
+    oid: &[u32],
+    timeout: Duration,
+) -> Option<String> {
+    let bind = match ip_address {
+        IpAddr::V4(_) => "0.0.0.0:0",
+        IpAddr::V6(_) => "[::]:0",
+    };
+    let socket = UdpSocket::bind(bind).ok()?;
+    socket.set_read_timeout(Some(timeout)).ok()?;
+    socket
+        .send_to(
+            &get_request(community, version, oid),
+            SocketAddr::new(ip_address, 161),
+        )
+        .ok()?;
+
+    let mut buffer = [0u8; 4096];
+    let size = socket.recv(&mut buffer).ok()?;
+    parse_response(&buffer[..size])
+}
+
+// Build an SNMP GetRequest message
+fn get_request(community: &str, version: u8, oid: &[u32]) -> Vec<u8> {
+    let varbind = tlv(
+        0x30,
+        &[tlv(0x06, &encode_oid(oid)), vec![0x05, 0x00]].concat(),
+    );
+    let pdu = tlv(
+        0xa0,
+        &[
+            tlv(0x02, &[0x13, 0x37]),
+            tlv(0x02, &[0x00]),
+            tlv(0x02, &[0x00]),
+            tlv(0x30, &varbind),
+        ]
+        .concat(),
+    );
+    tlv(
+        0x30,
+        &[tlv(0x02, &[version]), tlv(0x04, community.as_bytes()), pdu].concat(),
+    )
+}
+
+// Pull the value out of a GetResponse, ignoring responses that report an error
+fn parse_response(response: &[u8]) -> Option<String> {
+    let (_, message, _) = read_tlv(response)?;
+    let (_, _, rest) = read_tlv(message)?; // version
+    let (_, _, rest) = read_tlv(rest)?; // community
+    let (tag, pdu, _) = read_tlv(rest)?;
+    if tag != 0xa2 {
+        return None;
+    }
+    let (_, _, rest) = read_tlv(pdu)?; // request id
+    let (_, error_status, rest) = read_tlv(rest)?;
+    if error_status.iter().any(|&b| b != 0) {
+        return None;
+    }
+    let (_, _, rest) = read_tlv(rest)?; // error index
+    let (_, varbinds, _) = read_tlv(rest)?;
+    let (_, varbind, _) = read_tlv(varbinds)?;
+    let (_, _, rest) = read_tlv(varbind)?; // oid
+    let (tag, value, _) = read_tlv(rest)?;
+    // noSuchObject, noSuchInstance and endOfMibView mean the agent answered without a value
+    if (0x80..=0x82).contains(&tag) {
+        return None;
+    }
+    Some(String::from_utf8_lossy(value).to_string())
+}
+
+// Encode a tag, length and value
+fn tlv(tag: u8, value: &[u8]) -> Vec<u8> {
+    let mut output = vec![tag];
+    match value.len() {
+        len @ 0..=0x7f => output.push(len as u8),
+        len @ 0x80..=0xff => output.extend([0x81, len as u8]),
+        len => output.extend([0x82, (len >> 8) as u8, len as u8]),
+    }
+    output.extend_from_slice(value);
+    output
+}
+
+// Decode a tag, length and value, returning the remaining bytes too
+fn read_tlv(input: &[u8]) -> Option<(u8, &[u8], &[u8])> {
+    let tag = *input.first()?;
+    let first = *input.get(1)? as usize;
+    let (len, start) = match first {
+        0..=0x7f => (first, 2),
+        0x81 => (*input.get(2)? as usize, 3),
+        0x82 => (
+            ((*input.get(2)? as usize) << 8) | *input.get(3)? as usize,
+            4,
+        ),
+        _ => return None,
+    };
+    let value = input.get(start..start + len)?;
+    Some((tag, value, &input[start + len..]))
+}
+
+// Encode an OID, with the first two arcs packed together and the rest in base 128
+fn encode_oid(oid: &[u32]) -> Vec<u8> {
+    let mut output = vec![(oid[0] * 40 + oid[1]) as u8];
+    for &arc in &oid[2..] {
+        let mut bytes = vec![(arc & 0x7f) as u8];
+        let mut arc = arc >> 7;
+        while arc > 0 {
+            bytes.push((arc & 0x7f) as u8 | 0x80);
+            arc >>= 7;
+        }
+        output.extend(bytes.iter().rev());
+    }
+    output
+}
+
+// Pull the parts of a walk worth reading first out of snmpwalk's numeric output: process command lines, extend output and users
+pub fn interesting(walk: &str) -> Vec<(bool, String)> {
+    let values: Vec<(&str, &str)> = walk
+        .lines()
+        .filter_map(|line| line.split_once(" = "))
+        .map(|(oid, value)| (oid.trim(), strip_type(value)))
+        .collect();
+
+    let mut found = vec![];
+    for (oid, parameters) in &values {
+        if let Some(index) = oid.strip_prefix(PROCESS_PARAMETERS) {
+            if parameters.is_empty() {
+                continue;
+            }
+            let name = values
+                .iter()
+                .find(|(oid, _)| oid.strip_prefix(PROCESS_NAMES) == Some(index))
+                .map(|(_, name)| *name)
+                .unwrap_or("?");
+            found.push((
+                has_secret(parameters),
+                format!("process {name} {parameters}"),
+            ));
+        } else if oid.starts_with(EXTEND_OUTPUT) && !parameters.is_empty() {
+            found.push((
+                has_secret(parameters),
+                format!("extend output: {parameters}"),
+            ));
+        } else if oid.starts_with(WINDOWS_USERS) {
+            found.push((false, format!("user: {parameters}")));
+        }
+    }
+    found
+}
+
+// Drop the "STRING: " style type prefix and quotes snmpwalk puts on values
+fn strip_type(value: &str) -> &str {
+    let value = match value.split_once(": ") {
+        Some((kind, rest)) if kind.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') => rest,
+        _ => value,
+    };
+    value.trim().trim_matches('"')
+}
+
+// Whether an argument looks like it carries a credential: --password, --db-pass=x, apikey=x, Password: x, or a value
+// run straight into a short option the way mysql -pSecret takes one
+fn has_secret(value: &str) -> bool {
+    value.to_lowercase().split_whitespace().any(|argument| {
+        if argument.len() > 2
+            && argument.starts_with("-p")
+            && !argument.starts_with("--")
+            && !argument.contains('=')
+        {
+            return true;
+        }
+        let (name, has_value) = match argument.split_once(['=', ':']) {
+            Some((name, _)) => (name, true),
+            None => (argument, false),
+        };
+        // A bare word or path isn't naming a setting
+        if !has_value && !name.starts_with('-') {
+            return false;
+        }
+        let name = name.trim_start_matches('-');
+        let joined = name.replace(['-', '_', '.'], "");
+        name.split(['-', '_', '.'])
+            .any(|word| SECRETS.contains(&word))
+            || SECRETS
+                .iter()
+                .any(|secret| secret.len() > 4 && joined.ends_with(secret))
+    })
+}
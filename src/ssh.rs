@@ -0,0 +1,191 @@
+use crate::error::RecoverableDiscoveryError;
+use std::error::Error;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{IpAddr, SocketAddr, TcpStream};
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+// The name lists a server sends in its KEXINIT, in the order it sends them
+const NAME_LISTS: [&str; 10] = [
+    "kex",
+    "host key",
+    "cipher (client to server)",
+    "cipher (server to client)",
+    "mac (client to server)",
+    "mac (server to client)",
+    "compression (client to server)",
+    "compression (server to client)",
+    "language (client to server)",
+    "language (server to client)",
+];
+
+// Algorithms that are broken or deprecated, matched as prefixes
+const WEAK_ALGORITHMS: [&str; 14] = [
+    "diffie-hellman-group1-sha1",
+    "diffie-hellman-group14-sha1",
+    "diffie-hellman-group-exchange-sha1",
+    "gss-",
+    "ssh-dss",
+    "3des-cbc",
+    "aes128-cbc",
+    "aes192-cbc",
+    "aes256-cbc",
+    "blowfish-cbc",
+    "arcfour",
+    "hmac-md5",
+    "hmac-sha1-96",
+    "umac-64",
+];
+
+// KEXINIT packets are small, anything bigger than this isn't one
+const MAX_PACKET_SIZE: usize = 35000;
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SshAlgorithms {
+    banner: String,
+    name_lists: Vec<(String, Vec<String>)>,
+}
+
+impl SshAlgorithms {
+    pub fn banner(&self) -> &String {
+        &self.banner
+    }
+
+    pub fn name_lists(&self) -> &Vec<(String, Vec<String>)> {
+        &self.name_lists
+    }
+
+    // Every weak algorithm the server offers, without repeats for the two directions
+    pub fn weak(&self) -> Vec<String> {
+        let mut weak: Vec<String> = vec![];
+        for algorithm in self.name_lists.iter().flat_map(|(_, list)| list) {
+            if WEAK_ALGORITHMS.iter().any(|w| algorithm.starts_with(w)) && !weak.contains(algorithm)
+            {
+                weak.push(algorithm.clone());
+            }
+        }
+        weak
+    }
+}
+
+// Read the server's banner, then the algorithms it offers in the KEXINIT that it sends before any encryption starts
+pub fn algorithms(
+    ip_address: IpAddr,
+    port: u16,
+    timeout: Duration,
+) -> Result<SshAlgorithms, Box<dyn Error>> {
+    let mut stream = TcpStream::connect_timeout(&SocketAddr::new(ip_address, port), timeout)?;
+    stream.set_read_timeout(Some(timeout * 5))?;
+    stream.set_write_timeout(Some(timeout * 5))?;
+    stream.write_all(b"SSH-2.0-imd\r\n")?;
+
+    // Servers may send other lines before the banner
+    let mut reader = BufReader::new(stream);
+    let mut banner = String::new();
+    while !banner.starts_with("SSH-") {
+        banner.clear();
+        if reader.read_line(&mut banner)? == 0 {
+            return Err(RecoverableDiscoveryError::Response.into());
+        }
+    }
+
+    let mut length = [0u8; 4];
+    reader.read_exact(&mut length)?;
+    let length = u32::from_be_bytes(length) as usize;
+    if !(6..=MAX_PACKET_SIZE).contains(&length) {
+        return Err(RecoverableDiscoveryError::Response.into());
+    }
+    let mut packet = vec![0u8; length];
+    reader.read_exact(&mut packet)?;
+
+    Ok(SshAlgorithms {
+        banner: banner.trim().to_string(),
+        name_lists: parse_kexinit(&packet).ok_or(RecoverableDiscoveryError::Response)?,
+    })
+}
+
+// A KEXINIT payload is a message number, a 16 byte cookie and then ten length-prefixed, comma separated lists
+fn parse_kexinit(packet: &[u8]) -> Option<Vec<(String, Vec<String>)>> {
+    let padding = *packet.first()? as usize;
+    let payload = packet.get(1..packet.len().checked_sub(padding)?)?;
+    if *payload.first()? != 20 {
+        return None;
+    }
+
+    let mut rest = payload.get(17..)?;
+    let mut name_lists = vec![];
+    for name in NAME_LISTS {
+        let length = u32::from_be_bytes(rest.get(..4)?.try_into().ok()?) as usize;
+        let list = String::from_utf8_lossy(rest.get(4..4 + length)?);
+        let list = list
+            .split(',')
+            .filter(|a| !a.is_empty())
+            .map(str::to_string)
+            .collect();
+        name_lists.push((name.to_string(), list));
+        rest = &rest[4 + length..];
+    }
+    Some(name_lists)
+}
+
+// Ask the server which authentication methods it accepts by offering none at all
+pub fn auth_methods(ip_string: &str, port: u16) -> Result<Vec<String>, Box<dyn Error>> {
+    let port = port.to_string();
+    let out = Command::new("ssh")
+        .args(["-o", "PreferredAuthentications=none", "-o", "BatchMode=yes"])
+        .args([
+            "-o",
+            "StrictHostKeyChecking=no",
+            "-o",
+            "UserKnownHostsFile=/dev/null",
+        ])
+        .args(["-o", "ConnectTimeout=10", "-p", &port])
+        .arg(format!("imd@{ip_string}"))
+        .output()?;
+    let stderr = String::from_utf8_lossy(&out.stderr);
+
+    // The refusal looks like "imd@10.10.10.10: Permission denied (publickey,password)."
+    let methods = stderr
+        .lines()
+        .find_map(|line| line.split_once("Permission denied ("))
+        .and_then(|(_, methods)| methods.split_once(')'))
+        .ok_or(RecoverableDiscoveryError::Response)?
+        .0;
+    Ok(methods.split(',').map(str::to_string).collect())
+}
+
+// Collect the server's host keys and hand them to ssh-keygen for their fingerprints
+pub fn host_key_fingerprints(ip_string: &str, port: u16) -> Result<String, Box<dyn Error>> {
+    let port = port.to_string();
+    let keys = Command::new("ssh-keyscan")
+        .args(["-p", &port, "-t", "rsa,ecdsa,ed25519,dsa", ip_string])
+        .output()?;
+
+    let mut keygen = Command::new("ssh-keygen")
+        .args(["-l", "-f", "-"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()?;
+    keygen
+        .stdin
+        .take()
+        .ok_or(RecoverableDiscoveryError::Response)?
+        .write_all(&keys.stdout)?;
+    let out = keygen.wait_with_output()?;
+
+    Ok(String::from_utf8_lossy(&out.stdout).to_string())
+}
+
+// OpenSSH through 7.7 answers differently for real and made up usernames (CVE-2018-15473)
+pub fn user_enumeration(banner: &str) -> Option<&'static str> {
+    let version = banner.split("OpenSSH_").nth(1)?;
+    let mut numbers = version
+        .split(|c: char| !c.is_ascii_digit())
+        .filter_map(|n| n.parse::<u32>().ok());
+    let (major, minor) = (numbers.next()?, numbers.next()?);
+    match (major, minor) < (7, 8) {
+        true => Some("CVE-2018-15473"),
+        false => None,
+    }
+}
@@ -0,0 +1,165 @@
+use crate::http::{HttpClient, HttpResponse};
+use std::error::Error;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+// Subdomains tried in front of each of the target's domains, unless the user picks their own list
+pub const DEFAULT_WORDLIST: &str =
+    "/usr/share/wordlists/seclists/Discovery/DNS/subdomains-top1million-5000.txt";
+
+// Requests in flight at once, kept low so the web server isn't flattened
+const THREADS: usize = 20;
+
+// Names that are never real, to learn what the server does with a virtual host it doesn't know
+const BASELINE_NAMES: [&str; 2] = ["imd-baseline-7a3e", "imd-baseline-nonexistent-91c2"];
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct VhostHit {
+    hostname: String,
+    size: usize,
+    status: u16,
+}
+
+impl VhostHit {
+    pub fn hostname(&self) -> &String {
+        &self.hostname
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn status(&self) -> u16 {
+        self.status
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct VhostReport {
+    baseline_size: Option<usize>,
+    baseline_status: u16,
+    hits: Vec<VhostHit>,
+    tried: usize,
+}
+
+impl VhostReport {
+    // The size unknown virtual hosts come back with, or None if it changes from one request to the next
+    pub fn baseline_size(&self) -> Option<usize> {
+        self.baseline_size
+    }
+
+    pub fn baseline_status(&self) -> u16 {
+        self.baseline_status
+    }
+
+    pub fn hits(&self) -> &Vec<VhostHit> {
+        &self.hits
+    }
+
+    pub fn tried(&self) -> usize {
+        self.tried
+    }
+}
+
+// Read a subdomain wordlist, skipping blank lines and comments
+pub fn read_wordlist(path: &str) -> Vec<String> {
+    std::fs::read_to_string(path)
+        .unwrap_or_default()
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_string)
+        .collect()
+}
+
+// The hostnames worth fuzzing under, leaving out any that are already under another one
+pub fn fuzz_domains(hostnames: &[String]) -> Vec<String> {
+    let mut domains: Vec<String> = vec![];
+    for hostname in hostnames {
+        let covered = hostnames
+            .iter()
+            .any(|other| hostname.ends_with(&format!(".{other}")));
+        if !covered && !domains.contains(hostname) {
+            domains.push(hostname.clone());
+        }
+    }
+    domains
+}
+
+// Ask for every word as a subdomain of the domain, keeping the ones that don't look like the server's unknown host response
+pub fn fuzz(
+    client: &HttpClient,
+    domain: &str,
+    words: &[String],
+) -> Result<VhostReport, Box<dyn Error>> {
+    let mut baselines = vec![];
+    for name in BASELINE_NAMES {
+        let hostname = format!("{name}.{domain}");
+        let response = client.get(&hostname, "/")?;
+        baselines.push((response.status(), normalized_size(&response, &hostname)));
+    }
+    let baseline_status = baselines[0].0;
+    // Pages with something like a timestamp in them change size every time, so only the status can be compared
+    let baseline_size = match baselines[0] == baselines[1] {
+        true => Some(baselines[0].1),
+        false => None,
+    };
+
+    let tried = words.len();
+    let words = Arc::new(Mutex::new(words.to_vec()));
+    let hits = Arc::new(Mutex::new(vec![]));
+    let mut threads = vec![];
+    for _ in 0..THREADS {
+        threads.push(thread::spawn({
+            let client = client.clone();
+            let domain = domain.to_string();
+            let hits = hits.clone();
+            let words = words.clone();
+            move || loop {
+                let word = match words.lock().unwrap().pop() {
+                    Some(word) => word,
+                    None => break,
+                };
+                let hostname = format!("{word}.{domain}");
+                let response = match client.get(&hostname, "/") {
+                    Ok(response) => response,
+                    Err(_) => continue,
+                };
+                let status = response.status();
+                let size = normalized_size(&response, &hostname);
+                // Servers answer names they don't like with 400 or 421, which says nothing about a real virtual host
+                let different = status != baseline_status
+                    || baseline_size.is_some_and(|baseline| baseline != size);
+                if different && status != 400 && status != 421 {
+                    hits.lock().unwrap().push(VhostHit {
+                        hostname,
+                        size,
+                        status,
+                    });
+                }
+            }
+        }));
+    }
+    for thread in threads {
+        thread.join().unwrap();
+    }
+
+    let mut hits = hits.lock().unwrap().clone();
+    hits.sort_by(|a, b| a.hostname.cmp(&b.hostname));
+    Ok(VhostReport {
+        baseline_size,
+        baseline_status,
+        hits,
+        tried,
+    })
+}
+
+// The size of a response with the requested hostname taken out, since many servers echo it back in redirects and error pages
+fn normalized_size(response: &HttpResponse, hostname: &str) -> usize {
+    let text = response.text().replace(hostname, "");
+    let location = response
+        .header("Location")
+        .unwrap_or_default()
+        .replace(hostname, "");
+    text.len() + location.len()
+}
@@ -0,0 +1,245 @@
+use crate::content::{scan_file, TempFile};
+use crate::error::PanicDiscoveryError;
+use crate::http::{json_objects, json_value};
+use crate::run_command_with_args;
+use std::error::Error;
+use std::fs;
+
+// nuclei has no time limit of its own, so it is stopped after this long
+const NUCLEI_TIME_LIMIT: &str = "10m";
+
+// nikto findings that turn up on nearly every site and rarely lead anywhere
+const NIKTO_NOISE: [&str; 22] = [
+    "x-frame-options",
+    "clickjacking",
+    "x-content-type-options",
+    "x-xss-protection",
+    "strict-transport-security",
+    "content-security-policy",
+    "referrer-policy",
+    "permissions-policy",
+    "suggested security header missing",
+    "uncommon header",
+    "etag",
+    "inode",
+    "allowed http methods",
+    "no cgi directories found",
+    "retrieved x-powered-by header",
+    "without the httponly flag",
+    "without the secure flag",
+    "apache default file",
+    "/icons/readme",
+    "multiple index files found",
+    "junk http methods",
+    "root page / redirects to",
+];
+
+// Tool (or tools) used to look for web vulnerabilities
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum VulnScanner {
+    Both,
+    #[default]
+    Nikto,
+    Nuclei,
+}
+
+impl VulnScanner {
+    // Parse a VulnScanner from the command line arguments
+    pub fn new(input: &str) -> Result<VulnScanner, PanicDiscoveryError> {
+        match input {
+            "both" => Ok(VulnScanner::Both),
+            "nikto" => Ok(VulnScanner::Nikto),
+            "nuclei" => Ok(VulnScanner::Nuclei),
+            _ => Err(PanicDiscoveryError::InvalidVulnScanner),
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        match self {
+            VulnScanner::Both => "both",
+            VulnScanner::Nikto => "nikto",
+            VulnScanner::Nuclei => "nuclei",
+        }
+    }
+
+    // The individual tools to run
+    pub fn tools(&self) -> Vec<&'static str> {
+        match self {
+            VulnScanner::Both => vec!["nikto", "nuclei"],
+            VulnScanner::Nikto => vec!["nikto"],
+            VulnScanner::Nuclei => vec!["nuclei"],
+        }
+    }
+}
+
+// A finding from any of the vuln scanners, in a common shape
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct VulnFinding {
+    evidence: String,
+    id: String,
+    severity: String,
+    source: String,
+    url: String,
+}
+
+impl VulnFinding {
+    pub fn evidence(&self) -> &String {
+        &self.evidence
+    }
+
+    // The template or check that found it, E.G. a nuclei template ID or a nikto OSVDB number
+    pub fn id(&self) -> &String {
+        &self.id
+    }
+
+    pub fn severity(&self) -> &String {
+        &self.severity
+    }
+
+    pub fn source(&self) -> &String {
+        &self.source
+    }
+
+    pub fn url(&self) -> &String {
+        &self.url
+    }
+
+    // Whether the finding is worth flagging in the summary
+    pub fn serious(&self) -> bool {
+        matches!(self.severity.as_str(), "critical" | "high")
+    }
+
+    // nikto findings that aren't noise, and nuclei findings from medium severity up
+    pub fn worth_summarizing(&self) -> bool {
+        match self.source.as_str() {
+            "nikto" => !self.low_priority(),
+            _ => self.serious() || self.severity == "medium",
+        }
+    }
+
+    // nikto findings that are almost always noise, like missing security headers
+    pub fn low_priority(&self) -> bool {
+        let evidence = self.evidence.to_lowercase();
+        self.source == "nikto" && NIKTO_NOISE.iter().any(|noise| evidence.contains(noise))
+    }
+
+    // One line of web_vulns, the same whichever scanner found it
+    pub fn line(&self) -> String {
+        format!(
+            "[{}] {} {} {}: {}",
+            self.severity, self.source, self.id, self.url, self.evidence
+        )
+    }
+}
+
+// Run nikto against the URL, returning its findings (from its JSON report, kept in the directory while it runs, when it
+// writes one) and its raw output
+pub fn nikto(url: &str, directory: &str) -> Result<(Vec<VulnFinding>, String), Box<dyn Error>> {
+    let json_output = TempFile::new(&scan_file(directory, "nikto", url, "json"));
+    let args = vec![
+        "-host",
+        url,
+        "-maxtime",
+        "60",
+        "-Format",
+        "json",
+        "-output",
+        json_output.path(),
+    ];
+    let output = run_command_with_args("nikto", args)?;
+    let json = fs::read_to_string(json_output.path()).unwrap_or_default();
+
+    let mut findings = parse_nikto_json(&json, url);
+    if findings.is_empty() {
+        findings = output
+            .lines()
+            .filter_map(|line| parse_nikto_line(line, url))
+            .collect();
+    }
+    Ok((findings, output))
+}
+
+// nikto's JSON report has a "vulnerabilities" array of objects with an id, an OSVDB number, a URL path and a message
+fn parse_nikto_json(json: &str, url: &str) -> Vec<VulnFinding> {
+    let vulnerabilities = match json.split_once("\"vulnerabilities\"") {
+        Some((_, rest)) => rest,
+        None => return vec![],
+    };
+    let base = url.trim_end_matches('/');
+    json_objects(vulnerabilities)
+        .into_iter()
+        .filter_map(|object| {
+            let id = match json_value(object, "OSVDB").filter(|osvdb| osvdb != "0") {
+                Some(osvdb) => format!("OSVDB-{osvdb}"),
+                None => json_value(object, "id")?,
+            };
+            Some(VulnFinding {
+                evidence: json_value(object, "msg")?,
+                id,
+                severity: "info".to_string(),
+                source: "nikto".to_string(),
+                url: format!("{base}{}", json_value(object, "url").unwrap_or_default()),
+            })
+        })
+        .collect()
+}
+
+// nikto prints findings like "+ /admin/: Directory indexing found." or "+ OSVDB-3092: /admin/: This might be interesting"
+fn parse_nikto_line(line: &str, url: &str) -> Option<VulnFinding> {
+    let line = line.strip_prefix("+ ")?;
+    let (id, line) = match line.split_once(": ") {
+        Some((id, rest)) if id.starts_with("OSVDB-") || id.starts_with("CVE-") => (id, rest),
+        _ => ("nikto", line),
+    };
+    let (path, message) = line.split_once(": ")?;
+    if !path.starts_with('/') {
+        return None;
+    }
+    Some(VulnFinding {
+        evidence: message.trim().to_string(),
+        id: id.to_string(),
+        severity: "info".to_string(),
+        source: "nikto".to_string(),
+        url: format!("{}{path}", url.trim_end_matches('/')),
+    })
+}
+
+// Run nuclei against the URL, with a local templates directory if one was given so it works offline
+pub fn nuclei(
+    url: &str,
+    templates: Option<&str>,
+) -> Result<(Vec<VulnFinding>, String), Box<dyn Error>> {
+    let mut args = vec![
+        NUCLEI_TIME_LIMIT,
+        "nuclei",
+        "-u",
+        url,
+        "-jsonl",
+        "-silent",
+        "-duc",
+    ];
+    if let Some(templates) = templates {
+        args.extend(["-t", templates]);
+    }
+    let output = run_command_with_args("timeout", args)?;
+    let findings = output.lines().filter_map(parse_nuclei_line).collect();
+    Ok((findings, output))
+}
+
+// nuclei's -jsonl prints one object per finding
+fn parse_nuclei_line(line: &str) -> Option<VulnFinding> {
+    let id = json_value(line, "template-id")?;
+    let name = json_value(line, "name").unwrap_or_default();
+    let evidence = json_value(line, "extracted-results")
+        .filter(|extracted| !extracted.is_empty())
+        .or_else(|| json_value(line, "matcher-name"))
+        .map(|matched| format!("{name} ({matched})"))
+        .unwrap_or(name);
+    Some(VulnFinding {
+        evidence,
+        id,
+        severity: json_value(line, "severity").unwrap_or("unknown".to_string()),
+        source: "nuclei".to_string(),
+        url: json_value(line, "matched-at").or_else(|| json_value(line, "host"))?,
+    })
+}
@@ -0,0 +1,619 @@
+use crate::error::PanicDiscoveryError;
+use crate::http::{HttpClient, HttpResponse};
+use crate::run_command_with_args;
+use std::error::Error;
+
+// Follow-ups run when their technology turns up, unless the user picks their own
+pub const DEFAULT_FOLLOW_UPS: &str = "droopescan,gitlab,jenkins,tomcat-manager,wpscan";
+
+// Shodan style favicon hashes for the technologies imd recognizes
+const FAVICON_HASHES: [(i32, Technology); 3] = [
+    (81586312, Technology::Jenkins),
+    (1278323681, Technology::Gitlab),
+    (-297069493, Technology::Tomcat),
+];
+
+// Paths that only exist on one technology, and what in the response gives it away
+const KNOWN_PATHS: [(&str, Technology, &str); 7] = [
+    ("/wp-login.php", Technology::Wordpress, "wp-submit"),
+    ("/administrator/", Technology::Joomla, "joomla"),
+    ("/CHANGELOG.txt", Technology::Drupal, "drupal"),
+    ("/core/CHANGELOG.txt", Technology::Drupal, "drupal"),
+    ("/manager/html", Technology::Tomcat, "tomcat"),
+    ("/login", Technology::Jenkins, "jenkins"),
+    ("/users/sign_in", Technology::Gitlab, "gitlab"),
+];
+
+// Credentials Tomcat ships with in its examples, or that people commonly set
+const TOMCAT_CREDENTIALS: [(&str, &str); 8] = [
+    ("tomcat", "tomcat"),
+    ("admin", "admin"),
+    ("tomcat", "s3cret"),
+    ("admin", "tomcat"),
+    ("tomcat", "admin"),
+    ("admin", ""),
+    ("both", "tomcat"),
+    ("role1", "role1"),
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Technology {
+    Drupal,
+    Gitlab,
+    Jenkins,
+    Joomla,
+    Tomcat,
+    Wordpress,
+}
+
+impl Technology {
+    pub fn as_str(&self) -> &str {
+        match self {
+            Technology::Drupal => "Drupal",
+            Technology::Gitlab => "GitLab",
+            Technology::Jenkins => "Jenkins",
+            Technology::Joomla => "Joomla",
+            Technology::Tomcat => "Tomcat",
+            Technology::Wordpress => "WordPress",
+        }
+    }
+
+    // Find a technology named somewhere in some text, such as a generator tag or whatweb's output
+    fn from_text(text: &str) -> Vec<Technology> {
+        let text = text.to_lowercase();
+        [
+            Technology::Drupal,
+            Technology::Gitlab,
+            Technology::Jenkins,
+            Technology::Joomla,
+            Technology::Tomcat,
+            Technology::Wordpress,
+        ]
+        .into_iter()
+        .filter(|technology| text.contains(&technology.as_str().to_lowercase()))
+        .collect()
+    }
+}
+
+// Tools and checks worth running once a technology has been recognized
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FollowUp {
+    Droopescan,
+    Gitlab,
+    Jenkins,
+    TomcatManager,
+    Wpscan,
+}
+
+impl FollowUp {
+    pub fn as_str(&self) -> &str {
+        match self {
+            FollowUp::Droopescan => "droopescan",
+            FollowUp::Gitlab => "gitlab",
+            FollowUp::Jenkins => "jenkins",
+            FollowUp::TomcatManager => "tomcat-manager",
+            FollowUp::Wpscan => "wpscan",
+        }
+    }
+
+    // Whether the follow-up has anything to say about the technology
+    pub fn applies_to(&self, technology: Technology) -> bool {
+        matches!(
+            (self, technology),
+            (FollowUp::Droopescan, Technology::Drupal)
+                | (FollowUp::Droopescan, Technology::Joomla)
+                | (FollowUp::Gitlab, Technology::Gitlab)
+                | (FollowUp::Jenkins, Technology::Jenkins)
+                | (FollowUp::TomcatManager, Technology::Tomcat)
+                | (FollowUp::Wpscan, Technology::Wordpress)
+        )
+    }
+
+    pub fn run(
+        &self,
+        client: &HttpClient,
+        host: &str,
+        technology: Technology,
+    ) -> Result<FollowUpReport, Box<dyn Error>> {
+        let url = client.url(host, "/");
+        match self {
+            FollowUp::Droopescan => {
+                let cms = technology.as_str().to_lowercase();
+                let output = run_command_with_args("droopescan", vec!["scan", &cms, "-u", &url])?;
+                Ok(FollowUpReport::from_output(output))
+            }
+            FollowUp::Gitlab => gitlab(client, host),
+            FollowUp::Jenkins => jenkins(client, host),
+            FollowUp::TomcatManager => tomcat_manager(client, host),
+            FollowUp::Wpscan => {
+                let args = vec![
+                    "--url",
+                    &url,
+                    "--no-banner",
+                    "--disable-tls-checks",
+                    "--enumerate",
+                    "vp,vt,u",
+                ];
+                let output = run_command_with_args("wpscan", args)?;
+                let mut report = FollowUpReport::from_output(output);
+                report.usernames = wpscan_users(&report.output);
+                Ok(report)
+            }
+        }
+    }
+
+    // The external tool the follow-up needs, if any
+    pub fn command(&self) -> Option<&str> {
+        match self {
+            FollowUp::Droopescan => Some("droopescan"),
+            FollowUp::Wpscan => Some("wpscan"),
+            _ => None,
+        }
+    }
+}
+
+// Parse the follow-ups from the command line arguments, where none turns them all off
+pub fn parse_follow_ups(input: &str) -> Result<Vec<FollowUp>, PanicDiscoveryError> {
+    if input == "none" {
+        return Ok(vec![]);
+    }
+    input
+        .split(',')
+        .map(|follow_up| match follow_up.trim() {
+            "droopescan" => Ok(FollowUp::Droopescan),
+            "gitlab" => Ok(FollowUp::Gitlab),
+            "jenkins" => Ok(FollowUp::Jenkins),
+            "tomcat-manager" => Ok(FollowUp::TomcatManager),
+            "wpscan" => Ok(FollowUp::Wpscan),
+            _ => Err(PanicDiscoveryError::InvalidFollowUp),
+        })
+        .collect()
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FollowUpReport {
+    findings: Vec<(bool, String)>,
+    output: String,
+    usernames: Vec<String>,
+}
+
+impl FollowUpReport {
+    fn from_output(output: String) -> FollowUpReport {
+        FollowUpReport {
+            output,
+            ..Default::default()
+        }
+    }
+
+    // Lines for the target's summary, flagged if they are a likely way in
+    pub fn findings(&self) -> &Vec<(bool, String)> {
+        &self.findings
+    }
+
+    pub fn output(&self) -> &String {
+        &self.output
+    }
+
+    pub fn usernames(&self) -> &Vec<String> {
+        &self.usernames
+    }
+
+    fn line(&mut self, line: String) {
+        self.output.push_str(&line);
+        self.output.push('\n');
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Fingerprint {
+    evidence: Vec<(Technology, String)>,
+    favicon_hash: Option<i32>,
+    generator: Option<String>,
+    powered_by: Option<String>,
+    server: Option<String>,
+}
+
+impl Fingerprint {
+    // Each technology recognized, along with what gave it away
+    pub fn evidence(&self) -> &Vec<(Technology, String)> {
+        &self.evidence
+    }
+
+    pub fn favicon_hash(&self) -> Option<i32> {
+        self.favicon_hash
+    }
+
+    pub fn generator(&self) -> Option<&String> {
+        self.generator.as_ref()
+    }
+
+    pub fn powered_by(&self) -> Option<&String> {
+        self.powered_by.as_ref()
+    }
+
+    pub fn server(&self) -> Option<&String> {
+        self.server.as_ref()
+    }
+
+    // The headers, generator and technologies in one line, for rules that key off any of them
+    pub fn signature(&self) -> String {
+        let mut parts: Vec<String> = [&self.server, &self.powered_by, &self.generator]
+            .into_iter()
+            .flatten()
+            .cloned()
+            .collect();
+        parts.extend(
+            self.technologies()
+                .iter()
+                .map(|technology| technology.as_str().to_string()),
+        );
+        parts.join(", ")
+    }
+
+    // Every technology recognized, without repeats
+    pub fn technologies(&self) -> Vec<Technology> {
+        let mut technologies: Vec<Technology> = vec![];
+        for (technology, _) in &self.evidence {
+            if !technologies.contains(technology) {
+                technologies.push(*technology);
+            }
+        }
+        technologies
+    }
+
+    // Add technologies named in whatweb's output, which knows far more than imd does
+    pub fn add_whatweb(&mut self, output: &str) {
+        for technology in Technology::from_text(output) {
+            self.add(technology, "whatweb".to_string());
+        }
+    }
+
+    fn add(&mut self, technology: Technology, evidence: String) {
+        self.evidence.push((technology, evidence));
+    }
+}
+
+// Look at the home page's headers and HTML, the favicon and a handful of telltale paths
+pub fn fingerprint(client: &HttpClient, host: &str) -> Result<Fingerprint, Box<dyn Error>> {
+    let home = client.get(host, "/")?;
+    let mut fingerprint = Fingerprint {
+        powered_by: home.header("X-Powered-By").map(str::to_string),
+        server: home.header("Server").map(str::to_string),
+        ..Default::default()
+    };
+
+    fingerprint_headers(&mut fingerprint, &home);
+
+    let body = home.text();
+    fingerprint.generator = meta_generator(&body);
+    if let Some(generator) = fingerprint.generator.clone() {
+        for technology in Technology::from_text(&generator) {
+            fingerprint.add(technology, format!("meta generator '{generator}'"));
+        }
+    }
+    let markers = [
+        ("/wp-content/", Technology::Wordpress),
+        ("/wp-includes/", Technology::Wordpress),
+        ("/sites/default/files", Technology::Drupal),
+        ("drupal-settings-json", Technology::Drupal),
+        ("/media/jui/", Technology::Joomla),
+        ("/media/system/js/", Technology::Joomla),
+        ("gl-page-title", Technology::Gitlab),
+        ("apache tomcat", Technology::Tomcat),
+    ];
+    let lower = body.to_lowercase();
+    for (marker, technology) in markers {
+        if lower.contains(marker) {
+            fingerprint.add(technology, format!("'{marker}' in the home page"));
+        }
+    }
+
+    if let Ok(favicon) = client.get(host, "/favicon.ico") {
+        if favicon.status() == 200 && !favicon.body().is_empty() {
+            let hash = favicon_hash(favicon.body());
+            fingerprint.favicon_hash = Some(hash);
+            for (known, technology) in FAVICON_HASHES {
+                if hash == known {
+                    fingerprint.add(technology, format!("favicon hash {hash}"));
+                }
+            }
+        }
+    }
+
+    for (path, technology, marker) in KNOWN_PATHS {
+        let response = match client.get(host, path) {
+            Ok(response) => response,
+            Err(_) => continue,
+        };
+        // Tomcat's manager gives itself away in the realm it asks for credentials in
+        let realm = response.header("WWW-Authenticate").unwrap_or_default();
+        let text = format!("{realm} {}", response.text()).to_lowercase();
+        if response.status() < 500 && response.status() != 404 && text.contains(marker) {
+            fingerprint.add(technology, format!("{path} ({})", response.status()));
+        }
+        fingerprint_headers(&mut fingerprint, &response);
+    }
+
+    Ok(fingerprint)
+}
+
+// Headers and cookies that name the technology behind them
+fn fingerprint_headers(fingerprint: &mut Fingerprint, response: &HttpResponse) {
+    for (name, value) in response.headers() {
+        let name = name.to_lowercase();
+        let lower = value.to_lowercase();
+        let technology = match name.as_str() {
+            "x-jenkins" => Some(Technology::Jenkins),
+            "x-gitlab-meta" => Some(Technology::Gitlab),
+            "x-drupal-cache" | "x-drupal-dynamic-cache" => Some(Technology::Drupal),
+            "x-generator" => Technology::from_text(value).into_iter().next(),
+            "server" if lower.contains("coyote") || lower.contains("tomcat") => {
+                Some(Technology::Tomcat)
+            }
+            "link" if lower.contains("wp-json") => Some(Technology::Wordpress),
+            "set-cookie" if lower.starts_with("wordpress_") || lower.starts_with("wp-") => {
+                Some(Technology::Wordpress)
+            }
+            "set-cookie" if lower.starts_with("_gitlab_session") => Some(Technology::Gitlab),
+            "set-cookie" if lower.starts_with("jsessionid.") => Some(Technology::Jenkins),
+            _ => None,
+        };
+        if let Some(technology) = technology {
+            let evidence = format!("{name} header");
+            if !fingerprint
+                .evidence
+                .contains(&(technology, evidence.clone()))
+            {
+                fingerprint.add(technology, evidence);
+            }
+        }
+    }
+}
+
+// The content of a <meta name="generator"> tag, which CMSes like to fill in with their name and version
+fn meta_generator(body: &str) -> Option<String> {
+    // ASCII lowercasing keeps every byte where it was, so offsets into it are offsets into the body
+    let lower = body.to_ascii_lowercase();
+    let mut rest = lower.as_str();
+    while let Some(start) = rest.find("<meta") {
+        let tag = &rest[start..];
+        let tag = &tag[..tag.find('>').unwrap_or(tag.len())];
+        if tag.contains("name=\"generator\"") || tag.contains("name='generator'") {
+            // Take the value from the original body so the capitalisation survives
+            let offset = lower.len() - rest.len() + start;
+            let original = &body[offset..offset + tag.len()];
+            let value = tag
+                .find("content=")
+                .map(|content| &original[content + 8..])
+                .and_then(|content| {
+                    let quote = content.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+                    content[1..].split(quote).next()
+                });
+            if let Some(value) = value {
+                return Some(value.to_string());
+            }
+        }
+        rest = &rest[start + 5..];
+    }
+    None
+}
+
+// The hash Shodan and friends use: MurmurHash3 of the favicon's base64, line breaks and all
+pub fn favicon_hash(favicon: &[u8]) -> i32 {
+    let encoded = base64(favicon);
+    let mut wrapped = String::new();
+    for line in encoded.as_bytes().chunks(76) {
+        wrapped.push_str(&String::from_utf8_lossy(line));
+        wrapped.push('\n');
+    }
+    murmur3(wrapped.as_bytes()) as i32
+}
+
+fn base64(input: &[u8]) -> String {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut output = String::new();
+    for chunk in input.chunks(3) {
+        let bytes = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let triple = u32::from_be_bytes([0, bytes[0], bytes[1], bytes[2]]);
+        for i in 0..4 {
+            if i <= chunk.len() {
+                output.push(ALPHABET[(triple >> (18 - i * 6)) as usize & 0x3f] as char);
+            } else {
+                output.push('=');
+            }
+        }
+    }
+    output
+}
+
+// 32 bit MurmurHash3 with a seed of zero
+fn murmur3(data: &[u8]) -> u32 {
+    const C1: u32 = 0xcc9e2d51;
+    const C2: u32 = 0x1b873593;
+    let mut hash: u32 = 0;
+
+    let mut blocks = data.chunks_exact(4);
+    for block in blocks.by_ref() {
+        let mut k = u32::from_le_bytes(block.try_into().unwrap());
+        k = k.wrapping_mul(C1).rotate_left(15).wrapping_mul(C2);
+        hash ^= k;
+        hash = hash
+            .rotate_left(13)
+            .wrapping_mul(5)
+            .wrapping_add(0xe6546b64);
+    }
+
+    let tail = blocks.remainder();
+    if !tail.is_empty() {
+        let mut k: u32 = 0;
+        for (i, byte) in tail.iter().enumerate() {
+            k |= (*byte as u32) << (i * 8);
+        }
+        hash ^= k.wrapping_mul(C1).rotate_left(15).wrapping_mul(C2);
+    }
+
+    hash ^= data.len() as u32;
+    hash ^= hash >> 16;
+    hash = hash.wrapping_mul(0x85ebca6b);
+    hash ^= hash >> 13;
+    hash = hash.wrapping_mul(0xc2b2ae35);
+    hash ^ (hash >> 16)
+}
+
+// wpscan lists the users it finds under "User(s) Identified", one "[+] name" line each
+fn wpscan_users(output: &str) -> Vec<String> {
+    output
+        .split_once("User(s) Identified")
+        .map(|(_, users)| {
+            users
+                .lines()
+                .filter_map(|line| line.strip_prefix("[+] "))
+                .map(|user| user.trim().to_string())
+                .filter(|user| !user.is_empty() && !user.contains(' '))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+// Find Tomcat's manager apps, and try the usual default credentials on any that ask for them
+fn tomcat_manager(client: &HttpClient, host: &str) -> Result<FollowUpReport, Box<dyn Error>> {
+    let mut report = FollowUpReport::default();
+    let port = client.port();
+
+    for path in ["/manager/html", "/host-manager/html", "/manager/text/list"] {
+        // One dropped connection shouldn't cost the rest of the paths
+        let response = match client.get(host, path) {
+            Ok(response) => response,
+            Err(e) => {
+                report.line(format!("{path}: no response ({e})"));
+                continue;
+            }
+        };
+        let status = response.status();
+        report.line(format!("{path}: {status}"));
+        match status {
+            200 => {
+                let finding = format!("Tomcat {path} on port {port} is open without credentials");
+                report.findings.push((true, finding));
+            }
+            401 => {
+                for (username, password) in TOMCAT_CREDENTIALS {
+                    let authorization = format!(
+                        "Basic {}",
+                        base64(format!("{username}:{password}").as_bytes())
+                    );
+                    let headers = [("Authorization", authorization.as_str())];
+                    let response = match client.request("GET", host, path, &headers, b"") {
+                        Ok(response) => response,
+                        Err(_) => continue,
+                    };
+                    if response.status() == 200 || response.status() == 403 {
+                        report.line(format!(
+                            "  {username}:{password} accepted ({})",
+                            response.status()
+                        ));
+                        // A 403 means the credentials are good but the user lacks the role for this app
+                        let finding = format!(
+                            "Tomcat {path} on port {port} accepts the default credentials {username}:{password}"
+                        );
+                        report.findings.push((response.status() == 200, finding));
+                        break;
+                    }
+                }
+            }
+            403 => report.line("  only reachable from localhost or allowed addresses".to_string()),
+            _ => {}
+        }
+    }
+
+    Ok(report)
+}
+
+// Jenkins without authentication hands out a Groovy console, and some allow anyone to sign up
+fn jenkins(client: &HttpClient, host: &str) -> Result<FollowUpReport, Box<dyn Error>> {
+    let mut report = FollowUpReport::default();
+    let port = client.port();
+
+    let home = client.get(host, "/")?;
+    if let Some(version) = home.header("X-Jenkins") {
+        report.line(format!("Version: {version}"));
+    }
+
+    for path in ["/script", "/asynchPeople/", "/signup"] {
+        // One dropped connection shouldn't cost the rest of the paths
+        let response = match client.get(host, path) {
+            Ok(response) => response,
+            Err(e) => {
+                report.line(format!("{path}: no response ({e})"));
+                continue;
+            }
+        };
+        let status = response.status();
+        report.line(format!("{path}: {status}"));
+        if status != 200 {
+            continue;
+        }
+        let finding = match path {
+            "/script" => (
+                true,
+                format!("Jenkins on port {port} gives anonymous users the script console"),
+            ),
+            "/asynchPeople/" => (
+                false,
+                format!(
+                    "Jenkins on port {port} lists its users to anonymous users at /asynchPeople/"
+                ),
+            ),
+            _ => (
+                false,
+                format!("Jenkins on port {port} lets anyone sign up at /signup"),
+            ),
+        };
+        report.findings.push(finding);
+    }
+
+    Ok(report)
+}
+
+// GitLab is mostly interesting for open registration and whatever projects are public
+fn gitlab(client: &HttpClient, host: &str) -> Result<FollowUpReport, Box<dyn Error>> {
+    let mut report = FollowUpReport::default();
+    let port = client.port();
+
+    let sign_up = client.get(host, "/users/sign_up")?;
+    report.line(format!("/users/sign_up: {}", sign_up.status()));
+    if sign_up.status() == 200 && sign_up.text().contains("new_user") {
+        report
+            .findings
+            .push((false, format!("GitLab on port {port} lets anyone register")));
+    }
+
+    let projects = client.get(host, "/api/v4/projects?visibility=public&per_page=100")?;
+    report.line(format!("/api/v4/projects: {}", projects.status()));
+    if projects.status() == 200 {
+        let names: Vec<String> = projects
+            .text()
+            .split("\"path_with_namespace\":\"")
+            .skip(1)
+            .filter_map(|rest| rest.split('"').next())
+            .map(str::to_string)
+            .collect();
+        for name in &names {
+            report.line(format!("  public project: {name}"));
+        }
+        if !names.is_empty() {
+            let finding = format!(
+                "GitLab on port {port} has {} public projects: {}",
+                names.len(),
+                names.join(", ")
+            );
+            report.findings.push((false, finding));
+        }
+    }
+
+    Ok(report)
+}
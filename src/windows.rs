@@ -0,0 +1,98 @@
+use crate::hostnames;
+
+// Ports that suggest the target is a Windows machine worth asking about its names
+pub const WINDOWS_PORTS: [u16; 4] = [139, 389, 445, 3389];
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct WindowsInfo {
+    dns_domain: Option<String>,
+    fqdn: Option<String>,
+    netbios_domain: Option<String>,
+    netbios_name: Option<String>,
+}
+
+impl WindowsInfo {
+    // Pull the host and domain names out of nmap's smb-os-discovery, rdp-ntlm-info and ldap-rootdse output
+    pub fn parse(output: &str) -> WindowsInfo {
+        let mut info = WindowsInfo::default();
+
+        for line in output.lines() {
+            let line = line.trim_start_matches(['|', '_', ' ']);
+            let (key, value) = match line.split_once(':') {
+                Some((key, value)) => (key.trim(), value.trim().trim_end_matches("\\x00")),
+                None => continue,
+            };
+            if value.is_empty() {
+                continue;
+            }
+
+            match key {
+                "NetBIOS computer name" | "NetBIOS_Computer_Name" | "Computer name" => {
+                    info.netbios_name.get_or_insert(value.to_uppercase());
+                }
+                "NetBIOS_Domain_Name" | "Target_Name" | "Workgroup" => {
+                    info.netbios_domain.get_or_insert(value.to_uppercase());
+                }
+                "Domain name" | "DNS_Domain_Name" | "Forest name" | "DNS_Tree_Name" => {
+                    info.dns_domain.get_or_insert(value.to_lowercase());
+                }
+                "FQDN" | "DNS_Computer_Name" | "dnsHostName" => {
+                    info.fqdn.get_or_insert(value.to_lowercase());
+                }
+                "defaultNamingContext" | "rootDomainNamingContext" => {
+                    if let Some(domain) = naming_context_to_domain(value) {
+                        info.dns_domain.get_or_insert(domain);
+                    }
+                }
+                _ => (),
+            }
+        }
+
+        info
+    }
+
+    pub fn dns_domain(&self) -> Option<&String> {
+        self.dns_domain.as_ref()
+    }
+
+    pub fn fqdn(&self) -> Option<&String> {
+        self.fqdn.as_ref()
+    }
+
+    pub fn netbios_domain(&self) -> Option<&String> {
+        self.netbios_domain.as_ref()
+    }
+
+    pub fn netbios_name(&self) -> Option<&String> {
+        self.netbios_name.as_ref()
+    }
+
+    // The names worth putting in /etc/hosts: host.domain and the domain itself
+    pub fn hostnames(&self) -> Vec<String> {
+        let fqdn = match (&self.fqdn, &self.netbios_name, &self.dns_domain) {
+            (Some(fqdn), _, _) => Some(fqdn.clone()),
+            (None, Some(name), Some(domain)) => Some(format!("{}.{domain}", name.to_lowercase())),
+            _ => None,
+        };
+        [fqdn, self.dns_domain.clone()]
+            .into_iter()
+            .flatten()
+            .filter_map(|name| hostnames::clean(&name))
+            .collect()
+    }
+}
+
+// Turn an LDAP naming context like DC=htb,DC=local into htb.local
+pub fn naming_context_to_domain(context: &str) -> Option<String> {
+    let parts: Vec<&str> = context
+        .split(',')
+        .filter_map(|part| {
+            let (key, value) = part.trim().split_once('=')?;
+            key.eq_ignore_ascii_case("DC").then_some(value)
+        })
+        .collect();
+    match parts.is_empty() {
+        true => None,
+        false => Some(parts.join(".").to_lowercase()),
+    }
+}